@@ -0,0 +1,162 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+use alloy::rpc::json_rpc::{Id, RequestPacket, Response, ResponsePacket, SerializedRequest};
+use alloy::transports::{Transport, TransportError, TransportErrorKind, TransportFut};
+use futures::future::join_all;
+use tokio::sync::{mpsc, oneshot};
+use tower::{Service, ServiceExt};
+
+/// Default maximum number of calls per batch, a common limit of RPC providers
+pub const MAX_BATCH_SIZE: usize = 100;
+
+type Pending = (
+    SerializedRequest,
+    oneshot::Sender<Result<Response, TransportError>>,
+);
+
+/// Transport coalescing the calls issued concurrently into a single JSON-RPC batch.
+///
+/// Each call still gets its own response, so one failing call doesn't fail the others.
+/// Explicit batches are forwarded as is.
+#[derive(Debug, Clone)]
+pub struct BatchTransport<T> {
+    inner: T,
+    max_batch_size: usize,
+    queue: mpsc::UnboundedSender<Pending>,
+    /// Taken by the first call, to spawn the worker from within the runtime
+    worker: Arc<Mutex<Option<mpsc::UnboundedReceiver<Pending>>>>,
+}
+
+impl<T: Transport + Clone> BatchTransport<T> {
+    pub fn new(inner: T) -> Self {
+        Self::with_max_batch_size(inner, MAX_BATCH_SIZE)
+    }
+
+    pub fn with_max_batch_size(inner: T, max_batch_size: usize) -> Self {
+        let (queue, worker) = mpsc::unbounded_channel();
+
+        Self {
+            inner,
+            max_batch_size: max_batch_size.max(1),
+            queue,
+            worker: Arc::new(Mutex::new(Some(worker))),
+        }
+    }
+
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    fn spawn_worker(&self) {
+        let Some(mut worker) = self.worker.lock().unwrap().take() else {
+            return;
+        };
+        let inner = self.inner.clone();
+        let max_batch_size = self.max_batch_size;
+
+        tokio::spawn(async move {
+            while let Some(first) = worker.recv().await {
+                // Let the calls polled alongside the first one get queued too
+                tokio::task::yield_now().await;
+
+                let mut batch = vec![first];
+                while batch.len() < max_batch_size {
+                    match worker.try_recv() {
+                        Ok(pending) => batch.push(pending),
+                        Err(_) => break,
+                    }
+                }
+                tokio::spawn(send_batch(inner.clone(), batch));
+            }
+        });
+    }
+}
+
+/// Send the queued calls and hand each response to its caller
+async fn send_batch<T: Transport + Clone>(inner: T, mut batch: Vec<Pending>) {
+    if batch.len() == 1 {
+        let (request, sender) = batch.pop().unwrap();
+        let _ = sender.send(send_single(inner, request).await);
+        return;
+    }
+
+    let requests: Vec<_> = batch.iter().map(|(request, _)| request.clone()).collect();
+    match inner.clone().oneshot(RequestPacket::Batch(requests)).await {
+        Ok(ResponsePacket::Batch(responses)) => {
+            let mut responses: HashMap<Id, Response> = responses
+                .into_iter()
+                .map(|response| (response.id.clone(), response))
+                .collect();
+
+            for (request, sender) in batch {
+                let response = responses.remove(request.id()).ok_or_else(|| {
+                    TransportErrorKind::custom_str(&format!(
+                        "Missing batch response for {} (id {})",
+                        request.method(),
+                        request.id()
+                    ))
+                });
+                let _ = sender.send(response);
+            }
+        }
+        // The batch was rejected as a whole (e.g. batches are unsupported), send the calls one by one
+        Ok(ResponsePacket::Single(_)) => {
+            join_all(batch.into_iter().map(|(request, sender)| {
+                let inner = inner.clone();
+                async move {
+                    let _ = sender.send(send_single(inner, request).await);
+                }
+            }))
+            .await;
+        }
+        Err(e) => {
+            for (_, sender) in batch {
+                let _ = sender.send(Err(TransportErrorKind::custom_str(&e.to_string())));
+            }
+        }
+    }
+}
+
+async fn send_single<T: Transport + Clone>(
+    inner: T,
+    request: SerializedRequest,
+) -> Result<Response, TransportError> {
+    match inner.oneshot(RequestPacket::Single(request)).await? {
+        ResponsePacket::Single(response) => Ok(response),
+        ResponsePacket::Batch(_) => Err(TransportErrorKind::custom_str(
+            "Unexpected batch response to a single call",
+        )),
+    }
+}
+
+impl<T: Transport + Clone> Service<RequestPacket> for BatchTransport<T> {
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, packet: RequestPacket) -> Self::Future {
+        let request = match packet {
+            RequestPacket::Single(request) => request,
+            RequestPacket::Batch(_) => return Box::pin(self.inner.clone().oneshot(packet)),
+        };
+
+        self.spawn_worker();
+        let (sender, receiver) = oneshot::channel();
+        let queued = self.queue.send((request, sender));
+
+        Box::pin(async move {
+            queued.map_err(|_| TransportErrorKind::backend_gone())?;
+            let response = receiver
+                .await
+                .map_err(|_| TransportErrorKind::backend_gone())??;
+
+            Ok(ResponsePacket::Single(response))
+        })
+    }
+}
@@ -1,37 +1,102 @@
 use alloy::consensus::Account;
-use alloy::primitives::{Address, Bytes, B256, U256};
-use alloy::rpc::types::{BlockNumberOrTag, TransactionReceipt};
-use eyre::Result;
+use alloy::primitives::{Address, BlockHash, Bytes, B256, U256};
+use alloy::rpc::types::{
+    AccessListResult, Block, BlockNumberOrTag, EIP1186AccountProofResponse, Filter, Log,
+    Transaction, TransactionReceipt, TransactionRequest,
+};
+
+use serde::Serialize;
+
+use crate::errors::RpcError;
+
+/// Optional methods of an untrusted RPC, those it lacks are worked around
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Capabilities {
+    /// `eth_getAccount`, otherwise accounts are built from `eth_getProof`,
+    /// or from their balance, nonce and code
+    #[serde(rename = "eth_getAccount")]
+    pub get_account: bool,
+    /// `eth_getProof`, without which no state can be verified
+    #[serde(rename = "eth_getProof")]
+    pub get_proof: bool,
+    /// `eth_getBlockReceipts`, otherwise receipts are fetched one by one
+    #[serde(rename = "eth_getBlockReceipts")]
+    pub get_block_receipts: bool,
+}
+
+impl Capabilities {
+    /// The methods supported by both
+    pub fn intersection(self, other: Self) -> Self {
+        Self {
+            get_account: self.get_account && other.get_account,
+            get_proof: self.get_proof && other.get_proof,
+            get_block_receipts: self.get_block_receipts && other.get_block_receipts,
+        }
+    }
+}
+
+/// All methods are assumed supported until found otherwise
+impl Default for Capabilities {
+    fn default() -> Self {
+        Self {
+            get_account: true,
+            get_proof: true,
+            get_block_receipts: true,
+        }
+    }
+}
+
+impl std::fmt::Display for Capabilities {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let support = |supported| {
+            if supported {
+                "supported"
+            } else {
+                "unsupported"
+            }
+        };
+        write!(
+            f,
+            "eth_getAccount: {}, eth_getProof: {}, eth_getBlockReceipts: {}",
+            support(self.get_account),
+            support(self.get_proof),
+            support(self.get_block_receipts)
+        )
+    }
+}
 
 pub trait RpcVerifiableMethods {
     /// RPC method: `eth_getAccount`
+    ///
+    /// Unverified backends lacking `eth_getAccount` and `eth_getProof` can't serve the storage
+    /// root of an account with code, it is then [`B256::ZERO`] (never a valid trie root).
     fn get_account(
         &self,
         address: Address,
         slots: Option<&[B256]>,
         tag: Option<BlockNumberOrTag>,
-    ) -> impl core::future::Future<Output = Result<Account>> + Send;
+    ) -> impl core::future::Future<Output = Result<Account, RpcError>> + Send;
 
     /// RPC method: `eth_getBalance`
     fn get_balance(
         &self,
         address: Address,
         tag: Option<BlockNumberOrTag>,
-    ) -> impl core::future::Future<Output = Result<U256>> + Send;
+    ) -> impl core::future::Future<Output = Result<U256, RpcError>> + Send;
 
     /// RPC method: `eth_getTransactionCount`
     fn get_nonce(
         &self,
         address: Address,
         tag: Option<BlockNumberOrTag>,
-    ) -> impl core::future::Future<Output = Result<u64>> + Send;
+    ) -> impl core::future::Future<Output = Result<u64, RpcError>> + Send;
 
     /// RPC method: `eth_getCode`
     fn get_code(
         &self,
         address: Address,
         tag: Option<BlockNumberOrTag>,
-    ) -> impl core::future::Future<Output = Result<Bytes>> + Send;
+    ) -> impl core::future::Future<Output = Result<Bytes, RpcError>> + Send;
 
     /// RPC method: `eth_getStorageAt`
     fn get_storage_at(
@@ -39,17 +104,138 @@ pub trait RpcVerifiableMethods {
         address: Address,
         slot: B256,
         tag: Option<BlockNumberOrTag>,
-    ) -> impl core::future::Future<Output = Result<U256>> + Send;
+    ) -> impl core::future::Future<Output = Result<U256, RpcError>> + Send;
 
     /// RPC method: `eth_getTransactionReceipt`
     fn get_transaction_receipt(
         &self,
         tx_hash: B256,
-    ) -> impl core::future::Future<Output = Result<TransactionReceipt>> + Send;
+    ) -> impl core::future::Future<Output = Result<TransactionReceipt, RpcError>> + Send;
 
     /// RPC method: `eth_getBlockReceipts`
     fn get_block_receipts(
         &self,
         tag: Option<BlockNumberOrTag>,
-    ) -> impl core::future::Future<Output = Result<Vec<TransactionReceipt>>> + Send;
+    ) -> impl core::future::Future<Output = Result<Vec<TransactionReceipt>, RpcError>> + Send;
+
+    /// RPC method: `eth_getBlockByNumber`
+    fn get_block_by_number(
+        &self,
+        tag: Option<BlockNumberOrTag>,
+        full: bool,
+    ) -> impl core::future::Future<Output = Result<Block, RpcError>> + Send;
+
+    /// RPC method: `eth_getBlockByHash`
+    fn get_block_by_hash(
+        &self,
+        hash: BlockHash,
+        full: bool,
+    ) -> impl core::future::Future<Output = Result<Block, RpcError>> + Send;
+
+    /// RPC method: `eth_getTransactionByHash`
+    fn get_transaction_by_hash(
+        &self,
+        tx_hash: B256,
+    ) -> impl core::future::Future<Output = Result<Transaction, RpcError>> + Send;
+
+    /// RPC method: `eth_getTransactionByBlockNumberAndIndex`
+    fn get_transaction_by_block_number_and_index(
+        &self,
+        tag: Option<BlockNumberOrTag>,
+        index: u64,
+    ) -> impl core::future::Future<Output = Result<Transaction, RpcError>> + Send;
+
+    /// RPC method: `eth_getTransactionByBlockHashAndIndex`
+    fn get_transaction_by_block_hash_and_index(
+        &self,
+        hash: BlockHash,
+        index: u64,
+    ) -> impl core::future::Future<Output = Result<Transaction, RpcError>> + Send;
+
+    /// RPC method: `eth_getLogs`
+    fn get_logs(
+        &self,
+        filter: &Filter,
+    ) -> impl core::future::Future<Output = Result<Vec<Log>, RpcError>> + Send;
+
+    /// RPC method: `eth_call`
+    fn call(
+        &self,
+        request: &TransactionRequest,
+        tag: Option<BlockNumberOrTag>,
+    ) -> impl core::future::Future<Output = Result<Bytes, RpcError>> + Send;
+
+    /// RPC method: `eth_estimateGas`
+    fn estimate_gas(
+        &self,
+        request: &TransactionRequest,
+        tag: Option<BlockNumberOrTag>,
+    ) -> impl core::future::Future<Output = Result<u64, RpcError>> + Send;
+
+    /// RPC method: `eth_createAccessList`
+    fn create_access_list(
+        &self,
+        request: &TransactionRequest,
+        tag: Option<BlockNumberOrTag>,
+    ) -> impl core::future::Future<Output = Result<AccessListResult, RpcError>> + Send;
+}
+
+/// Untrusted data source that [`crate::verified_rpc_client::VerifiedRpcClient`] verifies against.
+///
+/// Besides the unverified [`RpcVerifiableMethods`] (used for code, receipts and blocks),
+/// a backend must serve the proofs and blocks required for verification.
+pub trait ExecutionRpc: RpcVerifiableMethods + Send + Sync {
+    /// RPC method: `eth_getProof`
+    fn get_proof(
+        &self,
+        address: Address,
+        slots: &[B256],
+        block_number: u64,
+    ) -> impl core::future::Future<Output = Result<EIP1186AccountProofResponse, RpcError>> + Send;
+
+    /// RPC method: `eth_chainId`
+    fn get_chain_id(&self) -> impl core::future::Future<Output = Result<u64, RpcError>> + Send;
+
+    /// RPC method: `eth_sendRawTransaction`
+    fn send_raw_transaction(
+        &self,
+        tx: &Bytes,
+    ) -> impl core::future::Future<Output = Result<B256, RpcError>> + Send;
+
+    /// Resolve the block tag to a block number
+    fn get_block_number(
+        &self,
+        tag: Option<BlockNumberOrTag>,
+    ) -> impl core::future::Future<Output = Result<u64, RpcError>> + Send;
+
+    /// Indexes of the providers behind the backend, best first. A verified request is tried
+    /// on each in turn until one serves valid data.
+    fn ranked_providers(&self) -> Vec<usize> {
+        vec![0]
+    }
+
+    /// Serve all the calls `request` makes to the backend from `provider` alone, so that
+    /// whatever goes wrong with its data is known to come from that provider
+    fn pinned<F>(
+        &self,
+        _provider: usize,
+        request: F,
+    ) -> impl core::future::Future<Output = F::Output> + Send
+    where
+        F: core::future::Future + Send,
+    {
+        request
+    }
+
+    /// Report that the data served by `provider` failed verification
+    fn report_invalid(&self, _provider: usize) {}
+
+    /// Report that `provider` lacked data that another provider then served
+    fn report_missing(&self, _provider: usize) {}
+
+    /// Probe the optional methods of the backend, so that those it lacks are worked around
+    /// from the start
+    fn detect_capabilities(&self) -> impl core::future::Future<Output = Capabilities> + Send {
+        async { Capabilities::default() }
+    }
 }
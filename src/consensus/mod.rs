@@ -0,0 +1,479 @@
+//! Beacon chain light client, used to acquire trusted execution blocks.
+//!
+//! Starting from a weak subjectivity checkpoint, the client follows the sync
+//! committee through light client updates, verifying their BLS signatures, and
+//! trusts the `execution_payload` headers committed to by the beacon blocks.
+//! Ref: https://github.com/ethereum/consensus-specs/blob/dev/specs/altair/light-client/sync-protocol.md
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use alloy::primitives::{b256, BlockHash, FixedBytes, B256};
+use blst::min_pk::{PublicKey, Signature};
+use blst::BLST_ERROR;
+
+use crate::common::ExecutionRpc;
+use crate::consensus::rpc::{ConsensusRpc, HttpConsensusRpc};
+use crate::consensus::ssz::{container_root, is_valid_merkle_branch, TreeHash};
+use crate::consensus::types::{
+    BlsPublicKey, BlsSignature, GenericUpdate, LightClientHeader, SyncCommittee,
+    MAX_EXTRA_DATA_BYTES,
+};
+use crate::errors::ConsensusError;
+use crate::verified_rpc_client::VerifiedRpcClient;
+
+pub mod rpc;
+pub mod ssz;
+#[cfg(test)]
+pub(crate) mod test_utils;
+pub mod types;
+
+pub const SLOTS_PER_EPOCH: u64 = 32;
+pub const EPOCHS_PER_SYNC_COMMITTEE_PERIOD: u64 = 256;
+pub const SYNC_COMMITTEE_SIZE: usize = 512;
+
+/// Max number of updates served by `/eth/v1/beacon/light_client/updates`
+const MAX_REQUEST_LIGHT_CLIENT_UPDATES: u64 = 128;
+const DOMAIN_SYNC_COMMITTEE: [u8; 4] = [7, 0, 0, 0];
+pub(crate) const BLS_DST: &[u8] = b"BLS_SIG_BLS12381G2_XMD:SHA-256_SSWU_RO_POP_";
+
+/// Generalized indices, as `(depth, index)` pairs. Electra grew the beacon state
+/// past 32 fields, adding one level to the state proofs.
+pub(crate) const EXECUTION_PAYLOAD_GINDEX: (usize, usize) = (4, 9);
+pub(crate) const FINALIZED_ROOT_GINDEX: (usize, usize) = (6, 41);
+pub(crate) const CURRENT_SYNC_COMMITTEE_GINDEX: (usize, usize) = (5, 22);
+pub(crate) const NEXT_SYNC_COMMITTEE_GINDEX: (usize, usize) = (5, 23);
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Fork {
+    pub epoch: u64,
+    pub version: FixedBytes<4>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChainConfig {
+    /// Execution chain id, the execution RPC must serve the same chain
+    pub chain_id: u64,
+    pub genesis_time: u64,
+    pub genesis_validators_root: B256,
+    pub seconds_per_slot: u64,
+    pub deneb: Fork,
+    pub electra: Fork,
+    pub fulu: Fork,
+}
+
+impl ChainConfig {
+    pub fn mainnet() -> Self {
+        Self {
+            chain_id: 1,
+            genesis_time: 1606824023,
+            genesis_validators_root: b256!(
+                "4b363db94e286120d76eb905340fdd4e54bfe9f06bf33ff6cf5ad27f511bfe95"
+            ),
+            seconds_per_slot: 12,
+            deneb: Fork {
+                epoch: 269568,
+                version: FixedBytes([4, 0, 0, 0]),
+            },
+            electra: Fork {
+                epoch: 364032,
+                version: FixedBytes([5, 0, 0, 0]),
+            },
+            fulu: Fork {
+                epoch: 411392,
+                version: FixedBytes([6, 0, 0, 0]),
+            },
+        }
+    }
+
+    fn fork_version(&self, slot: u64) -> Result<FixedBytes<4>, ConsensusError> {
+        let epoch = slot / SLOTS_PER_EPOCH;
+        [self.fulu, self.electra, self.deneb]
+            .into_iter()
+            .find(|fork| epoch >= fork.epoch)
+            .map(|fork| fork.version)
+            .ok_or(ConsensusError::UnsupportedFork(slot))
+    }
+
+    /// Root signed by the sync committee for the attested beacon block `object_root`
+    pub(crate) fn sync_committee_signing_root(
+        &self,
+        object_root: &B256,
+        signature_slot: u64,
+    ) -> Result<B256, ConsensusError> {
+        // The committee signs with the fork of the slot before `signature_slot`
+        let fork_version = self.fork_version(signature_slot.saturating_sub(1))?;
+        let fork_data_root =
+            container_root(&[fork_version.tree_hash_root(), self.genesis_validators_root]);
+
+        let mut domain = B256::ZERO;
+        domain[..4].copy_from_slice(&DOMAIN_SYNC_COMMITTEE);
+        domain[4..].copy_from_slice(&fork_data_root[..28]);
+
+        Ok(container_root(&[*object_root, domain]))
+    }
+
+    /// Depth offset of beacon state proofs at `slot`
+    pub(crate) fn state_depth_offset(&self, slot: u64) -> usize {
+        usize::from(slot / SLOTS_PER_EPOCH >= self.electra.epoch)
+    }
+
+    fn current_slot(&self) -> u64 {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        now.saturating_sub(self.genesis_time) / self.seconds_per_slot
+    }
+}
+
+pub fn sync_committee_period(slot: u64) -> u64 {
+    slot / SLOTS_PER_EPOCH / EPOCHS_PER_SYNC_COMMITTEE_PERIOD
+}
+
+#[derive(Debug, Clone)]
+struct LightClientStore {
+    finalized_header: LightClientHeader,
+    current_sync_committee: SyncCommittee,
+    next_sync_committee: Option<SyncCommittee>,
+    optimistic_header: LightClientHeader,
+    /// Highest participation of the updates of the previous sync committee period
+    previous_max_active_participants: usize,
+    /// Highest participation of the updates of the current sync committee period
+    current_max_active_participants: usize,
+}
+
+pub struct ConsensusClient<R = HttpConsensusRpc> {
+    rpc: R,
+    config: ChainConfig,
+    /// Weak subjectivity checkpoint, the root of a finalized beacon block
+    checkpoint: B256,
+    store: Option<LightClientStore>,
+}
+
+impl ConsensusClient {
+    pub fn new(rpc: &str, checkpoint: B256, config: ChainConfig) -> Self {
+        Self::with_rpc(HttpConsensusRpc::new(rpc), checkpoint, config)
+    }
+}
+
+impl<R: ConsensusRpc> ConsensusClient<R> {
+    pub fn with_rpc(rpc: R, checkpoint: B256, config: ChainConfig) -> Self {
+        Self {
+            rpc,
+            config,
+            checkpoint,
+            store: None,
+        }
+    }
+
+    /// Hash of the execution block of the latest finalized beacon block
+    pub fn finalized_block_hash(&self) -> Option<BlockHash> {
+        self.store
+            .as_ref()
+            .map(|store| store.finalized_header.execution.block_hash)
+    }
+
+    /// Hash of the execution block of the latest beacon block attested by the sync committee
+    pub fn optimistic_block_hash(&self) -> Option<BlockHash> {
+        self.store
+            .as_ref()
+            .map(|store| store.optimistic_header.execution.block_hash)
+    }
+
+    /// Initialize the store from the checkpoint
+    pub async fn bootstrap(&mut self) -> Result<(), ConsensusError> {
+        let bootstrap = self.rpc.get_bootstrap(self.checkpoint).await?;
+
+        let header_root = bootstrap.header.beacon.tree_hash_root();
+        if header_root != self.checkpoint {
+            return Err(ConsensusError::InvalidCheckpoint {
+                expected: self.checkpoint,
+                got: header_root,
+            });
+        }
+
+        self.verify_header(&bootstrap.header)?;
+        self.verify_state_branch(
+            &bootstrap.current_sync_committee.tree_hash_root(),
+            &bootstrap.current_sync_committee_branch,
+            CURRENT_SYNC_COMMITTEE_GINDEX,
+            &bootstrap.header,
+            "current sync committee",
+        )?;
+
+        self.store = Some(LightClientStore {
+            finalized_header: bootstrap.header.clone(),
+            current_sync_committee: bootstrap.current_sync_committee,
+            next_sync_committee: None,
+            optimistic_header: bootstrap.header,
+            previous_max_active_participants: 0,
+            current_max_active_participants: 0,
+        });
+
+        Ok(())
+    }
+
+    /// Bootstrap if needed, catch up with the sync committee periods and then
+    /// apply the latest finality and optimistic updates.
+    pub async fn sync(&mut self) -> Result<(), ConsensusError> {
+        if self.store.is_none() {
+            self.bootstrap().await?;
+        }
+
+        let period = sync_committee_period(self.store()?.finalized_header.beacon.slot);
+        let updates = self
+            .rpc
+            .get_updates(period, MAX_REQUEST_LIGHT_CLIENT_UPDATES)
+            .await?;
+        for update in updates {
+            self.process_update(update.into())?;
+        }
+
+        self.advance().await
+    }
+
+    /// Apply the latest finality and optimistic updates
+    pub async fn advance(&mut self) -> Result<(), ConsensusError> {
+        let finality_update = self.rpc.get_finality_update().await?;
+        self.process_update(finality_update.into())?;
+
+        let optimistic_update = self.rpc.get_optimistic_update().await?;
+        self.process_update(optimistic_update.into())
+    }
+
+    /// Sync and add the finalized and optimistic execution blocks to the trusted
+    /// blocks of `client`, the former being its finalized block and the latter its head.
+    /// Trusted blocks that are not their ancestors are dropped.
+    pub async fn sync_trusted_blocks<B: ExecutionRpc>(
+        &mut self,
+        client: &VerifiedRpcClient<B>,
+    ) -> Result<(), ConsensusError> {
+        self.sync().await?;
+
+        if let Some(hash) = self.finalized_block_hash() {
+            client
+                .retry_invalid(|| client.trust_finalized_block(hash))
+                .await?;
+        }
+        if let Some(hash) = self.optimistic_block_hash() {
+            client.retry_invalid(|| client.trust_head(hash)).await?;
+        }
+
+        Ok(())
+    }
+
+    fn store(&self) -> Result<&LightClientStore, ConsensusError> {
+        self.store.as_ref().ok_or(ConsensusError::NotBootstrapped)
+    }
+
+    fn process_update(&mut self, update: GenericUpdate) -> Result<(), ConsensusError> {
+        self.verify_update(&update)?;
+        self.apply_update(update)
+    }
+
+    /// Ref: https://github.com/ethereum/consensus-specs/blob/dev/specs/altair/light-client/sync-protocol.md#validate_light_client_update
+    fn verify_update(&self, update: &GenericUpdate) -> Result<(), ConsensusError> {
+        let store = self.store()?;
+
+        if update.sync_aggregate.sync_committee_bits.len() != SYNC_COMMITTEE_SIZE / 8 {
+            return Err(ConsensusError::InvalidUpdate("invalid sync committee bits"));
+        }
+        let participants = update.sync_aggregate.participants();
+        if participants.is_empty() {
+            return Err(ConsensusError::InsufficientParticipation {
+                participants: 0,
+                required: 1,
+            });
+        }
+
+        let attested = &update.attested_header;
+        let finalized_slot = update
+            .finalized_header
+            .as_ref()
+            .map_or(0, |(header, _)| header.beacon.slot);
+        if update.signature_slot <= attested.beacon.slot || attested.beacon.slot < finalized_slot {
+            return Err(ConsensusError::InvalidUpdate("slots out of order"));
+        }
+        if update.signature_slot > self.config.current_slot() {
+            return Err(ConsensusError::InvalidUpdate(
+                "signature slot in the future",
+            ));
+        }
+
+        let store_period = sync_committee_period(store.finalized_header.beacon.slot);
+        let update_period = sync_committee_period(update.signature_slot);
+        let sync_committee = match &store.next_sync_committee {
+            _ if update_period == store_period => &store.current_sync_committee,
+            Some(next) if update_period == store_period + 1 => next,
+            _ => {
+                return Err(ConsensusError::InvalidPeriod {
+                    store_period,
+                    update_period,
+                })
+            }
+        };
+
+        self.verify_header(attested)?;
+
+        if let Some((finalized, branch)) = &update.finalized_header {
+            self.verify_header(finalized)?;
+            self.verify_state_branch(
+                &finalized.beacon.tree_hash_root(),
+                branch,
+                FINALIZED_ROOT_GINDEX,
+                attested,
+                "finality",
+            )?;
+        }
+
+        if let Some((next_sync_committee, branch)) = &update.next_sync_committee {
+            self.verify_state_branch(
+                &next_sync_committee.tree_hash_root(),
+                branch,
+                NEXT_SYNC_COMMITTEE_GINDEX,
+                attested,
+                "next sync committee",
+            )?;
+        }
+
+        let pubkeys = participants
+            .iter()
+            .map(|&i| &sync_committee.pubkeys[i])
+            .collect::<Vec<_>>();
+        self.verify_sync_committee_signature(
+            &pubkeys,
+            &attested.beacon.tree_hash_root(),
+            &update.sync_aggregate.sync_committee_signature,
+            update.signature_slot,
+        )
+    }
+
+    /// Ref: https://github.com/ethereum/consensus-specs/blob/dev/specs/altair/light-client/sync-protocol.md#apply_light_client_update
+    fn apply_update(&mut self, update: GenericUpdate) -> Result<(), ConsensusError> {
+        let store = self.store.as_mut().ok_or(ConsensusError::NotBootstrapped)?;
+        let store_period = sync_committee_period(store.finalized_header.beacon.slot);
+        let attested_period = sync_committee_period(update.attested_header.beacon.slot);
+
+        // Finality and sync committee changes need a supermajority, the optimistic header
+        // more than half of the highest participation seen over the last two periods
+        let participants = update.sync_aggregate.participants().len();
+        store.current_max_active_participants =
+            store.current_max_active_participants.max(participants);
+        let safety_threshold = store
+            .previous_max_active_participants
+            .max(store.current_max_active_participants)
+            / 2;
+        if participants <= safety_threshold {
+            tracing::debug!(
+                "ignoring the optimistic header of an update signed by {participants} participants only"
+            );
+        } else if update.attested_header.beacon.slot > store.optimistic_header.beacon.slot {
+            store.optimistic_header = update.attested_header.clone();
+        }
+
+        let supermajority = participants * 3 >= SYNC_COMMITTEE_SIZE * 2;
+        if !supermajority && update.finalized_header.is_some() {
+            tracing::debug!(
+                "ignoring the finality of an update signed by {participants} participants only"
+            );
+        }
+
+        if let Some((finalized, _)) = update.finalized_header.filter(|_| supermajority) {
+            let finalized_period = sync_committee_period(finalized.beacon.slot);
+
+            if store.next_sync_committee.is_none() {
+                if finalized_period == store_period && attested_period == store_period {
+                    store.next_sync_committee = update.next_sync_committee.map(|(c, _)| c);
+                }
+            } else if finalized_period == store_period + 1 {
+                store.current_sync_committee = store.next_sync_committee.take().unwrap();
+                store.previous_max_active_participants = store.current_max_active_participants;
+                store.current_max_active_participants = 0;
+                if attested_period == finalized_period {
+                    store.next_sync_committee = update.next_sync_committee.map(|(c, _)| c);
+                }
+            }
+
+            if finalized.beacon.slot > store.finalized_header.beacon.slot {
+                store.finalized_header = finalized;
+            }
+        }
+
+        if store.finalized_header.beacon.slot > store.optimistic_header.beacon.slot {
+            store.optimistic_header = store.finalized_header.clone();
+        }
+
+        Ok(())
+    }
+
+    /// Verify the execution payload header against the beacon block body
+    fn verify_header(&self, header: &LightClientHeader) -> Result<(), ConsensusError> {
+        // Fails for pre-Deneb headers, whose execution payload has different fields
+        self.config.fork_version(header.beacon.slot)?;
+        // Bounded by the SSZ type, longer data can't be merkleized
+        if header.execution.extra_data.len() > MAX_EXTRA_DATA_BYTES {
+            return Err(ConsensusError::ExtraDataTooLong(
+                header.execution.extra_data.len(),
+            ));
+        }
+
+        let (depth, index) = EXECUTION_PAYLOAD_GINDEX;
+        if !is_valid_merkle_branch(
+            &header.execution.tree_hash_root(),
+            &header.execution_branch,
+            depth,
+            index,
+            &header.beacon.body_root,
+        ) {
+            return Err(ConsensusError::InvalidMerkleBranch("execution payload"));
+        }
+
+        Ok(())
+    }
+
+    /// Verify a field of the beacon state of `header`
+    fn verify_state_branch(
+        &self,
+        leaf: &B256,
+        branch: &[B256],
+        (depth, index): (usize, usize),
+        header: &LightClientHeader,
+        name: &'static str,
+    ) -> Result<(), ConsensusError> {
+        let depth = depth + self.config.state_depth_offset(header.beacon.slot);
+        if !is_valid_merkle_branch(leaf, branch, depth, index, &header.beacon.state_root) {
+            return Err(ConsensusError::InvalidMerkleBranch(name));
+        }
+
+        Ok(())
+    }
+
+    fn verify_sync_committee_signature(
+        &self,
+        pubkeys: &[&BlsPublicKey],
+        attested_root: &B256,
+        signature: &BlsSignature,
+        signature_slot: u64,
+    ) -> Result<(), ConsensusError> {
+        let signing_root = self
+            .config
+            .sync_committee_signing_root(attested_root, signature_slot)?;
+
+        let pubkeys = pubkeys
+            .iter()
+            .map(|pubkey| PublicKey::from_bytes(pubkey.as_slice()))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| ConsensusError::InvalidSignature)?;
+        let pubkeys = pubkeys.iter().collect::<Vec<_>>();
+        let signature = Signature::from_bytes(signature.as_slice())
+            .map_err(|_| ConsensusError::InvalidSignature)?;
+
+        let result =
+            signature.fast_aggregate_verify(true, signing_root.as_slice(), BLS_DST, &pubkeys);
+        if result != BLST_ERROR::BLST_SUCCESS {
+            return Err(ConsensusError::InvalidSignature);
+        }
+
+        Ok(())
+    }
+}
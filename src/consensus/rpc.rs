@@ -0,0 +1,100 @@
+use alloy::primitives::B256;
+use reqwest::Client;
+use serde::de::DeserializeOwned;
+
+use crate::consensus::types::{
+    Bootstrap, FinalityUpdate, OptimisticUpdate, Update, VersionedResponse,
+};
+use crate::errors::ConsensusError;
+
+/// Untrusted beacon API serving the light client endpoints.
+pub trait ConsensusRpc: Send + Sync {
+    /// `GET /eth/v1/beacon/light_client/bootstrap/{block_root}`
+    fn get_bootstrap(
+        &self,
+        block_root: B256,
+    ) -> impl core::future::Future<Output = Result<Bootstrap, ConsensusError>> + Send;
+
+    /// `GET /eth/v1/beacon/light_client/updates?start_period={period}&count={count}`
+    fn get_updates(
+        &self,
+        period: u64,
+        count: u64,
+    ) -> impl core::future::Future<Output = Result<Vec<Update>, ConsensusError>> + Send;
+
+    /// `GET /eth/v1/beacon/light_client/finality_update`
+    fn get_finality_update(
+        &self,
+    ) -> impl core::future::Future<Output = Result<FinalityUpdate, ConsensusError>> + Send;
+
+    /// `GET /eth/v1/beacon/light_client/optimistic_update`
+    fn get_optimistic_update(
+        &self,
+    ) -> impl core::future::Future<Output = Result<OptimisticUpdate, ConsensusError>> + Send;
+}
+
+pub struct HttpConsensusRpc {
+    url: String,
+    client: Client,
+}
+
+impl HttpConsensusRpc {
+    pub fn new(rpc: &str) -> Self {
+        Self {
+            url: rpc.trim_end_matches('/').to_string(),
+            client: Client::new(),
+        }
+    }
+
+    async fn get<R: DeserializeOwned>(&self, path: &str) -> Result<R, ConsensusError> {
+        let response = self
+            .client
+            .get(format!("{}{path}", self.url))
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|source| ConsensusError::Transport {
+                path: path.to_string(),
+                source,
+            })?;
+
+        response
+            .json()
+            .await
+            .map_err(|source| ConsensusError::Transport {
+                path: path.to_string(),
+                source,
+            })
+    }
+}
+
+impl ConsensusRpc for HttpConsensusRpc {
+    async fn get_bootstrap(&self, block_root: B256) -> Result<Bootstrap, ConsensusError> {
+        let path = format!("/eth/v1/beacon/light_client/bootstrap/{block_root}");
+        let response: VersionedResponse<Bootstrap> = self.get(&path).await?;
+
+        Ok(response.data)
+    }
+
+    async fn get_updates(&self, period: u64, count: u64) -> Result<Vec<Update>, ConsensusError> {
+        let path =
+            format!("/eth/v1/beacon/light_client/updates?start_period={period}&count={count}");
+        let response: Vec<VersionedResponse<Update>> = self.get(&path).await?;
+
+        Ok(response.into_iter().map(|update| update.data).collect())
+    }
+
+    async fn get_finality_update(&self) -> Result<FinalityUpdate, ConsensusError> {
+        let path = "/eth/v1/beacon/light_client/finality_update";
+        let response: VersionedResponse<FinalityUpdate> = self.get(path).await?;
+
+        Ok(response.data)
+    }
+
+    async fn get_optimistic_update(&self) -> Result<OptimisticUpdate, ConsensusError> {
+        let path = "/eth/v1/beacon/light_client/optimistic_update";
+        let response: VersionedResponse<OptimisticUpdate> = self.get(path).await?;
+
+        Ok(response.data)
+    }
+}
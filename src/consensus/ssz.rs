@@ -0,0 +1,113 @@
+//! Minimal SSZ merkleization, enough to hash the light client containers.
+//! Ref: https://github.com/ethereum/consensus-specs/blob/dev/ssz/simple-serialize.md#merkleization
+
+use alloy::primitives::{Address, Bytes, FixedBytes, B256, U256};
+use sha2::{Digest, Sha256};
+
+pub trait TreeHash {
+    fn tree_hash_root(&self) -> B256;
+}
+
+pub fn hash_pair(left: &B256, right: &B256) -> B256 {
+    let mut hasher = Sha256::new();
+    hasher.update(left);
+    hasher.update(right);
+    B256::from_slice(&hasher.finalize())
+}
+
+/// Merkleize `chunks`, padded with zero chunks up to the next power of two of `limit`.
+pub fn merkleize(chunks: &[B256], limit: usize) -> B256 {
+    assert!(chunks.len() <= limit.max(1), "too many chunks to merkleize");
+
+    let width = limit.max(1).next_power_of_two();
+    let mut layer = chunks.to_vec();
+    layer.resize(width, B256::ZERO);
+
+    while layer.len() > 1 {
+        layer = layer
+            .chunks(2)
+            .map(|pair| hash_pair(&pair[0], &pair[1]))
+            .collect();
+    }
+
+    layer[0]
+}
+
+pub fn mix_in_length(root: &B256, length: usize) -> B256 {
+    hash_pair(root, &(length as u64).tree_hash_root())
+}
+
+/// Split `bytes` into 32-byte chunks, right padding the last one.
+pub fn pack_bytes(bytes: &[u8]) -> Vec<B256> {
+    bytes
+        .chunks(32)
+        .map(|chunk| {
+            let mut word = B256::ZERO;
+            word[..chunk.len()].copy_from_slice(chunk);
+            word
+        })
+        .collect()
+}
+
+/// Root of a `ByteList[max_len]`.
+pub fn byte_list_root(bytes: &Bytes, max_len: usize) -> B256 {
+    mix_in_length(
+        &merkleize(&pack_bytes(bytes), max_len.div_ceil(32)),
+        bytes.len(),
+    )
+}
+
+/// Root of a container with the given field roots.
+pub fn container_root(fields: &[B256]) -> B256 {
+    merkleize(fields, fields.len())
+}
+
+/// Ref: https://github.com/ethereum/consensus-specs/blob/dev/specs/phase0/beacon-chain.md#is_valid_merkle_branch
+pub fn is_valid_merkle_branch(
+    leaf: &B256,
+    branch: &[B256],
+    depth: usize,
+    index: usize,
+    root: &B256,
+) -> bool {
+    if branch.len() != depth {
+        return false;
+    }
+
+    let computed = branch.iter().enumerate().fold(*leaf, |node, (i, sibling)| {
+        if (index >> i) & 1 == 1 {
+            hash_pair(sibling, &node)
+        } else {
+            hash_pair(&node, sibling)
+        }
+    });
+
+    computed == *root
+}
+
+impl TreeHash for u64 {
+    fn tree_hash_root(&self) -> B256 {
+        let mut word = B256::ZERO;
+        word[..8].copy_from_slice(&self.to_le_bytes());
+        word
+    }
+}
+
+impl TreeHash for U256 {
+    fn tree_hash_root(&self) -> B256 {
+        B256::from(self.to_le_bytes::<32>())
+    }
+}
+
+impl TreeHash for Address {
+    fn tree_hash_root(&self) -> B256 {
+        pack_bytes(self.as_slice())[0]
+    }
+}
+
+impl<const N: usize> TreeHash for FixedBytes<N> {
+    fn tree_hash_root(&self) -> B256 {
+        let chunks = pack_bytes(self.as_slice());
+        merkleize(&chunks, chunks.len())
+    }
+}
@@ -0,0 +1,356 @@
+//! Beacon chain fixtures committing to the blocks of a [`MockRpc`] chain, served
+//! by a local beacon API stub.
+
+use std::sync::Arc;
+
+use alloy::primitives::{FixedBytes, B256, U256};
+use alloy::rpc::types::Block;
+use axum::extract::{Path, Query, State};
+use axum::routing::get;
+use axum::{Json, Router};
+use blst::min_pk::{AggregatePublicKey, AggregateSignature, SecretKey};
+use serde::Deserialize;
+
+use crate::consensus::ssz::{container_root, hash_pair, merkleize, TreeHash};
+use crate::consensus::types::{
+    BeaconBlockHeader, Bootstrap, ExecutionPayloadHeader, FinalityUpdate, LightClientHeader,
+    OptimisticUpdate, SyncAggregate, SyncCommittee, Update, VersionedResponse,
+};
+use crate::consensus::{
+    sync_committee_period, ChainConfig, Fork, BLS_DST, EPOCHS_PER_SYNC_COMMITTEE_PERIOD,
+    SLOTS_PER_EPOCH, SYNC_COMMITTEE_SIZE,
+};
+use crate::test_utils::{MockRpc, MOCK_CHAIN_ID};
+
+/// Distinct keys per committee, repeated to fill it (aggregation stays cheap)
+const MOCK_KEYS_PER_COMMITTEE: usize = 4;
+
+pub const MOCK_PERIOD: u64 = 10;
+
+/// Deneb from genesis, later forks never activate
+pub fn mock_chain_config() -> ChainConfig {
+    ChainConfig {
+        chain_id: MOCK_CHAIN_ID,
+        genesis_time: 1606824023,
+        genesis_validators_root: B256::repeat_byte(0x42),
+        seconds_per_slot: 12,
+        deneb: Fork {
+            epoch: 0,
+            version: FixedBytes([4, 0, 0, 0]),
+        },
+        electra: Fork {
+            epoch: u64::MAX,
+            version: FixedBytes([5, 0, 0, 0]),
+        },
+        fulu: Fork {
+            epoch: u64::MAX,
+            version: FixedBytes([6, 0, 0, 0]),
+        },
+    }
+}
+
+/// Electra from genesis, its beacon state proofs are one level deeper
+pub fn mock_electra_chain_config() -> ChainConfig {
+    let mut config = mock_chain_config();
+    config.electra.epoch = 0;
+    config
+}
+
+pub struct MockCommittee {
+    keys: Vec<SecretKey>,
+    pub committee: SyncCommittee,
+}
+
+impl MockCommittee {
+    pub fn new(seed: u8) -> Self {
+        let keys = (0..MOCK_KEYS_PER_COMMITTEE as u8)
+            .map(|i| SecretKey::key_gen(&[seed.wrapping_add(i); 32], &[]).unwrap())
+            .collect::<Vec<_>>();
+        let members = (0..SYNC_COMMITTEE_SIZE)
+            .map(|i| keys[i % keys.len()].sk_to_pk())
+            .collect::<Vec<_>>();
+        let aggregate_pubkey =
+            AggregatePublicKey::aggregate(&members.iter().collect::<Vec<_>>(), false).unwrap();
+
+        Self {
+            committee: SyncCommittee {
+                pubkeys: members.iter().map(|pk| FixedBytes(pk.compress())).collect(),
+                aggregate_pubkey: FixedBytes(aggregate_pubkey.to_public_key().compress()),
+            },
+            keys,
+        }
+    }
+
+    /// Signature of the whole committee over `attested`
+    pub fn sign(
+        &self,
+        config: &ChainConfig,
+        attested: &BeaconBlockHeader,
+        signature_slot: u64,
+    ) -> SyncAggregate {
+        self.sign_by(config, attested, signature_slot, SYNC_COMMITTEE_SIZE)
+    }
+
+    /// Signature of the first `participants` committee members over `attested`
+    pub fn sign_by(
+        &self,
+        config: &ChainConfig,
+        attested: &BeaconBlockHeader,
+        signature_slot: u64,
+        participants: usize,
+    ) -> SyncAggregate {
+        let signing_root = config
+            .sync_committee_signing_root(&attested.tree_hash_root(), signature_slot)
+            .unwrap();
+        let signatures = self
+            .keys
+            .iter()
+            .map(|key| key.sign(signing_root.as_slice(), BLS_DST, &[]))
+            .collect::<Vec<_>>();
+        let members = (0..participants)
+            .map(|i| &signatures[i % signatures.len()])
+            .collect::<Vec<_>>();
+        let signature = AggregateSignature::aggregate(&members, false).unwrap();
+
+        let mut bits = vec![0; SYNC_COMMITTEE_SIZE / 8];
+        for i in 0..participants {
+            bits[i / 8] |= 1 << (i % 8);
+        }
+
+        SyncAggregate {
+            sync_committee_bits: bits.into(),
+            sync_committee_signature: FixedBytes(signature.to_signature().compress()),
+        }
+    }
+}
+
+/// Sibling nodes from `leaves[index]` up to the root
+pub fn merkle_proof(leaves: &[B256], mut index: usize) -> Vec<B256> {
+    let mut layer = leaves.to_vec();
+    let mut proof = Vec::new();
+
+    while layer.len() > 1 {
+        proof.push(layer[index ^ 1]);
+        layer = layer
+            .chunks(2)
+            .map(|pair| hash_pair(&pair[0], &pair[1]))
+            .collect();
+        index /= 2;
+    }
+
+    proof
+}
+
+pub fn execution_payload_header(block: &Block) -> ExecutionPayloadHeader {
+    let header = &block.header;
+
+    ExecutionPayloadHeader {
+        parent_hash: header.parent_hash,
+        fee_recipient: header.miner,
+        state_root: header.state_root,
+        receipts_root: header.receipts_root,
+        logs_bloom: header.logs_bloom,
+        prev_randao: header.mix_hash.unwrap_or_default(),
+        block_number: header.number,
+        gas_limit: header.gas_limit,
+        gas_used: header.gas_used,
+        timestamp: header.timestamp,
+        extra_data: header.extra_data.clone(),
+        base_fee_per_gas: U256::from(header.base_fee_per_gas.unwrap_or_default()),
+        block_hash: header.hash,
+        transactions_root: header.transactions_root,
+        withdrawals_root: header.withdrawals_root.unwrap_or_default(),
+        blob_gas_used: header.blob_gas_used.unwrap_or_default(),
+        excess_blob_gas: header.excess_blob_gas.unwrap_or_default(),
+    }
+}
+
+/// A beacon block with the given execution payload, and the leaves of its
+/// beacon state (32 fields, 64 from Electra on).
+fn mock_header(
+    config: &ChainConfig,
+    slot: u64,
+    execution: ExecutionPayloadHeader,
+    finalized: &BeaconBlockHeader,
+    current: &SyncCommittee,
+    next: &SyncCommittee,
+) -> (LightClientHeader, Vec<B256>) {
+    let fields = 32 << config.state_depth_offset(slot);
+    let mut state = (0..fields).map(B256::with_last_byte).collect::<Vec<_>>();
+    // finalized_checkpoint: Checkpoint { epoch, root }
+    state[20] = container_root(&[
+        (finalized.slot / SLOTS_PER_EPOCH).tree_hash_root(),
+        finalized.tree_hash_root(),
+    ]);
+    state[22] = current.tree_hash_root();
+    state[23] = next.tree_hash_root();
+
+    let mut body = (0..16).map(B256::repeat_byte).collect::<Vec<_>>();
+    body[9] = execution.tree_hash_root();
+
+    let header = LightClientHeader {
+        beacon: BeaconBlockHeader {
+            slot,
+            proposer_index: slot % 1024,
+            parent_root: B256::with_last_byte(slot as u8),
+            state_root: merkleize(&state, state.len()),
+            body_root: merkleize(&body, body.len()),
+        },
+        execution,
+        execution_branch: merkle_proof(&body, 9),
+    };
+
+    (header, state)
+}
+
+fn finality_branch(state: &[B256], finalized: &BeaconBlockHeader) -> Vec<B256> {
+    let epoch = (finalized.slot / SLOTS_PER_EPOCH).tree_hash_root();
+    [vec![epoch], merkle_proof(state, 20)].concat()
+}
+
+#[derive(Clone)]
+pub struct MockBeaconChain {
+    pub config: ChainConfig,
+    pub checkpoint: B256,
+    pub bootstrap: Bootstrap,
+    pub updates: Vec<Update>,
+    pub finality_update: FinalityUpdate,
+    pub optimistic_update: OptimisticUpdate,
+}
+
+/// A beacon chain within one sync committee period: the checkpoint commits to
+/// block 1, the finality update to the third to last block, and the optimistic
+/// update to the last block of `execution`.
+pub fn mock_beacon_chain(execution: &MockRpc) -> MockBeaconChain {
+    mock_beacon_chain_with_config(execution, mock_chain_config())
+}
+
+pub fn mock_beacon_chain_with_config(execution: &MockRpc, config: ChainConfig) -> MockBeaconChain {
+    let current = MockCommittee::new(1);
+    let next = MockCommittee::new(101);
+    let payload = |number| execution_payload_header(&execution.blocks[&number].block);
+    let latest = *execution.blocks.keys().last().unwrap();
+    let start = MOCK_PERIOD * EPOCHS_PER_SYNC_COMMITTEE_PERIOD * SLOTS_PER_EPOCH;
+    let header = |slot, number, finalized: &BeaconBlockHeader| {
+        mock_header(
+            &config,
+            slot,
+            payload(number),
+            finalized,
+            &current.committee,
+            &next.committee,
+        )
+    };
+
+    let (checkpoint, state) = header(start + 32, 1, &BeaconBlockHeader::default());
+    let bootstrap = Bootstrap {
+        header: checkpoint.clone(),
+        current_sync_committee: current.committee.clone(),
+        current_sync_committee_branch: merkle_proof(&state, 22),
+    };
+
+    let (finalized, _) = header(start + 64, 2, &checkpoint.beacon);
+    let (attested, state) = header(start + 128, 3, &finalized.beacon);
+    let update = Update {
+        sync_aggregate: current.sign(&config, &attested.beacon, start + 129),
+        signature_slot: start + 129,
+        next_sync_committee: next.committee.clone(),
+        next_sync_committee_branch: merkle_proof(&state, 23),
+        finality_branch: finality_branch(&state, &finalized.beacon),
+        attested_header: attested,
+        finalized_header: finalized,
+    };
+
+    let (finalized, _) = header(start + 160, latest - 2, &checkpoint.beacon);
+    let (attested, state) = header(start + 192, latest - 1, &finalized.beacon);
+    let finality_update = FinalityUpdate {
+        sync_aggregate: current.sign(&config, &attested.beacon, start + 193),
+        signature_slot: start + 193,
+        finality_branch: finality_branch(&state, &finalized.beacon),
+        attested_header: attested,
+        finalized_header: finalized.clone(),
+    };
+
+    let (attested, _) = header(start + 224, latest, &finalized.beacon);
+    let optimistic_update = OptimisticUpdate {
+        sync_aggregate: current.sign(&config, &attested.beacon, start + 225),
+        signature_slot: start + 225,
+        attested_header: attested,
+    };
+
+    MockBeaconChain {
+        checkpoint: checkpoint.beacon.tree_hash_root(),
+        config,
+        bootstrap,
+        updates: vec![update],
+        finality_update,
+        optimistic_update,
+    }
+}
+
+fn versioned<T>(data: T) -> Json<VersionedResponse<T>> {
+    Json(VersionedResponse {
+        version: "deneb".to_string(),
+        data,
+    })
+}
+
+#[derive(Deserialize)]
+struct UpdatesQuery {
+    start_period: u64,
+    count: usize,
+}
+
+/// Serve `chain` on a random local port, returning the beacon API URL.
+/// The stub answers every bootstrap request with the same (checkpoint) bootstrap.
+pub async fn serve_mock_beacon_api(chain: MockBeaconChain) -> String {
+    let router =
+        Router::new()
+            .route(
+                "/eth/v1/beacon/light_client/bootstrap/:block_root",
+                get(
+                    |State(chain): State<Arc<MockBeaconChain>>, Path(_): Path<B256>| async move {
+                        versioned(chain.bootstrap.clone())
+                    },
+                ),
+            )
+            .route(
+                "/eth/v1/beacon/light_client/updates",
+                get(
+                    |State(chain): State<Arc<MockBeaconChain>>,
+                     Query(query): Query<UpdatesQuery>| async move {
+                        let updates = chain
+                            .updates
+                            .iter()
+                            .filter(|update| {
+                                sync_committee_period(update.attested_header.beacon.slot)
+                                    >= query.start_period
+                            })
+                            .take(query.count)
+                            .cloned()
+                            .map(|update| versioned(update).0)
+                            .collect::<Vec<_>>();
+                        Json(updates)
+                    },
+                ),
+            )
+            .route(
+                "/eth/v1/beacon/light_client/finality_update",
+                get(|State(chain): State<Arc<MockBeaconChain>>| async move {
+                    versioned(chain.finality_update.clone())
+                }),
+            )
+            .route(
+                "/eth/v1/beacon/light_client/optimistic_update",
+                get(|State(chain): State<Arc<MockBeaconChain>>| async move {
+                    versioned(chain.optimistic_update.clone())
+                }),
+            )
+            .with_state(Arc::new(chain));
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+
+    url
+}
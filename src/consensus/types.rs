@@ -0,0 +1,251 @@
+//! Light client containers as served by the beacon API.
+//! Ref: https://github.com/ethereum/consensus-specs/blob/dev/specs/deneb/light-client/sync-protocol.md
+
+use alloy::primitives::{Address, Bloom, Bytes, FixedBytes, B256, U256};
+use serde::{Deserialize, Serialize};
+
+use crate::consensus::ssz::{byte_list_root, container_root, merkleize, TreeHash};
+
+pub type BlsPublicKey = FixedBytes<48>;
+pub type BlsSignature = FixedBytes<96>;
+
+pub(crate) const MAX_EXTRA_DATA_BYTES: usize = 32;
+
+/// The beacon API quotes integers, e.g. `"slot": "1"`.
+mod quoted {
+    use std::fmt::Display;
+    use std::str::FromStr;
+
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<T: Display, S: Serializer>(value: &T, s: S) -> Result<S::Ok, S::Error> {
+        s.collect_str(value)
+    }
+
+    pub fn deserialize<'de, T, D>(d: D) -> Result<T, D::Error>
+    where
+        T: FromStr,
+        T::Err: Display,
+        D: Deserializer<'de>,
+    {
+        String::deserialize(d)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// `{"version": "deneb", "data": {...}}` envelope of the light client endpoints.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VersionedResponse<T> {
+    pub version: String,
+    pub data: T,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BeaconBlockHeader {
+    #[serde(with = "quoted")]
+    pub slot: u64,
+    #[serde(with = "quoted")]
+    pub proposer_index: u64,
+    pub parent_root: B256,
+    pub state_root: B256,
+    pub body_root: B256,
+}
+
+impl TreeHash for BeaconBlockHeader {
+    fn tree_hash_root(&self) -> B256 {
+        container_root(&[
+            self.slot.tree_hash_root(),
+            self.proposer_index.tree_hash_root(),
+            self.parent_root,
+            self.state_root,
+            self.body_root,
+        ])
+    }
+}
+
+/// Deneb `ExecutionPayloadHeader`.
+///
+/// Note: `transactions_root` and `withdrawals_root` are SSZ roots and
+/// differ from the roots of the same name in the execution block header.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExecutionPayloadHeader {
+    pub parent_hash: B256,
+    pub fee_recipient: Address,
+    pub state_root: B256,
+    pub receipts_root: B256,
+    pub logs_bloom: Bloom,
+    pub prev_randao: B256,
+    #[serde(with = "quoted")]
+    pub block_number: u64,
+    #[serde(with = "quoted")]
+    pub gas_limit: u64,
+    #[serde(with = "quoted")]
+    pub gas_used: u64,
+    #[serde(with = "quoted")]
+    pub timestamp: u64,
+    pub extra_data: Bytes,
+    #[serde(with = "quoted")]
+    pub base_fee_per_gas: U256,
+    pub block_hash: B256,
+    pub transactions_root: B256,
+    pub withdrawals_root: B256,
+    #[serde(with = "quoted")]
+    pub blob_gas_used: u64,
+    #[serde(with = "quoted")]
+    pub excess_blob_gas: u64,
+}
+
+impl TreeHash for ExecutionPayloadHeader {
+    fn tree_hash_root(&self) -> B256 {
+        container_root(&[
+            self.parent_hash,
+            self.fee_recipient.tree_hash_root(),
+            self.state_root,
+            self.receipts_root,
+            self.logs_bloom.0.tree_hash_root(),
+            self.prev_randao,
+            self.block_number.tree_hash_root(),
+            self.gas_limit.tree_hash_root(),
+            self.gas_used.tree_hash_root(),
+            self.timestamp.tree_hash_root(),
+            byte_list_root(&self.extra_data, MAX_EXTRA_DATA_BYTES),
+            self.base_fee_per_gas.tree_hash_root(),
+            self.block_hash,
+            self.transactions_root,
+            self.withdrawals_root,
+            self.blob_gas_used.tree_hash_root(),
+            self.excess_blob_gas.tree_hash_root(),
+        ])
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LightClientHeader {
+    pub beacon: BeaconBlockHeader,
+    pub execution: ExecutionPayloadHeader,
+    pub execution_branch: Vec<B256>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncCommittee {
+    pub pubkeys: Vec<BlsPublicKey>,
+    pub aggregate_pubkey: BlsPublicKey,
+}
+
+impl TreeHash for SyncCommittee {
+    fn tree_hash_root(&self) -> B256 {
+        let pubkeys = self
+            .pubkeys
+            .iter()
+            .map(TreeHash::tree_hash_root)
+            .collect::<Vec<_>>();
+
+        container_root(&[
+            merkleize(&pubkeys, pubkeys.len()),
+            self.aggregate_pubkey.tree_hash_root(),
+        ])
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncAggregate {
+    /// `Bitvector[SYNC_COMMITTEE_SIZE]`, little-endian bit order
+    pub sync_committee_bits: Bytes,
+    pub sync_committee_signature: BlsSignature,
+}
+
+impl SyncAggregate {
+    /// Indices of the sync committee members that signed
+    pub fn participants(&self) -> Vec<usize> {
+        (0..self.sync_committee_bits.len() * 8)
+            .filter(|i| self.sync_committee_bits[i / 8] >> (i % 8) & 1 == 1)
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Bootstrap {
+    pub header: LightClientHeader,
+    pub current_sync_committee: SyncCommittee,
+    pub current_sync_committee_branch: Vec<B256>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Update {
+    pub attested_header: LightClientHeader,
+    pub next_sync_committee: SyncCommittee,
+    pub next_sync_committee_branch: Vec<B256>,
+    pub finalized_header: LightClientHeader,
+    pub finality_branch: Vec<B256>,
+    pub sync_aggregate: SyncAggregate,
+    #[serde(with = "quoted")]
+    pub signature_slot: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FinalityUpdate {
+    pub attested_header: LightClientHeader,
+    pub finalized_header: LightClientHeader,
+    pub finality_branch: Vec<B256>,
+    pub sync_aggregate: SyncAggregate,
+    #[serde(with = "quoted")]
+    pub signature_slot: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OptimisticUpdate {
+    pub attested_header: LightClientHeader,
+    pub sync_aggregate: SyncAggregate,
+    #[serde(with = "quoted")]
+    pub signature_slot: u64,
+}
+
+/// Common shape of [`Update`], [`FinalityUpdate`] and [`OptimisticUpdate`].
+#[derive(Debug, Clone)]
+pub(crate) struct GenericUpdate {
+    pub attested_header: LightClientHeader,
+    pub next_sync_committee: Option<(SyncCommittee, Vec<B256>)>,
+    pub finalized_header: Option<(LightClientHeader, Vec<B256>)>,
+    pub sync_aggregate: SyncAggregate,
+    pub signature_slot: u64,
+}
+
+impl From<Update> for GenericUpdate {
+    fn from(update: Update) -> Self {
+        Self {
+            attested_header: update.attested_header,
+            next_sync_committee: Some((
+                update.next_sync_committee,
+                update.next_sync_committee_branch,
+            )),
+            finalized_header: Some((update.finalized_header, update.finality_branch)),
+            sync_aggregate: update.sync_aggregate,
+            signature_slot: update.signature_slot,
+        }
+    }
+}
+
+impl From<FinalityUpdate> for GenericUpdate {
+    fn from(update: FinalityUpdate) -> Self {
+        Self {
+            attested_header: update.attested_header,
+            next_sync_committee: None,
+            finalized_header: Some((update.finalized_header, update.finality_branch)),
+            sync_aggregate: update.sync_aggregate,
+            signature_slot: update.signature_slot,
+        }
+    }
+}
+
+impl From<OptimisticUpdate> for GenericUpdate {
+    fn from(update: OptimisticUpdate) -> Self {
+        Self {
+            attested_header: update.attested_header,
+            next_sync_committee: None,
+            finalized_header: None,
+            sync_aggregate: update.sync_aggregate,
+            signature_slot: update.signature_slot,
+        }
+    }
+}
@@ -0,0 +1,237 @@
+use alloy::eips::BlockNumberOrTag;
+use alloy::primitives::{Address, BlockHash, BlockNumber, Bytes, B256};
+use alloy::rpc::json_rpc::ErrorPayload;
+use alloy::transports::TransportError;
+use alloy_trie::proof::ProofVerificationError;
+use thiserror::Error;
+
+/// Data returned by the untrusted RPC does not match the trusted state.
+#[derive(Debug, Error)]
+pub enum VerificationError {
+    #[error("Block {0} is not in trusted list")]
+    UntrustedBlock(BlockNumber),
+
+    #[error("Failed to verify account proof for address {address}: {source}")]
+    InvalidAccountProof {
+        address: Address,
+        source: Box<ProofVerificationError>,
+    },
+
+    #[error("Failed to verify storage proof for address {address} and slot {slot}: {source}")]
+    InvalidStorageProof {
+        address: Address,
+        slot: B256,
+        source: Box<ProofVerificationError>,
+    },
+
+    #[error("Code hash mismatch for address {address}: expected {expected}, got {got}")]
+    CodeHashMismatch {
+        address: Address,
+        expected: B256,
+        got: B256,
+    },
+
+    #[error("Receipts root mismatch for block {block_number}: expected {expected}, got {got}")]
+    ReceiptsRootMismatch {
+        block_number: BlockNumber,
+        expected: B256,
+        got: B256,
+    },
+
+    #[error("Failed to verify receipt for tx hash {tx_hash} in block {block_number}")]
+    InvalidReceipt {
+        tx_hash: B256,
+        block_number: BlockNumber,
+    },
+
+    #[error("Transactions root mismatch for block {block_number}: expected {expected}, got {got}")]
+    TransactionsRootMismatch {
+        block_number: BlockNumber,
+        expected: B256,
+        got: B256,
+    },
+
+    #[error("Failed to verify transaction {tx_hash} in block {block_number}")]
+    InvalidTransaction {
+        tx_hash: B256,
+        block_number: BlockNumber,
+    },
+
+    #[error("Withdrawals root mismatch for block {block_number}: expected {expected}, got {got}")]
+    WithdrawalsRootMismatch {
+        block_number: BlockNumber,
+        expected: B256,
+        got: B256,
+    },
+
+    #[error("Uncles of block {block_number} do not match its ommers hash")]
+    OmmersHashMismatch { block_number: BlockNumber },
+
+    #[error("Invalid header for block {hash}: {reason}")]
+    InvalidHeader { hash: BlockHash, reason: String },
+
+    #[error("Block hash mismatch: expected {expected}, got {got}")]
+    BlockHashMismatch { expected: BlockHash, got: BlockHash },
+
+    #[error("Block {number} ({hash}) is not linked to the trusted chain")]
+    UnlinkedBlock {
+        number: BlockNumber,
+        hash: BlockHash,
+    },
+
+    #[error("Block tag {0} cannot be resolved from trusted blocks")]
+    UnresolvedTag(BlockNumberOrTag),
+}
+
+impl VerificationError {
+    /// Whether the untrusted RPC served invalid data, rather than data for a block that isn't
+    /// trusted (yet), or that the client asked for off the trusted chain
+    pub fn is_invalid_data(&self) -> bool {
+        !matches!(
+            self,
+            Self::UntrustedBlock(_) | Self::UnresolvedTag(_) | Self::UnlinkedBlock { .. }
+        )
+    }
+}
+
+/// Error returned by the [`crate::common::RpcVerifiableMethods`] implementations.
+#[derive(Debug, Error)]
+pub enum RpcError {
+    #[error("Invalid RPC URL {0}")]
+    InvalidUrl(String),
+
+    /// The request to the untrusted RPC failed
+    #[error("Method: {method}, Error: {source}")]
+    Transport {
+        method: String,
+        source: TransportError,
+    },
+
+    /// The untrusted RPC did not answer in time
+    #[error("Method: {method}, Error: timed out")]
+    Timeout { method: String },
+
+    /// The untrusted RPC did not return the requested data
+    #[error("{0}")]
+    MissingData(String),
+
+    /// The request cannot be served as is, regardless of the untrusted RPC
+    #[error("{0}")]
+    InvalidParams(String),
+
+    /// The client was not configured with the chain id its calls are executed with
+    #[error("Chain id is not configured")]
+    UnknownChainId,
+
+    #[error(transparent)]
+    Verification(#[from] VerificationError),
+
+    #[error(transparent)]
+    Evm(#[from] EvmError),
+}
+
+impl RpcError {
+    /// The error response of the untrusted RPC, if it answered with one
+    pub fn as_error_resp(&self) -> Option<&ErrorPayload> {
+        match self {
+            Self::Transport { source, .. } => source.as_error_resp(),
+            _ => None,
+        }
+    }
+
+    /// Whether the untrusted RPC rejected the method itself, as unknown or unsupported
+    pub fn is_unsupported_method(&self) -> bool {
+        let Some(error) = self.as_error_resp() else {
+            return false;
+        };
+
+        // -32601: method not found, -32004: method not supported (EIP-1474).
+        // Some proxies in front of geth keep its wording but not its code.
+        // Looser wording is not enough, e.g. "block ... does not exist" is about the data
+        matches!(error.code, -32601 | -32004)
+            || (error.message.starts_with("the method ")
+                && error.message.ends_with(" does not exist/is not available"))
+    }
+
+    /// Whether another provider may serve the request: the untrusted RPC failed or timed out,
+    /// rather than answering with an error about the request itself (e.g. a rejected
+    /// transaction)
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::Transport { source, .. } => match source.as_error_resp() {
+                // -32002: resource unavailable, -32005: limit exceeded (EIP-1474),
+                // -32603: internal error
+                Some(error) => matches!(error.code, -32002 | -32005 | -32603),
+                None => true,
+            },
+            Self::Timeout { .. } => true,
+            _ => false,
+        }
+    }
+}
+
+/// The local execution of a call against verified state failed.
+#[derive(Debug, Error)]
+pub enum EvmError {
+    /// The call reverted, with the revert data as output
+    #[error("execution reverted")]
+    Reverted(Bytes),
+
+    #[error("execution halted: {0}")]
+    Halted(String),
+
+    /// The call cannot be executed (e.g. insufficient funds, nonce too low)
+    #[error("invalid call: {0}")]
+    InvalidCall(String),
+
+    /// The call reads more state than is loaded for a single request
+    #[error("state limit exceeded: {0}")]
+    StateLimitExceeded(String),
+}
+
+/// Error returned by the beacon chain light client.
+#[derive(Debug, Error)]
+pub enum ConsensusError {
+    /// The request to the untrusted beacon API failed
+    #[error("Request: {path}, Error: {source}")]
+    Transport {
+        path: String,
+        source: reqwest::Error,
+    },
+
+    #[error("Light client is not bootstrapped")]
+    NotBootstrapped,
+
+    #[error("Bootstrap header root {got} does not match checkpoint {expected}")]
+    InvalidCheckpoint { expected: B256, got: B256 },
+
+    #[error("Slot {0} is before the Deneb fork and is not supported")]
+    UnsupportedFork(u64),
+
+    #[error("Execution payload extra data of {0} bytes exceeds the limit")]
+    ExtraDataTooLong(usize),
+
+    #[error("Invalid {0} merkle branch")]
+    InvalidMerkleBranch(&'static str),
+
+    #[error("Invalid light client update: {0}")]
+    InvalidUpdate(&'static str),
+
+    #[error("Update for sync committee period {update_period} cannot be verified at period {store_period}")]
+    InvalidPeriod {
+        store_period: u64,
+        update_period: u64,
+    },
+
+    #[error("Insufficient sync committee participation: {participants} of {required} required")]
+    InsufficientParticipation {
+        participants: usize,
+        required: usize,
+    },
+
+    #[error("Invalid sync committee signature")]
+    InvalidSignature,
+
+    #[error("Failed to trust execution block: {0}")]
+    Execution(#[from] RpcError),
+}
@@ -0,0 +1,482 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::convert::Infallible;
+
+use alloy::consensus::Header as ConsensusHeader;
+use alloy::eips::eip7702::SignedAuthorization;
+use alloy::eips::BlockNumberOrTag;
+use alloy::primitives::{Address, BlockNumber, Bytes, TxKind, B256, U256};
+use alloy::rpc::types::{AccessList, AccessListItem, AccessListResult, TransactionRequest};
+use futures::future::try_join_all;
+use revm::precompile::{PrecompileSpecId, Precompiles};
+use revm::primitives::{
+    AccountInfo, Authorization as RevmAuthorization, AuthorizationList, BlockEnv, Bytecode, CfgEnv,
+    EVMError, Env, ExecutionResult, ResultAndState, SignedAuthorization as RevmSignedAuthorization,
+    SpecId, TxEnv,
+};
+use revm::{Database, Evm};
+
+use crate::common::ExecutionRpc;
+use crate::errors::{EvmError, RpcError};
+use crate::proof::verify_block_header;
+use crate::verified_rpc_client::VerifiedRpcClient;
+
+/// Max number of executions of a call, each loading the state read by the previous one.
+/// Calls reading dependent state (e.g. walking a linked list) need one per step.
+pub const MAX_EXECUTION_ROUNDS: usize = 64;
+
+/// Max number of accounts, storage slots and block hashes loaded for a single request
+pub const MAX_LOADED_STATE: usize = 4_096;
+
+/// Max gas limit of a call, higher limits are lowered to it (as geth's `RPCGasCap`)
+pub const RPC_GAS_CAP: u64 = 50_000_000;
+
+/// State read by the EVM that is not loaded yet
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum StateKey {
+    Account(Address),
+    Slot(Address, U256),
+    BlockHash(BlockNumber),
+}
+
+/// Verified state loaded so far. Reads of anything else are recorded as missing
+/// and answered with empty values, the execution is then repeated once loaded.
+#[derive(Debug, Default)]
+struct ProofDb {
+    accounts: HashMap<Address, Option<AccountInfo>>,
+    storage: HashMap<(Address, U256), U256>,
+    block_hashes: HashMap<BlockNumber, B256>,
+    missing: HashSet<StateKey>,
+}
+
+impl Database for ProofDb {
+    type Error = Infallible;
+
+    fn basic(&mut self, address: Address) -> Result<Option<AccountInfo>, Self::Error> {
+        match self.accounts.get(&address) {
+            Some(info) => Ok(info.clone()),
+            None => {
+                self.missing.insert(StateKey::Account(address));
+                Ok(None)
+            }
+        }
+    }
+
+    fn code_by_hash(&mut self, code_hash: B256) -> Result<Bytecode, Self::Error> {
+        // Code is loaded along with its account, see `basic`
+        let code = self
+            .accounts
+            .values()
+            .flatten()
+            .find(|info| info.code_hash == code_hash)
+            .and_then(|info| info.code.clone());
+
+        Ok(code.unwrap_or_default())
+    }
+
+    fn storage(&mut self, address: Address, index: U256) -> Result<U256, Self::Error> {
+        match self.storage.get(&(address, index)) {
+            Some(value) => Ok(*value),
+            None => {
+                self.missing.insert(StateKey::Slot(address, index));
+                Ok(U256::ZERO)
+            }
+        }
+    }
+
+    fn block_hash(&mut self, number: u64) -> Result<B256, Self::Error> {
+        match self.block_hashes.get(&number) {
+            Some(hash) => Ok(*hash),
+            None => {
+                self.missing.insert(StateKey::BlockHash(number));
+                Ok(B256::ZERO)
+            }
+        }
+    }
+}
+
+/// Executes calls locally on top of a trusted block.
+///
+/// The state the EVM reads is loaded lazily through the [`VerifiedRpcClient`], so every
+/// account, code and storage value is checked against the trusted state root, and the
+/// block context comes from the verified header. The provider only serves proofs.
+pub struct VerifiedEvm<'a, B> {
+    client: &'a VerifiedRpcClient<B>,
+    chain_id: u64,
+    header: ConsensusHeader,
+    spec_id: SpecId,
+    db: ProofDb,
+    /// Number of state keys loaded so far, up to [`MAX_LOADED_STATE`]
+    loaded: usize,
+}
+
+impl<'a, B: ExecutionRpc> VerifiedEvm<'a, B> {
+    /// Prepare the execution on top of the trusted block `tag`
+    pub async fn new(
+        client: &'a VerifiedRpcClient<B>,
+        tag: Option<BlockNumberOrTag>,
+    ) -> Result<Self, RpcError> {
+        let chain_id = client.chain_id()?;
+        let block_number = client.state.resolve_block_number(tag)?;
+        let trusted_block = client.trusted_block(block_number).await?;
+
+        let block = client
+            .backend()
+            .get_block_by_hash(trusted_block.hash, false)
+            .await?;
+        let header = verify_block_header(&block.header, &trusted_block.hash)?;
+        let spec_id = spec_id(&header, chain_id).ok_or_else(|| {
+            RpcError::InvalidParams(format!(
+                "Block {block_number} predates the merge, its fork is only known on mainnet"
+            ))
+        })?;
+
+        Ok(Self {
+            client,
+            chain_id,
+            header,
+            spec_id,
+            db: ProofDb::default(),
+            loaded: 0,
+        })
+    }
+
+    /// Execute `request` without committing its changes, loading the state it reads
+    /// until the execution no longer reads anything missing, in up to
+    /// [`MAX_EXECUTION_ROUNDS`] executions.
+    pub async fn transact(
+        &mut self,
+        request: &TransactionRequest,
+    ) -> Result<ResultAndState, RpcError> {
+        for _ in 0..MAX_EXECUTION_ROUNDS {
+            let result = self.execute(request);
+
+            if self.db.missing.is_empty() {
+                return result.map_err(|e| EvmError::InvalidCall(e.to_string()).into());
+            }
+
+            self.load_missing().await?;
+        }
+
+        Err(EvmError::StateLimitExceeded(format!(
+            "still reading missing state after {MAX_EXECUTION_ROUNDS} executions"
+        ))
+        .into())
+    }
+
+    fn execute(
+        &mut self,
+        request: &TransactionRequest,
+    ) -> Result<ResultAndState, EVMError<Infallible>> {
+        let env = self.env(request)?;
+
+        Evm::builder()
+            .with_db(&mut self.db)
+            .with_env(Box::new(env))
+            .with_spec_id(self.spec_id)
+            .build()
+            .transact()
+    }
+
+    /// The lowest gas limit `request` succeeds with, by binary search between the gas
+    /// it uses and the gas limit it is given (the block gas limit by default).
+    pub async fn estimate_gas(&mut self, request: &TransactionRequest) -> Result<u64, RpcError> {
+        let result = self.transact(request).await?.result;
+        let gas_used = result.gas_used();
+        execution_output(result)?;
+
+        // Refunds are deducted from the gas used, so it can be lower than the gas needed
+        let mut lo = gas_used.saturating_sub(1);
+        let mut hi = self.gas_limit(request);
+        let mut request = request.clone();
+        while lo + 1 < hi {
+            let mid = lo + (hi - lo) / 2;
+            request.gas = Some(mid);
+
+            let success = match self.transact(&request).await {
+                Ok(result) => result.result.is_success(),
+                // e.g. below the intrinsic gas
+                Err(RpcError::Evm(EvmError::InvalidCall(_))) => false,
+                Err(e) => return Err(e),
+            };
+            if success {
+                hi = mid;
+            } else {
+                lo = mid;
+            }
+        }
+
+        Ok(hi)
+    }
+
+    /// The accounts and storage slots `request` accesses, with the gas it uses once
+    /// they are declared. The accesses of each execution are added to the declared list, as
+    /// geth's access list tracer does, until declaring it no longer grows it, in up to
+    /// [`MAX_EXECUTION_ROUNDS`] executions.
+    pub async fn create_access_list(
+        &mut self,
+        request: &TransactionRequest,
+    ) -> Result<AccessListResult, RpcError> {
+        let precompiles = Precompiles::new(PrecompileSpecId::from_spec_id(self.spec_id));
+        // Accessed anyway, declaring them only costs gas (unless their storage is accessed)
+        let excluded = [
+            Some(request.from.unwrap_or_default()),
+            request.to.and_then(|to| to.to().copied()),
+            Some(self.header.beneficiary),
+        ];
+
+        // Only grows, so that calls whose accesses depend on the gas left (e.g. branching on
+        // `GAS`) can't alternate between two lists
+        let mut accessed: BTreeMap<Address, BTreeSet<B256>> = BTreeMap::new();
+        let mut request = request.clone();
+        for _ in 0..MAX_EXECUTION_ROUNDS {
+            let ResultAndState { result, state } = self.transact(&request).await?;
+
+            for (address, account) in state {
+                accessed
+                    .entry(address)
+                    .or_default()
+                    .extend(account.storage.into_keys().map(B256::from));
+            }
+            let access_list = AccessList(
+                accessed
+                    .iter()
+                    .filter(|(address, storage_keys)| {
+                        !storage_keys.is_empty()
+                            || !(excluded.contains(&Some(**address))
+                                || precompiles.contains(address))
+                    })
+                    .map(|(address, storage_keys)| AccessListItem {
+                        address: *address,
+                        storage_keys: storage_keys.iter().copied().collect(),
+                    })
+                    .collect(),
+            );
+
+            if request.access_list.as_ref() == Some(&access_list) {
+                return Ok(AccessListResult {
+                    access_list,
+                    gas_used: U256::from(result.gas_used()),
+                    error: execution_output(result).err().map(|e| e.to_string()),
+                });
+            }
+            request.access_list = Some(access_list);
+        }
+
+        Err(EvmError::StateLimitExceeded(format!(
+            "access list still growing after {MAX_EXECUTION_ROUNDS} executions"
+        ))
+        .into())
+    }
+
+    /// Load the state read by the last execution through the verified client.
+    /// Independent loads run concurrently, so each execution round costs one round trip.
+    async fn load_missing(&mut self) -> Result<(), RpcError> {
+        let tag = Some(BlockNumberOrTag::Number(self.header.number));
+
+        self.loaded += self.db.missing.len();
+        if self.loaded > MAX_LOADED_STATE {
+            return Err(EvmError::StateLimitExceeded(format!(
+                "reading more than {MAX_LOADED_STATE} accounts, storage slots and block hashes"
+            ))
+            .into());
+        }
+
+        // Missing accounts come with their missing slots, in a single proof each, as do the
+        // missing slots of each loaded account
+        let mut accounts: BTreeMap<Address, Vec<B256>> = BTreeMap::new();
+        let mut slots: BTreeMap<Address, Vec<B256>> = BTreeMap::new();
+        let mut block_numbers = Vec::new();
+        for key in std::mem::take(&mut self.db.missing) {
+            match key {
+                StateKey::Account(address) => {
+                    accounts.entry(address).or_default();
+                }
+                StateKey::Slot(address, index) if !self.db.accounts.contains_key(&address) => {
+                    accounts.entry(address).or_default().push(index.into());
+                }
+                StateKey::Slot(address, index) => {
+                    slots.entry(address).or_default().push(index.into());
+                }
+                StateKey::BlockHash(number) => block_numbers.push(number),
+            }
+        }
+        let accounts: Vec<_> = accounts.into_iter().collect();
+
+        let (accounts, values, trusted_blocks) = tokio::try_join!(
+            self.client.get_accounts_with_storage(&accounts, tag),
+            try_join_all(
+                slots
+                    .iter()
+                    .map(|(address, indices)| self.client.get_storage(*address, indices, tag))
+            ),
+            try_join_all(
+                block_numbers
+                    .iter()
+                    .map(|number| self.client.trusted_block(*number))
+            ),
+        )?;
+
+        for verified in accounts {
+            let info = AccountInfo::new(
+                verified.account.balance,
+                verified.account.nonce,
+                verified.account.code_hash,
+                Bytecode::new_raw(verified.code),
+            );
+            // Empty accounts do not exist as far as the EVM is concerned
+            let info = (!info.is_empty()).then_some(info);
+            self.db.accounts.insert(verified.address, info);
+
+            for (slot, value) in verified.storage {
+                self.db
+                    .storage
+                    .insert((verified.address, slot.into()), value);
+            }
+        }
+        for ((address, indices), values) in slots.into_iter().zip(values) {
+            for (index, value) in indices.into_iter().zip(values) {
+                self.db.storage.insert((address, index.into()), value);
+            }
+        }
+        for trusted_block in trusted_blocks {
+            self.db
+                .block_hashes
+                .insert(trusted_block.number, trusted_block.hash);
+        }
+
+        Ok(())
+    }
+
+    /// The gas limit of `request`, the block gas limit by default, capped to [`RPC_GAS_CAP`]
+    fn gas_limit(&self, request: &TransactionRequest) -> u64 {
+        request
+            .gas
+            .unwrap_or(self.header.gas_limit)
+            .min(RPC_GAS_CAP)
+    }
+
+    fn env(&self, request: &TransactionRequest) -> Result<Env, EVMError<Infallible>> {
+        let header = &self.header;
+
+        let mut cfg = CfgEnv::default();
+        cfg.chain_id = self.chain_id;
+        // `eth_call` semantics: the call is neither paid for nor bound by the block
+        cfg.disable_base_fee = true;
+        cfg.disable_block_gas_limit = true;
+        cfg.disable_eip3607 = true;
+
+        let mut block = BlockEnv {
+            number: U256::from(header.number),
+            coinbase: header.beneficiary,
+            timestamp: U256::from(header.timestamp),
+            gas_limit: U256::from(header.gas_limit),
+            basefee: U256::from(header.base_fee_per_gas.unwrap_or_default()),
+            difficulty: header.difficulty,
+            prevrandao: Some(header.mix_hash),
+            blob_excess_gas_and_price: None,
+        };
+        if let Some(excess_blob_gas) = header.excess_blob_gas {
+            block.set_blob_excess_gas_and_price(excess_blob_gas);
+        }
+
+        let tx = TxEnv {
+            caller: request.from.unwrap_or_default(),
+            gas_limit: self.gas_limit(request),
+            gas_price: U256::from(
+                request
+                    .gas_price
+                    .or(request.max_fee_per_gas)
+                    .unwrap_or_default(),
+            ),
+            gas_priority_fee: request.max_priority_fee_per_gas.map(U256::from),
+            transact_to: request.to.unwrap_or(TxKind::Create),
+            value: request.value.unwrap_or_default(),
+            data: request.input.input().cloned().unwrap_or_default(),
+            nonce: request.nonce,
+            chain_id: request.chain_id,
+            access_list: request
+                .access_list
+                .clone()
+                .map(|access_list| access_list.0)
+                .unwrap_or_default(),
+            blob_hashes: request.blob_versioned_hashes.clone().unwrap_or_default(),
+            max_fee_per_blob_gas: request.max_fee_per_blob_gas.map(U256::from),
+            authorization_list: request
+                .authorization_list
+                .as_ref()
+                .map(|list| list.iter().map(revm_authorization).collect())
+                .transpose()?
+                .map(AuthorizationList::Signed),
+        };
+
+        Ok(Env { cfg, block, tx })
+    }
+}
+
+const MAINNET_CHAIN_ID: u64 = 1;
+
+/// Mainnet forks before the merge with their activation block
+const MAINNET_PRE_MERGE_FORKS: [(BlockNumber, SpecId); 14] = [
+    (15_050_000, SpecId::GRAY_GLACIER),
+    (13_773_000, SpecId::ARROW_GLACIER),
+    (12_965_000, SpecId::LONDON),
+    (12_244_000, SpecId::BERLIN),
+    (9_200_000, SpecId::MUIR_GLACIER),
+    (9_069_000, SpecId::ISTANBUL),
+    (7_280_000, SpecId::PETERSBURG),
+    (4_370_000, SpecId::BYZANTIUM),
+    (2_675_000, SpecId::SPURIOUS_DRAGON),
+    (2_463_000, SpecId::TANGERINE),
+    (1_920_000, SpecId::DAO_FORK),
+    (1_150_000, SpecId::HOMESTEAD),
+    (200_000, SpecId::FRONTIER_THAWING),
+    (0, SpecId::FRONTIER),
+];
+
+/// The fork of the block on the chain `chain_id`. Since the merge it is inferred from the
+/// fields its header commits to. Before, most forks only changed the execution rules, so it is
+/// only known from the activation blocks of mainnet: `None` on other chains.
+pub fn spec_id(header: &ConsensusHeader, chain_id: u64) -> Option<SpecId> {
+    if header.requests_hash.is_some() {
+        Some(SpecId::PRAGUE)
+    } else if header.parent_beacon_block_root.is_some() {
+        Some(SpecId::CANCUN)
+    } else if header.withdrawals_root.is_some() {
+        Some(SpecId::SHANGHAI)
+    } else if header.difficulty.is_zero() {
+        Some(SpecId::MERGE)
+    } else if chain_id == MAINNET_CHAIN_ID {
+        MAINNET_PRE_MERGE_FORKS
+            .iter()
+            .find(|(block, _)| header.number >= *block)
+            .map(|(_, spec_id)| *spec_id)
+    } else {
+        None
+    }
+}
+
+/// The signed authorization `auth` as revm takes it, which depends on an older alloy-eip7702.
+/// Its authority is recovered by revm, it is only rejected here if its parity isn't 0 or 1.
+fn revm_authorization(
+    auth: &SignedAuthorization,
+) -> Result<RevmSignedAuthorization, EVMError<Infallible>> {
+    let signature = auth
+        .signature()
+        .map_err(|e| EVMError::Custom(format!("invalid authorization signature: {e}")))?;
+    let authorization = RevmAuthorization {
+        chain_id: U256::from(auth.chain_id),
+        address: auth.address,
+        nonce: auth.nonce,
+    };
+
+    Ok(authorization.into_signed(signature))
+}
+
+/// The output of a successful execution, or why it failed
+pub fn execution_output(result: ExecutionResult) -> Result<Bytes, EvmError> {
+    match result {
+        ExecutionResult::Success { output, .. } => Ok(output.into_data()),
+        ExecutionResult::Revert { output, .. } => Err(EvmError::Reverted(output)),
+        ExecutionResult::Halt { reason, .. } => Err(EvmError::Halted(format!("{reason:?}"))),
+    }
+}
@@ -0,0 +1,376 @@
+use std::future::Future;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::time::Duration;
+
+use alloy::consensus::Account;
+use alloy::primitives::{Address, BlockHash, Bytes, B256, U256};
+use alloy::rpc::types::{
+    AccessListResult, Block, BlockNumberOrTag, EIP1186AccountProofResponse, Filter, Log,
+    Transaction, TransactionReceipt, TransactionRequest,
+};
+use futures::future::join_all;
+
+use crate::common::{Capabilities, ExecutionRpc, RpcVerifiableMethods};
+use crate::errors::RpcError;
+use crate::http_rpc::HttpRpc;
+
+/// Default time a provider has to answer before the request fails over to the next one
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Score gained by a provider for each response, up to [`MAX_SCORE`]
+pub const SUCCESS_REWARD: i64 = 1;
+pub const MAX_SCORE: i64 = 100;
+/// Score lost by a provider for each failed or timed out request, error responses about
+/// the request itself aside
+pub const ERROR_PENALTY: i64 = 10;
+/// Score lost by a provider caught serving data that fails verification,
+/// enough to rank it after any provider that merely fails
+pub const INVALID_DATA_PENALTY: i64 = 1_000;
+
+tokio::task_local! {
+    /// Provider the calls of the current verified request are pinned to
+    static PINNED: usize;
+}
+
+struct Provider<B> {
+    rpc: B,
+    score: AtomicI64,
+}
+
+impl<B> Provider<B> {
+    fn reward(&self) {
+        let _ = self
+            .score
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |score| {
+                Some((score + SUCCESS_REWARD).min(MAX_SCORE))
+            });
+    }
+
+    fn penalize(&self, penalty: i64) {
+        self.score.fetch_sub(penalty, Ordering::Relaxed);
+    }
+}
+
+/// Backend spreading requests over several untrusted providers.
+///
+/// Requests go to the provider with the best score, and fail over to the next one when
+/// a provider errors, times out or lacks the data. The calls of a verified request are
+/// [pinned](ExecutionRpc::pinned) to a single provider instead, which is demoted below all
+/// others if its data fails verification, so it can't keep failing requests.
+pub struct FailoverRpc<B = HttpRpc> {
+    providers: Vec<Provider<B>>,
+    timeout: Duration,
+}
+
+impl FailoverRpc {
+    pub fn new(rpcs: &[&str]) -> Result<Self, RpcError> {
+        let backends = rpcs
+            .iter()
+            .map(|rpc| HttpRpc::new(rpc))
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self::with_backends(backends))
+    }
+}
+
+impl<B: ExecutionRpc> FailoverRpc<B> {
+    /// Fail over between `backends`, ranked in the given order until they get scored.
+    ///
+    /// # Panics
+    ///
+    /// If `backends` is empty.
+    pub fn with_backends(backends: Vec<B>) -> Self {
+        assert!(
+            !backends.is_empty(),
+            "FailoverRpc needs at least one backend"
+        );
+
+        Self {
+            providers: backends
+                .into_iter()
+                .map(|rpc| Provider {
+                    rpc,
+                    score: AtomicI64::new(0),
+                })
+                .collect(),
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// The current score of each backend, in the given order
+    pub fn scores(&self) -> Vec<i64> {
+        self.providers
+            .iter()
+            .map(|provider| provider.score.load(Ordering::Relaxed))
+            .collect()
+    }
+
+    /// Indexes of the providers by decreasing score, ties kept in the given order
+    fn ranked(&self) -> Vec<usize> {
+        let scores = self.scores();
+        let mut ranked: Vec<usize> = (0..scores.len()).collect();
+        ranked.sort_by_key(|&index| std::cmp::Reverse(scores[index]));
+        ranked
+    }
+
+    /// Send the request with `send`, to each provider in turn until one answers in time with
+    /// the data, or only to the provider the current request is pinned to.
+    /// A provider lacking data that the next one has is penalized, as one that fails.
+    /// Any other error (e.g. invalid params, a rejected transaction) is returned as is, unless
+    /// [retryable](RpcError::is_retryable).
+    async fn request<'a, T, Fut>(
+        &'a self,
+        method: &str,
+        send: impl Fn(&'a B) -> Fut,
+    ) -> Result<T, RpcError>
+    where
+        Fut: Future<Output = Result<T, RpcError>>,
+    {
+        let candidates = match PINNED.try_with(|&provider| provider) {
+            Ok(provider) => vec![provider],
+            Err(_) => self.ranked(),
+        };
+
+        let mut missing: Vec<usize> = Vec::new();
+        let mut last_error = None;
+        for index in candidates {
+            let provider = &self.providers[index];
+            let error = match tokio::time::timeout(self.timeout, send(&provider.rpc)).await {
+                Ok(Ok(response)) => {
+                    provider.reward();
+                    for &index in &missing {
+                        self.providers[index].penalize(ERROR_PENALTY);
+                    }
+                    return Ok(response);
+                }
+                Ok(Err(e @ RpcError::MissingData(_))) => {
+                    missing.push(index);
+                    last_error = Some(e);
+                    continue;
+                }
+                Ok(Err(e)) if e.is_retryable() => e,
+                Ok(Err(e)) => return Err(e),
+                Err(_) => RpcError::Timeout {
+                    method: method.to_string(),
+                },
+            };
+
+            tracing::warn!("provider {index} failed: {error}");
+            provider.penalize(ERROR_PENALTY);
+            last_error = Some(error);
+        }
+
+        Err(last_error.expect("FailoverRpc has at least one provider"))
+    }
+}
+
+impl<B: ExecutionRpc> RpcVerifiableMethods for FailoverRpc<B> {
+    async fn get_account(
+        &self,
+        address: Address,
+        slots: Option<&[B256]>,
+        tag: Option<BlockNumberOrTag>,
+    ) -> Result<Account, RpcError> {
+        self.request("eth_getAccount", |rpc| rpc.get_account(address, slots, tag))
+            .await
+    }
+
+    async fn get_balance(
+        &self,
+        address: Address,
+        tag: Option<BlockNumberOrTag>,
+    ) -> Result<U256, RpcError> {
+        self.request("eth_getBalance", |rpc| rpc.get_balance(address, tag))
+            .await
+    }
+
+    async fn get_nonce(
+        &self,
+        address: Address,
+        tag: Option<BlockNumberOrTag>,
+    ) -> Result<u64, RpcError> {
+        self.request("eth_getTransactionCount", |rpc| rpc.get_nonce(address, tag))
+            .await
+    }
+
+    async fn get_code(
+        &self,
+        address: Address,
+        tag: Option<BlockNumberOrTag>,
+    ) -> Result<Bytes, RpcError> {
+        self.request("eth_getCode", |rpc| rpc.get_code(address, tag))
+            .await
+    }
+
+    async fn get_storage_at(
+        &self,
+        address: Address,
+        slot: B256,
+        tag: Option<BlockNumberOrTag>,
+    ) -> Result<U256, RpcError> {
+        self.request("eth_getStorageAt", |rpc| {
+            rpc.get_storage_at(address, slot, tag)
+        })
+        .await
+    }
+
+    async fn get_transaction_receipt(&self, tx_hash: B256) -> Result<TransactionReceipt, RpcError> {
+        self.request("eth_getTransactionReceipt", |rpc| {
+            rpc.get_transaction_receipt(tx_hash)
+        })
+        .await
+    }
+
+    async fn get_block_receipts(
+        &self,
+        tag: Option<BlockNumberOrTag>,
+    ) -> Result<Vec<TransactionReceipt>, RpcError> {
+        self.request("eth_getBlockReceipts", |rpc| rpc.get_block_receipts(tag))
+            .await
+    }
+
+    async fn get_block_by_number(
+        &self,
+        tag: Option<BlockNumberOrTag>,
+        full: bool,
+    ) -> Result<Block, RpcError> {
+        self.request("eth_getBlockByNumber", |rpc| {
+            rpc.get_block_by_number(tag, full)
+        })
+        .await
+    }
+
+    async fn get_block_by_hash(&self, hash: BlockHash, full: bool) -> Result<Block, RpcError> {
+        self.request("eth_getBlockByHash", |rpc| {
+            rpc.get_block_by_hash(hash, full)
+        })
+        .await
+    }
+
+    async fn get_transaction_by_hash(&self, tx_hash: B256) -> Result<Transaction, RpcError> {
+        self.request("eth_getTransactionByHash", |rpc| {
+            rpc.get_transaction_by_hash(tx_hash)
+        })
+        .await
+    }
+
+    async fn get_transaction_by_block_number_and_index(
+        &self,
+        tag: Option<BlockNumberOrTag>,
+        index: u64,
+    ) -> Result<Transaction, RpcError> {
+        self.request("eth_getTransactionByBlockNumberAndIndex", |rpc| {
+            rpc.get_transaction_by_block_number_and_index(tag, index)
+        })
+        .await
+    }
+
+    async fn get_transaction_by_block_hash_and_index(
+        &self,
+        hash: BlockHash,
+        index: u64,
+    ) -> Result<Transaction, RpcError> {
+        self.request("eth_getTransactionByBlockHashAndIndex", |rpc| {
+            rpc.get_transaction_by_block_hash_and_index(hash, index)
+        })
+        .await
+    }
+
+    async fn get_logs(&self, filter: &Filter) -> Result<Vec<Log>, RpcError> {
+        self.request("eth_getLogs", |rpc| rpc.get_logs(filter))
+            .await
+    }
+
+    async fn call(
+        &self,
+        request: &TransactionRequest,
+        tag: Option<BlockNumberOrTag>,
+    ) -> Result<Bytes, RpcError> {
+        self.request("eth_call", |rpc| rpc.call(request, tag)).await
+    }
+
+    async fn estimate_gas(
+        &self,
+        request: &TransactionRequest,
+        tag: Option<BlockNumberOrTag>,
+    ) -> Result<u64, RpcError> {
+        self.request("eth_estimateGas", |rpc| rpc.estimate_gas(request, tag))
+            .await
+    }
+
+    async fn create_access_list(
+        &self,
+        request: &TransactionRequest,
+        tag: Option<BlockNumberOrTag>,
+    ) -> Result<AccessListResult, RpcError> {
+        self.request("eth_createAccessList", |rpc| {
+            rpc.create_access_list(request, tag)
+        })
+        .await
+    }
+}
+
+impl<B: ExecutionRpc> ExecutionRpc for FailoverRpc<B> {
+    async fn get_proof(
+        &self,
+        address: Address,
+        slots: &[B256],
+        block_number: u64,
+    ) -> Result<EIP1186AccountProofResponse, RpcError> {
+        self.request("eth_getProof", |rpc| {
+            rpc.get_proof(address, slots, block_number)
+        })
+        .await
+    }
+
+    async fn get_chain_id(&self) -> Result<u64, RpcError> {
+        self.request("eth_chainId", |rpc| rpc.get_chain_id()).await
+    }
+
+    async fn send_raw_transaction(&self, tx: &Bytes) -> Result<B256, RpcError> {
+        self.request("eth_sendRawTransaction", |rpc| rpc.send_raw_transaction(tx))
+            .await
+    }
+
+    async fn get_block_number(&self, tag: Option<BlockNumberOrTag>) -> Result<u64, RpcError> {
+        self.request("eth_blockNumber", |rpc| rpc.get_block_number(tag))
+            .await
+    }
+
+    fn ranked_providers(&self) -> Vec<usize> {
+        self.ranked()
+    }
+
+    fn pinned<F>(&self, provider: usize, request: F) -> impl Future<Output = F::Output> + Send
+    where
+        F: Future + Send,
+    {
+        PINNED.scope(provider, request)
+    }
+
+    fn report_invalid(&self, provider: usize) {
+        tracing::warn!("provider {provider} served invalid data, demoting it");
+        self.providers[provider].penalize(INVALID_DATA_PENALTY);
+    }
+
+    fn report_missing(&self, provider: usize) {
+        self.providers[provider].penalize(ERROR_PENALTY);
+    }
+
+    /// Only the methods supported by every provider, as any of them may serve a request
+    async fn detect_capabilities(&self) -> Capabilities {
+        join_all(
+            self.providers
+                .iter()
+                .map(|provider| provider.rpc.detect_capabilities()),
+        )
+        .await
+        .into_iter()
+        .fold(Capabilities::default(), Capabilities::intersection)
+    }
+}
@@ -0,0 +1,128 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use alloy::eips::BlockNumberOrTag;
+use alloy::rpc::types::Header;
+use futures::{Stream, StreamExt};
+use tokio::task::JoinHandle;
+
+use crate::common::ExecutionRpc;
+use crate::errors::RpcError;
+use crate::verified_rpc_client::{TrustedBlock, VerifiedRpcClient};
+
+/// Explicit approval of a new head, on top of it being linked to the trusted chain
+pub type TrustCallback = Box<dyn Fn(&Header) -> bool + Send + Sync>;
+
+/// Trust callback approving every head linked to the trusted chain, so that the untrusted
+/// RPC alone picks the chain followed past the starting block (trust-on-first-use)
+pub fn trust_on_first_use(_: &Header) -> bool {
+    true
+}
+
+/// Follows the chain head of the untrusted RPC.
+///
+/// A new head is trusted only once it is linked to a trusted block through its ancestors'
+/// parent hashes, and approved by the trust callback. Linking only proves the head descends
+/// from the trusted chain, not that it is canonical: the trust callback is the trust source.
+pub struct HeadSync<B> {
+    client: Arc<VerifiedRpcClient<B>>,
+    interval: Duration,
+    trust: TrustCallback,
+}
+
+impl<B: ExecutionRpc + 'static> HeadSync<B> {
+    /// Follow the heads approved by `trust`, e.g. checked against another source, or
+    /// [`trust_on_first_use`]
+    pub fn new(
+        client: Arc<VerifiedRpcClient<B>>,
+        interval: Duration,
+        trust: impl Fn(&Header) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            client,
+            interval,
+            trust: Box::new(trust),
+        }
+    }
+
+    /// Fetch the head of the untrusted RPC and trust it if it extends the trusted chain.
+    /// Returns `None` if there is no new head or the trust callback rejected it.
+    pub async fn poll(&self) -> Result<Option<TrustedBlock>, RpcError> {
+        let head = self
+            .client
+            .backend()
+            .get_block_by_number(Some(BlockNumberOrTag::Latest), false)
+            .await?
+            .header;
+
+        self.on_new_head(&head).await
+    }
+
+    /// Trust `head`, as announced by the untrusted RPC, if it extends the trusted chain or
+    /// reorgs its latest blocks.
+    /// Returns `None` if it is not a new head or the trust callback rejected it.
+    pub async fn on_new_head(&self, head: &Header) -> Result<Option<TrustedBlock>, RpcError> {
+        let state = &self.client.state;
+        if state
+            .latest_trusted_block()
+            .is_some_and(|latest| head.number < latest.number)
+            || state
+                .get_trusted_block(head.number)
+                .is_some_and(|trusted| trusted.hash == head.hash)
+        {
+            return Ok(None);
+        }
+        if !(self.trust)(head) {
+            tracing::warn!(
+                "head {} ({}) rejected by trust callback",
+                head.number,
+                head.hash
+            );
+            return Ok(None);
+        }
+
+        // A provider serving a forged ancestor is demoted, the others then link the head
+        self.client
+            .retry_invalid(|| self.client.extend_trusted_chain(head.hash))
+            .await
+            .map(Some)
+    }
+
+    /// Poll for new heads in the background, every `interval`
+    pub fn spawn(self) -> JoinHandle<()> {
+        tokio::spawn(async move { self.poll_forever().await })
+    }
+
+    /// Follow the heads pushed by the untrusted RPC (e.g. a `newHeads` subscription) in the
+    /// background, then poll for them every `interval` once the stream ends
+    pub fn spawn_with_heads(
+        self,
+        mut heads: impl Stream<Item = Header> + Send + Unpin + 'static,
+    ) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            while let Some(head) = heads.next().await {
+                log_synced_head(self.on_new_head(&head).await);
+            }
+            tracing::warn!(
+                "new heads stream ended, polling the head every {:?} instead",
+                self.interval
+            );
+            self.poll_forever().await
+        })
+    }
+
+    async fn poll_forever(&self) {
+        loop {
+            tokio::time::sleep(self.interval).await;
+            log_synced_head(self.poll().await);
+        }
+    }
+}
+
+fn log_synced_head(result: Result<Option<TrustedBlock>, RpcError>) {
+    match result {
+        Ok(Some(head)) => tracing::debug!("trusted new head {}", head.number),
+        Ok(None) => {}
+        Err(e) => tracing::warn!("failed to sync head: {e}"),
+    }
+}
@@ -1,49 +1,126 @@
 // make the following modules public
+pub mod batch_transport;
 pub mod common;
+#[cfg(feature = "consensus")]
+pub mod consensus;
+pub mod errors;
+pub mod evm;
+pub mod failover_rpc;
+pub mod head_sync;
 pub mod http_rpc;
 pub mod proof;
+pub mod provider_rpc;
+pub mod pubsub_rpc;
+pub mod server;
+pub mod subscription;
 pub mod utils;
 pub mod verified_rpc_client;
 
+#[cfg(test)]
+mod test_utils;
+
 #[cfg(test)]
 mod tests {
     use std::str::FromStr;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
 
-    use alloy::primitives::{Address, B256};
-    use alloy::rpc::types::BlockNumberOrTag;
+    use alloy::consensus::constants::EMPTY_ROOT_HASH;
+    use alloy::consensus::{Account, ReceiptEnvelope};
+    use alloy::eips::eip7702::Authorization;
+    use alloy::primitives::{bytes, keccak256, Address, Bytes, TxKind, B256, U256};
+    use alloy::rpc::types::{
+        AccessList, AccessListItem, Block, BlockNumberOrTag, BlockTransactions, Filter, Header,
+        Log, TransactionRequest,
+    };
+    use alloy::signers::local::PrivateKeySigner;
+    use alloy::signers::SignerSync;
+    use revm::primitives::SpecId;
 
-    use crate::common::RpcVerifiableMethods;
+    use crate::common::{Capabilities, ExecutionRpc, RpcVerifiableMethods};
+    use crate::errors::{EvmError, RpcError, VerificationError};
+    use crate::evm;
+    use crate::failover_rpc::{FailoverRpc, ERROR_PENALTY, INVALID_DATA_PENALTY, SUCCESS_REWARD};
+    use crate::head_sync::{trust_on_first_use, HeadSync};
     use crate::http_rpc::HttpRpc;
+    use crate::proof::{
+        proof_to_account, verify_account_proof, verify_block_header, verify_code_hash,
+        verify_storage_proof, verify_transaction,
+    };
+    use crate::pubsub_rpc::PubSubRpc;
+    use crate::server;
+    use crate::subscription::{SubscriptionKind, Subscriptions};
+    use crate::test_utils::{
+        mainnet_absent_account_proof, mainnet_account_proof, mainnet_headers, mock_chain,
+        mock_signer, prague_header, recorded_transactions, MockAccount, MockBlock, MockRpc,
+        MockState, MOCK_CHAIN_ID, MOCK_CONTRACT, TRANSFER_TOPIC,
+    };
     use crate::verified_rpc_client::{TrustedBlock, VerifiedRpcClient};
 
-    const ETHEREUM_RPC_URL: &str = "https://eth.merkle.io";
     const ADDRESS: &str = "0xdeadbeefdeadbeefdeadbeefdeadbeefdeadbeef";
+    const MOCK_CHAIN_LENGTH: u64 = 8;
 
-    async fn setup() -> (HttpRpc, VerifiedRpcClient, TrustedBlock) {
-        let rpc = HttpRpc::new(ETHEREUM_RPC_URL).expect("failed to create HttpRpc");
+    async fn setup() -> (MockRpc, VerifiedRpcClient<MockRpc>, TrustedBlock) {
+        // [testing] deterministic in-memory chain, no network required
+        let rpc = mock_chain(MOCK_CHAIN_LENGTH);
 
-        let mut verified_client =
-            VerifiedRpcClient::new(ETHEREUM_RPC_URL).expect("failed to create VerifiedRpcClient");
+        let verified_client =
+            VerifiedRpcClient::with_backend(rpc.clone()).with_chain_id(MOCK_CHAIN_ID);
 
-        // [testing] fetch latest block and mark it trusted
-        // HTTP call is required bcz public RPC only allows eth_getProof for the latest block
+        // [testing] fetch latest block and mark its hash trusted
         let latest_block = rpc
-            .get_block(BlockNumberOrTag::Latest.into())
+            .get_block_by_number(BlockNumberOrTag::Latest.into(), false)
             .await
             .expect("failed to fetch latest block");
-        println!("latest block number: {:?}", latest_block.header.number);
 
-        let trusted_block = TrustedBlock {
-            number: latest_block.header.number,
-            hash: latest_block.header.hash,
-            state_root: latest_block.header.state_root,
-            receipts_root: latest_block.header.receipts_root,
-        };
-        verified_client.state.add_trusted_blocks(&[trusted_block]);
+        let trusted_block = verified_client
+            .trust_block_hash(latest_block.header.hash)
+            .await
+            .expect("failed to VerifiedRpcClient.trust_block_hash");
 
         (rpc, verified_client, trusted_block)
     }
 
+    /// Expected to pass
+    #[tokio::test]
+    async fn test_verified_rpc_client_trust_block_hash_success() {
+        let (rpc, verified_client, trusted_block) = setup().await;
+
+        assert_eq!(
+            trusted_block,
+            rpc.blocks[&MOCK_CHAIN_LENGTH].trusted_block()
+        );
+        assert_eq!(
+            verified_client.state.get_trusted_block(MOCK_CHAIN_LENGTH),
+            Some(trusted_block)
+        );
+    }
+
+    /// Expected to fail because the served header does not hash to the trusted hash
+    #[tokio::test]
+    async fn test_verified_rpc_client_trust_block_hash_error_block_hash_mismatch() {
+        let mut rpc = mock_chain(MOCK_CHAIN_LENGTH);
+        let hash = rpc.blocks[&MOCK_CHAIN_LENGTH].block.header.hash;
+
+        // [testing] serve a forged state root under the trusted hash
+        rpc.blocks
+            .get_mut(&MOCK_CHAIN_LENGTH)
+            .unwrap()
+            .block
+            .header
+            .state_root = B256::ZERO;
+        let verified_client = VerifiedRpcClient::with_backend(rpc);
+
+        let result = verified_client.trust_block_hash(hash).await;
+
+        assert!(result.is_err_and(|e| matches!(
+            e,
+            RpcError::Verification(VerificationError::BlockHashMismatch { .. })
+        )));
+        assert_eq!(verified_client.state.latest_trusted_block(), None);
+    }
+
     /// Expected to pass
     #[tokio::test]
     async fn test_verified_rpc_client_get_account_success() {
@@ -56,50 +133,309 @@ mod tests {
         let account = rpc
             .get_account(addr, None, tag)
             .await
-            .expect("failed to HttpRpc.get_account");
+            .expect("failed to MockRpc.get_account");
         let verified_account = verified_client
             .get_account(addr, None, tag)
             .await
             .expect("failed to VerifiedRpcClient.get_account");
 
         assert_eq!(account, verified_account);
+        assert!(!verified_account.balance.is_zero());
     }
 
     /// Expected to fail because the requested block number is not available as a trusted block
     #[tokio::test]
     async fn test_verified_rpc_client_get_account_error_untrusted_block() {
-        let verified_client =
-            VerifiedRpcClient::new(ETHEREUM_RPC_URL).expect("failed to create VerifiedRpcClient");
+        let (_, verified_client, _) = setup().await;
 
-        // [testing] request for a block number that is not trusted
-        let tag = Some(BlockNumberOrTag::Number(1));
+        // [testing] request for a block number after the latest trusted block
+        let untrusted_block_number = MOCK_CHAIN_LENGTH + 1;
+        let tag = Some(BlockNumberOrTag::Number(untrusted_block_number));
 
         let addr = Address::from_str(ADDRESS).expect("failed to parse address");
 
         let result = verified_client.get_account(addr, None, tag).await;
 
-        assert!(result.is_err_and(|e| e.to_string() == "Block 1 is not in trusted list"));
+        assert!(result.is_err_and(|e| matches!(
+            e,
+            RpcError::Verification(VerificationError::UntrustedBlock(n)) if n == untrusted_block_number
+        )));
+    }
+
+    /// Expected to fail without overflowing, no block is trusted after the last block number
+    #[tokio::test]
+    async fn test_verified_rpc_client_get_block_error_max_block_number() {
+        let (_, verified_client, _) = setup().await;
+
+        let tag = Some(BlockNumberOrTag::Number(u64::MAX));
+        let result = verified_client.get_block_by_number(tag, false).await;
+
+        assert!(result.is_err_and(|e| matches!(
+            e,
+            RpcError::Verification(VerificationError::UntrustedBlock(u64::MAX))
+        )));
+    }
+
+    /// Expected to pass
+    #[tokio::test]
+    async fn test_verified_rpc_client_get_balance_finalized_success() {
+        let (rpc, verified_client, _) = setup().await;
+        verified_client
+            .state
+            .set_finalized_block(rpc.blocks[&3].trusted_block());
+
+        let addr = Address::from_str(ADDRESS).expect("failed to parse address");
+        let balance = rpc
+            .get_balance(addr, Some(BlockNumberOrTag::Number(3)))
+            .await
+            .expect("failed to MockRpc.get_balance");
+
+        // [testing] safe falls back to finalized
+        for tag in [BlockNumberOrTag::Finalized, BlockNumberOrTag::Safe] {
+            let verified_balance = verified_client
+                .get_balance(addr, Some(tag))
+                .await
+                .expect("failed to VerifiedRpcClient.get_balance");
+            assert_eq!(balance, verified_balance);
+        }
+    }
+
+    /// Expected to fail because no trusted block is finalized or pending
+    #[tokio::test]
+    async fn test_verified_rpc_client_get_balance_error_unresolved_tag() {
+        let (_, verified_client, _) = setup().await;
+
+        let addr = Address::from_str(ADDRESS).expect("failed to parse address");
+
+        for tag in [BlockNumberOrTag::Finalized, BlockNumberOrTag::Pending] {
+            let result = verified_client.get_balance(addr, Some(tag)).await;
+
+            assert!(result.is_err_and(|e| matches!(
+                e,
+                RpcError::Verification(VerificationError::UnresolvedTag(t)) if t == tag
+            )));
+        }
+    }
+
+    /// Expected to pass, a never used address is proven absent
+    #[tokio::test]
+    async fn test_verified_rpc_client_get_account_absent_success() {
+        let (_, verified_client, _) = setup().await;
+
+        let addr = Address::repeat_byte(0x11);
+        let account = verified_client
+            .get_account(addr, Some(&[B256::ZERO]), None)
+            .await
+            .expect("failed to VerifiedRpcClient.get_account");
+        assert_eq!(account, Account::default());
+
+        let code = verified_client
+            .get_code(addr, None)
+            .await
+            .expect("failed to VerifiedRpcClient.get_code");
+        assert!(code.is_empty());
+
+        // as well as an unused slot of an existing contract
+        let value = verified_client
+            .get_storage_at(MOCK_CONTRACT, B256::with_last_byte(1), None)
+            .await
+            .expect("failed to VerifiedRpcClient.get_storage_at");
+        assert_eq!(value, U256::ZERO);
+    }
+
+    /// Expected to fail because the provider hides an existing account
+    #[tokio::test]
+    async fn test_verified_rpc_client_get_account_error_forged_absent() {
+        let (mut rpc, _, trusted_block) = setup().await;
+
+        // [testing] drop the account, it is then served zeroed with an exclusion proof
+        let addr = Address::from_str(ADDRESS).expect("failed to parse address");
+        let state = &mut rpc.blocks.get_mut(&trusted_block.number).unwrap().state;
+        state.accounts.remove(&addr);
+
+        let verified_client = VerifiedRpcClient::with_backend(rpc);
+        verified_client.state.add_trusted_blocks(&[trusted_block]);
+
+        let result = verified_client.get_balance(addr, None).await;
+
+        assert!(result.is_err_and(|e| matches!(
+            e,
+            RpcError::Verification(VerificationError::InvalidAccountProof { .. })
+        )));
+    }
+
+    /// Expected to pass
+    #[tokio::test]
+    async fn test_verified_rpc_client_get_accounts_with_storage_success() {
+        let (rpc, verified_client, trusted_block) = setup().await;
+
+        let signer = mock_signer().address();
+        let absent = Address::repeat_byte(0x11);
+        let slots = vec![B256::ZERO, B256::with_last_byte(1)];
+        let accounts = verified_client
+            .get_accounts_with_storage(
+                &[
+                    (signer, vec![]),
+                    (MOCK_CONTRACT, slots.clone()),
+                    (absent, slots.clone()),
+                ],
+                None,
+            )
+            .await
+            .expect("failed to VerifiedRpcClient.get_accounts_with_storage");
+
+        let state = &rpc.blocks[&trusted_block.number].state;
+        assert_eq!(accounts.len(), 3);
+        assert_eq!(accounts[0].account, state.accounts[&signer].account());
+        assert!(accounts[0].storage.is_empty());
+        assert_eq!(accounts[1].code, state.code(MOCK_CONTRACT));
+        assert_eq!(
+            accounts[1].storage,
+            [
+                (B256::ZERO, U256::from(trusted_block.number)),
+                (B256::with_last_byte(1), U256::ZERO)
+            ]
+            .into_iter()
+            .collect()
+        );
+        assert_eq!(accounts[2].address, absent);
+        assert_eq!(accounts[2].account, Account::default());
+        assert!(accounts[2].storage.values().all(|value| value.is_zero()));
+    }
+
+    /// Expected to fail because one of the accounts is forged
+    #[tokio::test]
+    async fn test_verified_rpc_client_get_accounts_with_storage_error_invalid_account_proof() {
+        let (mut rpc, _, trusted_block) = setup().await;
+
+        // [testing] forge the counter of the contract
+        let state = &mut rpc.blocks.get_mut(&trusted_block.number).unwrap().state;
+        let contract = state.accounts.get_mut(&MOCK_CONTRACT).unwrap();
+        contract.storage.insert(B256::ZERO, U256::from(1337));
+
+        let verified_client = VerifiedRpcClient::with_backend(rpc);
+        verified_client.state.add_trusted_blocks(&[trusted_block]);
+
+        let result = verified_client
+            .get_accounts_with_storage(
+                &[
+                    (mock_signer().address(), vec![]),
+                    (MOCK_CONTRACT, vec![B256::ZERO]),
+                ],
+                None,
+            )
+            .await;
+
+        assert!(result.is_err_and(|e| matches!(
+            e,
+            RpcError::Verification(VerificationError::InvalidAccountProof { .. })
+        )));
+    }
+
+    /// Expected to pass with a single proof request, and no code request for accounts without code
+    #[tokio::test]
+    async fn test_verified_rpc_client_get_account_requests_success() {
+        let (rpc, verified_client, _) = setup().await;
+        rpc.take_requests();
+
+        let signer = mock_signer().address();
+        verified_client
+            .get_account(MOCK_CONTRACT, None, None)
+            .await
+            .expect("failed to VerifiedRpcClient.get_account");
+        assert_eq!(rpc.take_requests(), ["eth_getProof"]);
+
+        let accounts = verified_client
+            .get_accounts_with_storage(&[(signer, vec![]), (MOCK_CONTRACT, vec![])], None)
+            .await
+            .expect("failed to VerifiedRpcClient.get_accounts_with_storage");
+        assert!(accounts[0].code.is_empty());
+        let mut requests = rpc.take_requests();
+        requests.sort();
+        assert_eq!(requests, ["eth_getCode", "eth_getProof", "eth_getProof"]);
+    }
+
+    /// Expected to pass by syncing the ancestors of the trusted block
+    #[tokio::test]
+    async fn test_verified_rpc_client_get_account_ancestor_success() {
+        let (rpc, verified_client, _) = setup().await;
+
+        let tag = Some(BlockNumberOrTag::Number(2));
+
+        let addr = Address::from_str(ADDRESS).expect("failed to parse address");
+
+        let account = rpc
+            .get_account(addr, None, tag)
+            .await
+            .expect("failed to MockRpc.get_account");
+        let verified_account = verified_client
+            .get_account(addr, None, tag)
+            .await
+            .expect("failed to VerifiedRpcClient.get_account");
+
+        assert_eq!(account, verified_account);
+        for number in 2..=MOCK_CHAIN_LENGTH {
+            assert_eq!(
+                verified_client.state.get_trusted_block(number),
+                Some(rpc.blocks[&number].trusted_block())
+            );
+        }
+    }
+
+    /// Expected to fail because an ancestor header is forged
+    #[tokio::test]
+    async fn test_verified_rpc_client_trusted_block_error_forged_ancestor() {
+        let (mut rpc, _, trusted_block) = setup().await;
+
+        // [testing] serve a forged state root for an ancestor of the trusted block
+        rpc.blocks.get_mut(&5).unwrap().block.header.state_root = B256::ZERO;
+        let verified_client = VerifiedRpcClient::with_backend(rpc);
+        verified_client.state.add_trusted_blocks(&[trusted_block]);
+
+        let result = verified_client.trusted_block(2).await;
+
+        assert!(result.is_err_and(|e| matches!(
+            e,
+            RpcError::Verification(VerificationError::BlockHashMismatch { .. })
+        )));
+        assert_eq!(verified_client.state.get_trusted_block(5), None);
+    }
+
+    /// Expected to fail because an ancestor is linked to a block that is not numbered as its parent
+    #[tokio::test]
+    async fn test_verified_rpc_client_trusted_block_error_skipped_ancestor() {
+        let mut rpc = mock_chain(MOCK_CHAIN_LENGTH);
+        // [testing] link block 5 to block 3, skipping block 4
+        rpc.relink(5, 3);
+        let verified_client = VerifiedRpcClient::with_backend(rpc.clone());
+        let trusted_block = rpc.blocks[&MOCK_CHAIN_LENGTH].trusted_block();
+        verified_client.state.add_trusted_blocks(&[trusted_block]);
+
+        let result = verified_client.trusted_block(4).await;
+
+        assert!(result.is_err_and(|e| matches!(
+            e,
+            RpcError::Verification(VerificationError::InvalidHeader { .. })
+        )));
+        assert_eq!(verified_client.state.get_trusted_block(4), None);
     }
 
     /// Expected to fail because the provided state root is invalid
     #[tokio::test]
     async fn test_verified_rpc_client_get_account_error_invalid_state_root() {
-        let rpc = HttpRpc::new(ETHEREUM_RPC_URL).expect("failed to create HttpRpc");
+        let rpc = mock_chain(MOCK_CHAIN_LENGTH);
 
-        let mut verified_client =
-            VerifiedRpcClient::new(ETHEREUM_RPC_URL).expect("failed to create VerifiedRpcClient");
+        let verified_client = VerifiedRpcClient::with_backend(rpc.clone());
 
         // [testing] fetch latest block and mark it trusted but with an invalid state root
         let latest_block_number = rpc
             .get_block_number(BlockNumberOrTag::Latest.into())
             .await
             .expect("failed to fetch latest block number");
-        println!("latest block number: {:?}", latest_block_number);
         let trusted_block = TrustedBlock {
             number: latest_block_number,
-            hash: B256::ZERO,
             state_root: B256::ZERO,
-            receipts_root: B256::ZERO,
+            ..rpc.blocks[&latest_block_number].trusted_block()
         };
 
         verified_client.state.add_trusted_blocks(&[trusted_block]);
@@ -109,7 +445,35 @@ mod tests {
 
         let result = verified_client.get_account(addr, None, tag).await;
 
-        assert!(result.is_err_and(|e| e.to_string().starts_with("Failed to verify account proof")));
+        assert!(result.is_err_and(|e| matches!(
+            e,
+            RpcError::Verification(VerificationError::InvalidAccountProof { .. })
+        )));
+    }
+
+    /// Expected to pass
+    #[tokio::test]
+    async fn test_verified_rpc_client_get_code_and_storage_at_success() {
+        let (rpc, verified_client, trusted_block) = setup().await;
+
+        let tag = Some(BlockNumberOrTag::Number(trusted_block.number));
+
+        let code = verified_client
+            .get_code(MOCK_CONTRACT, tag)
+            .await
+            .expect("failed to VerifiedRpcClient.get_code");
+        assert_eq!(
+            code,
+            rpc.get_code(MOCK_CONTRACT, tag)
+                .await
+                .expect("failed to MockRpc.get_code")
+        );
+
+        let value = verified_client
+            .get_storage_at(MOCK_CONTRACT, B256::ZERO, tag)
+            .await
+            .expect("failed to VerifiedRpcClient.get_storage_at");
+        assert_eq!(value, U256::from(trusted_block.number));
     }
 
     /// Expected to pass
@@ -119,7 +483,10 @@ mod tests {
 
         let tag = Some(BlockNumberOrTag::Number(trusted_block.number));
 
-        let block = rpc.get_block(tag).await.expect("failed to fetch block");
+        let block = rpc
+            .get_block_by_number(tag, false)
+            .await
+            .expect("failed to fetch block");
 
         let tx_hash = block
             .transactions
@@ -157,4 +524,2535 @@ mod tests {
 
         assert_eq!(receipts, verified_receipts);
     }
+
+    /// Expected to fail because the provider tampered with a receipt
+    #[tokio::test]
+    async fn test_verified_rpc_client_get_block_receipts_error_receipts_root_mismatch() {
+        let (mut rpc, _, trusted_block) = setup().await;
+
+        // [testing] drop the logs of the contract call
+        let receipts = &mut rpc.blocks.get_mut(&trusted_block.number).unwrap().receipts;
+        let receipt = &mut receipts[1];
+        if let ReceiptEnvelope::Legacy(receipt) = &mut receipt.inner {
+            receipt.receipt.logs.clear();
+        }
+
+        let verified_client = VerifiedRpcClient::with_backend(rpc);
+        verified_client.state.add_trusted_blocks(&[trusted_block]);
+
+        let tag = Some(BlockNumberOrTag::Number(trusted_block.number));
+        let result = verified_client.get_block_receipts(tag).await;
+
+        assert!(result.is_err_and(|e| matches!(
+            e,
+            RpcError::Verification(VerificationError::ReceiptsRootMismatch { .. })
+        )));
+    }
+
+    /// Expected to pass with receipts fetched one by one, as `eth_getBlockReceipts` is unsupported
+    #[tokio::test]
+    async fn test_verified_rpc_client_get_block_receipts_unsupported_success() {
+        let (mut rpc, _, trusted_block) = setup().await;
+        rpc.unsupported.insert("eth_getBlockReceipts");
+
+        let verified_client = VerifiedRpcClient::with_backend(rpc.clone());
+        verified_client.state.add_trusted_blocks(&[trusted_block]);
+
+        let tag = Some(BlockNumberOrTag::Number(trusted_block.number));
+        let receipts = rpc.blocks[&trusted_block.number].receipts.clone();
+        let verified_receipts = verified_client
+            .get_block_receipts(tag)
+            .await
+            .expect("failed to VerifiedRpcClient.get_block_receipts");
+        assert_eq!(receipts, verified_receipts);
+
+        let verified_receipt = verified_client
+            .get_transaction_receipt(receipts[0].transaction_hash)
+            .await
+            .expect("failed to VerifiedRpcClient.get_transaction_receipt");
+        assert_eq!(receipts[0], verified_receipt);
+    }
+
+    /// Expected to fail because a receipt fetched one by one is served for another block,
+    /// which the receipts root doesn't cover
+    #[tokio::test]
+    async fn test_verified_rpc_client_get_block_receipts_unsupported_error_forged_block_hash() {
+        let (mut rpc, _, trusted_block) = setup().await;
+        rpc.unsupported.insert("eth_getBlockReceipts");
+
+        // [testing] serve a receipt for another block hash
+        rpc.blocks.get_mut(&trusted_block.number).unwrap().receipts[1].block_hash =
+            Some(B256::ZERO);
+
+        let verified_client = VerifiedRpcClient::with_backend(rpc);
+        verified_client.state.add_trusted_blocks(&[trusted_block]);
+
+        let tag = Some(BlockNumberOrTag::Number(trusted_block.number));
+        let result = verified_client.get_block_receipts(tag).await;
+
+        assert!(result.is_err_and(|e| matches!(
+            e,
+            RpcError::Verification(VerificationError::InvalidReceipt { .. })
+        )));
+    }
+
+    /// Expected to fail because the receipt is served at another index, along with the
+    /// block receipts, which the receipts root doesn't cover
+    #[tokio::test]
+    async fn test_verified_rpc_client_get_transaction_receipt_error_forged_index() {
+        let (mut rpc, _, trusted_block) = setup().await;
+
+        // [testing] serve the receipt of the first transaction at another index
+        let receipts = &mut rpc.blocks.get_mut(&trusted_block.number).unwrap().receipts;
+        receipts[0].transaction_index = Some(1);
+        let tx_hash = receipts[0].transaction_hash;
+
+        let verified_client = VerifiedRpcClient::with_backend(rpc);
+        verified_client.state.add_trusted_blocks(&[trusted_block]);
+
+        let result = verified_client.get_transaction_receipt(tx_hash).await;
+
+        assert!(result.is_err_and(|e| matches!(
+            e,
+            RpcError::Verification(VerificationError::InvalidReceipt { .. })
+        )));
+    }
+
+    /// Expected to tell unsupported methods apart from missing data worded alike
+    #[test]
+    fn test_rpc_error_is_unsupported_method() {
+        use alloy::rpc::json_rpc::ErrorPayload;
+        use alloy::transports::TransportError;
+
+        let error = |code, message: &str| RpcError::Transport {
+            method: "eth_getBlockReceipts".to_string(),
+            source: TransportError::err_resp(ErrorPayload {
+                code,
+                message: message.to_string().into(),
+                data: None,
+            }),
+        };
+
+        assert!(error(-32601, "Method not found").is_unsupported_method());
+        assert!(error(-32004, "Method not supported").is_unsupported_method());
+        assert!(error(
+            -32000,
+            "the method eth_getBlockReceipts does not exist/is not available"
+        )
+        .is_unsupported_method());
+        assert!(!error(-32000, "block 0x1234 does not exist").is_unsupported_method());
+        assert!(!error(-32000, "unsupported block number").is_unsupported_method());
+    }
+
+    /// Expected to fail because the provider tampered with a receipt fetched one by one
+    #[tokio::test]
+    async fn test_verified_rpc_client_get_block_receipts_unsupported_error_receipts_root_mismatch()
+    {
+        let (mut rpc, _, trusted_block) = setup().await;
+        rpc.unsupported.insert("eth_getBlockReceipts");
+
+        // [testing] drop the logs of the contract call
+        let receipts = &mut rpc.blocks.get_mut(&trusted_block.number).unwrap().receipts;
+        let receipt = &mut receipts[1];
+        if let ReceiptEnvelope::Legacy(receipt) = &mut receipt.inner {
+            receipt.receipt.logs.clear();
+        }
+
+        let verified_client = VerifiedRpcClient::with_backend(rpc);
+        verified_client.state.add_trusted_blocks(&[trusted_block]);
+
+        let tag = Some(BlockNumberOrTag::Number(trusted_block.number));
+        let result = verified_client.get_block_receipts(tag).await;
+
+        assert!(result.is_err_and(|e| matches!(
+            e,
+            RpcError::Verification(VerificationError::ReceiptsRootMismatch { .. })
+        )));
+    }
+
+    /// Expected to pass
+    #[tokio::test]
+    async fn test_verified_rpc_client_get_block_success() {
+        let (rpc, verified_client, trusted_block) = setup().await;
+
+        for full in [true, false] {
+            let tag = Some(BlockNumberOrTag::Number(trusted_block.number));
+            let block = rpc
+                .get_block_by_number(tag, full)
+                .await
+                .expect("failed to MockRpc.get_block_by_number");
+
+            let verified_block = verified_client
+                .get_block_by_number(tag, full)
+                .await
+                .expect("failed to VerifiedRpcClient.get_block_by_number");
+            assert_eq!(block, verified_block);
+
+            let verified_block = verified_client
+                .get_block_by_hash(trusted_block.hash, full)
+                .await
+                .expect("failed to VerifiedRpcClient.get_block_by_hash");
+            assert_eq!(block, verified_block);
+        }
+    }
+
+    /// Expected to return the hashes computed from the transactions without full transactions,
+    /// without recovering their senders, and to fail on the forged fields with them
+    #[tokio::test]
+    async fn test_verified_rpc_client_get_block_forged_fields_success() {
+        let (mut rpc, _, trusted_block) = setup().await;
+        let tx_hashes: Vec<B256> = rpc.blocks[&trusted_block.number]
+            .block
+            .transactions
+            .hashes()
+            .collect();
+
+        // [testing] forge the served hash and sender of the first transaction
+        let block = &mut rpc.blocks.get_mut(&trusted_block.number).unwrap().block;
+        if let BlockTransactions::Full(transactions) = &mut block.transactions {
+            transactions[0].hash = B256::ZERO;
+            transactions[0].from = Address::ZERO;
+        }
+
+        let verified_client = VerifiedRpcClient::with_backend(rpc);
+        verified_client.state.add_trusted_blocks(&[trusted_block]);
+
+        let verified_block = verified_client
+            .get_block_by_hash(trusted_block.hash, false)
+            .await
+            .expect("failed to VerifiedRpcClient.get_block_by_hash");
+        assert_eq!(
+            verified_block.transactions.hashes().collect::<Vec<_>>(),
+            tx_hashes
+        );
+
+        let result = verified_client
+            .get_block_by_hash(trusted_block.hash, true)
+            .await;
+        assert!(result.is_err_and(|e| matches!(
+            e,
+            RpcError::Verification(VerificationError::InvalidTransaction { .. })
+        )));
+    }
+
+    /// Expected to fail because a transaction is left out of the block
+    #[tokio::test]
+    async fn test_verified_rpc_client_get_block_error_transactions_root_mismatch() {
+        let (mut rpc, _, trusted_block) = setup().await;
+
+        // [testing] drop the last transaction
+        let block = &mut rpc.blocks.get_mut(&trusted_block.number).unwrap().block;
+        if let BlockTransactions::Full(transactions) = &mut block.transactions {
+            transactions.pop();
+        }
+
+        let verified_client = VerifiedRpcClient::with_backend(rpc);
+        verified_client.state.add_trusted_blocks(&[trusted_block]);
+
+        let result = verified_client
+            .get_block_by_number(Some(BlockNumberOrTag::Latest), false)
+            .await;
+
+        assert!(result.is_err_and(|e| matches!(
+            e,
+            RpcError::Verification(VerificationError::TransactionsRootMismatch { .. })
+        )));
+    }
+
+    /// Expected to fail because a withdrawal is forged
+    #[tokio::test]
+    async fn test_verified_rpc_client_get_block_error_withdrawals_root_mismatch() {
+        let (mut rpc, _, trusted_block) = setup().await;
+
+        // [testing] inflate the first withdrawal
+        let block = &mut rpc.blocks.get_mut(&trusted_block.number).unwrap().block;
+        block.withdrawals.as_mut().unwrap()[0].amount += 1;
+
+        let verified_client = VerifiedRpcClient::with_backend(rpc);
+        verified_client.state.add_trusted_blocks(&[trusted_block]);
+
+        let result = verified_client
+            .get_block_by_hash(trusted_block.hash, true)
+            .await;
+
+        assert!(result.is_err_and(|e| matches!(
+            e,
+            RpcError::Verification(VerificationError::WithdrawalsRootMismatch { .. })
+        )));
+    }
+
+    /// Expected to fail because the provider added an uncle to a block without any
+    #[tokio::test]
+    async fn test_verified_rpc_client_get_block_error_ommers_hash_mismatch() {
+        let (mut rpc, _, trusted_block) = setup().await;
+
+        // [testing] inject a fake uncle hash
+        let block = &mut rpc.blocks.get_mut(&trusted_block.number).unwrap().block;
+        block.uncles.push(B256::repeat_byte(1));
+
+        let verified_client = VerifiedRpcClient::with_backend(rpc);
+        verified_client.state.add_trusted_blocks(&[trusted_block]);
+
+        for full in [true, false] {
+            let result = verified_client
+                .get_block_by_hash(trusted_block.hash, full)
+                .await;
+
+            assert!(result.is_err_and(|e| matches!(
+                e,
+                RpcError::Verification(VerificationError::OmmersHashMismatch { .. })
+            )));
+        }
+    }
+
+    /// Expected to pass
+    #[tokio::test]
+    async fn test_verified_rpc_client_get_transaction_success() {
+        let (rpc, verified_client, trusted_block) = setup().await;
+
+        // [testing] one transaction of each type, legacy to EIP-7702
+        let tag = Some(BlockNumberOrTag::Number(trusted_block.number));
+        let mut tx_types = vec![];
+        for index in 0..5 {
+            let tx = rpc
+                .get_transaction_by_block_number_and_index(tag, index)
+                .await
+                .expect("failed to MockRpc.get_transaction_by_block_number_and_index");
+            tx_types.extend(tx.transaction_type);
+
+            let verified_tx = verified_client
+                .get_transaction_by_hash(tx.hash)
+                .await
+                .expect("failed to VerifiedRpcClient.get_transaction_by_hash");
+            assert_eq!(tx, verified_tx);
+
+            let verified_tx = verified_client
+                .get_transaction_by_block_number_and_index(tag, index)
+                .await
+                .expect("failed to VerifiedRpcClient.get_transaction_by_block_number_and_index");
+            assert_eq!(tx, verified_tx);
+
+            let verified_tx = verified_client
+                .get_transaction_by_block_hash_and_index(trusted_block.hash, index)
+                .await
+                .expect("failed to VerifiedRpcClient.get_transaction_by_block_hash_and_index");
+            assert_eq!(tx, verified_tx);
+        }
+        tx_types.sort();
+        assert_eq!(tx_types, [0, 1, 2, 3, 4]);
+    }
+
+    /// Expected to fail because the transaction is forged
+    #[tokio::test]
+    async fn test_verified_rpc_client_get_transaction_error_invalid_transaction() {
+        let (mut rpc, _, trusted_block) = setup().await;
+
+        // [testing] inflate the value of the first transaction, keeping its hash
+        let block = &mut rpc.blocks.get_mut(&trusted_block.number).unwrap().block;
+        let BlockTransactions::Full(transactions) = &mut block.transactions else {
+            unreachable!("mock blocks hold full transactions");
+        };
+        transactions[0].value += U256::from(1);
+        let tx_hash = transactions[0].hash;
+
+        let verified_client = VerifiedRpcClient::with_backend(rpc);
+        verified_client.state.add_trusted_blocks(&[trusted_block]);
+
+        let result = verified_client.get_transaction_by_hash(tx_hash).await;
+
+        assert!(result.is_err_and(|e| matches!(
+            e,
+            RpcError::Verification(VerificationError::InvalidTransaction { .. })
+        )));
+    }
+
+    /// Expected to accept transactions of all types recorded from nodes, and reject them once
+    /// tampered with
+    #[test]
+    fn test_verify_transaction_recorded_success() {
+        for mut tx in recorded_transactions() {
+            let block_hash = tx.block_hash.unwrap();
+            let block_number = tx.block_number.unwrap();
+            let index = tx.transaction_index.unwrap() as usize;
+
+            let encoded = verify_transaction(&tx, &block_hash, block_number, index)
+                .expect("failed to verify_transaction");
+            assert_eq!(keccak256(encoded), tx.hash);
+
+            tx.value += U256::from(1);
+            let result = verify_transaction(&tx, &block_hash, block_number, index);
+            assert!(
+                result.is_err_and(|e| matches!(e, VerificationError::InvalidTransaction { .. }))
+            );
+        }
+    }
+
+    /// Expected to pass
+    #[tokio::test]
+    async fn test_verified_rpc_client_get_logs_success() {
+        let (rpc, verified_client, trusted_block) = setup().await;
+
+        let filter = Filter::new()
+            .from_block(1)
+            .to_block(BlockNumberOrTag::Latest)
+            .address(MOCK_CONTRACT)
+            .event_signature(TRANSFER_TOPIC);
+        let logs = rpc
+            .get_logs(&filter)
+            .await
+            .expect("failed to MockRpc.get_logs");
+
+        let verified_logs = verified_client
+            .get_logs(&filter)
+            .await
+            .expect("failed to VerifiedRpcClient.get_logs");
+        assert_eq!(verified_logs.len() as u64, MOCK_CHAIN_LENGTH);
+        assert_eq!(logs, verified_logs);
+
+        let filter = filter.at_block_hash(trusted_block.hash);
+        let verified_logs = verified_client
+            .get_logs(&filter)
+            .await
+            .expect("failed to VerifiedRpcClient.get_logs");
+        assert_eq!(verified_logs, logs[logs.len() - 1..]);
+    }
+
+    /// Expected to pass with the fields not covered by the receipts root taken from the
+    /// verified block, whatever the provider served
+    #[tokio::test]
+    async fn test_verified_rpc_client_get_logs_forged_fields_success() {
+        let (mut rpc, _, trusted_block) = setup().await;
+        let filter = Filter::new().at_block_hash(trusted_block.hash);
+        let logs = rpc.get_logs(&filter).await.unwrap();
+
+        // [testing] misattribute the logs and mark them removed
+        let receipts = &mut rpc.blocks.get_mut(&trusted_block.number).unwrap().receipts;
+        for receipt in receipts {
+            if let ReceiptEnvelope::Legacy(receipt) | ReceiptEnvelope::Eip1559(receipt) =
+                &mut receipt.inner
+            {
+                for log in &mut receipt.receipt.logs {
+                    log.transaction_hash = Some(B256::repeat_byte(1));
+                    log.block_timestamp = Some(0);
+                    log.removed = true;
+                }
+            }
+        }
+
+        let verified_client = VerifiedRpcClient::with_backend(rpc);
+        verified_client.state.add_trusted_blocks(&[trusted_block]);
+
+        let verified_logs = verified_client
+            .get_logs(&filter)
+            .await
+            .expect("failed to VerifiedRpcClient.get_logs");
+        assert!(!logs.is_empty());
+        assert_eq!(logs, verified_logs);
+    }
+
+    /// Expected to pass without fetching receipts, the logs bloom rules out every block
+    #[tokio::test]
+    async fn test_verified_rpc_client_get_logs_bloom_success() {
+        let (mut rpc, _, trusted_block) = setup().await;
+
+        // [testing] drop all receipts, fetching them would fail verification
+        for block in rpc.blocks.values_mut() {
+            block.receipts.clear();
+        }
+
+        let verified_client = VerifiedRpcClient::with_backend(rpc);
+        verified_client.state.add_trusted_blocks(&[trusted_block]);
+
+        let filter = Filter::new()
+            .from_block(1)
+            .to_block(trusted_block.number)
+            .address(Address::from_str(ADDRESS).unwrap());
+        let logs = verified_client
+            .get_logs(&filter)
+            .await
+            .expect("failed to VerifiedRpcClient.get_logs");
+
+        assert!(logs.is_empty());
+    }
+
+    /// Expected to fail because the provider left a log out
+    #[tokio::test]
+    async fn test_verified_rpc_client_get_logs_error_receipts_root_mismatch() {
+        let (mut rpc, _, trusted_block) = setup().await;
+
+        // [testing] drop the logs of the contract call
+        let receipts = &mut rpc.blocks.get_mut(&trusted_block.number).unwrap().receipts;
+        let receipt = &mut receipts[1];
+        if let ReceiptEnvelope::Legacy(receipt) = &mut receipt.inner {
+            receipt.receipt.logs.clear();
+        }
+
+        let verified_client = VerifiedRpcClient::with_backend(rpc);
+        verified_client.state.add_trusted_blocks(&[trusted_block]);
+
+        let filter = Filter::new().address(MOCK_CONTRACT);
+        let result = verified_client.get_logs(&filter).await;
+
+        assert!(result.is_err_and(|e| matches!(
+            e,
+            RpcError::Verification(VerificationError::ReceiptsRootMismatch { .. })
+        )));
+    }
+
+    /// Expected to pass
+    #[tokio::test]
+    async fn test_verified_rpc_client_call_success() {
+        let (_, verified_client, trusted_block) = setup().await;
+
+        let request = TransactionRequest::default()
+            .from(mock_signer().address())
+            .to(MOCK_CONTRACT);
+
+        // the contract returns its counter bumped by one, the counter is the block number
+        for number in [4, trusted_block.number] {
+            let output = verified_client
+                .call(&request, Some(BlockNumberOrTag::Number(number)))
+                .await
+                .expect("failed to VerifiedRpcClient.call");
+            assert_eq!(output[..], B256::from(U256::from(number + 1))[..]);
+        }
+    }
+
+    /// Expected to fail because the provider forged the contract storage
+    #[tokio::test]
+    async fn test_verified_rpc_client_call_error_forged_storage() {
+        let (mut rpc, _, trusted_block) = setup().await;
+
+        // [testing] forge the counter of the contract
+        let state = &mut rpc.blocks.get_mut(&trusted_block.number).unwrap().state;
+        let contract = state.accounts.get_mut(&MOCK_CONTRACT).unwrap();
+        contract.storage.insert(B256::ZERO, U256::from(1337));
+
+        let verified_client = VerifiedRpcClient::with_backend(rpc).with_chain_id(MOCK_CHAIN_ID);
+        verified_client.state.add_trusted_blocks(&[trusted_block]);
+
+        let request = TransactionRequest::default()
+            .from(mock_signer().address())
+            .to(MOCK_CONTRACT);
+        let result = verified_client.call(&request, None).await;
+
+        assert!(result.is_err_and(|e| matches!(
+            e,
+            RpcError::Verification(VerificationError::InvalidAccountProof { .. })
+        )));
+    }
+
+    /// Expected to load the slots an execution reads of a loaded account with one proof
+    #[tokio::test]
+    async fn test_verified_rpc_client_call_slots_single_proof_success() {
+        let mut rpc = mock_chain(MOCK_CHAIN_LENGTH);
+        let contract = Address::repeat_byte(0xcc);
+        // [testing] PUSH0 SLOAD DUP1 SLOAD POP PUSH1 1 ADD SLOAD PUSH0 MSTORE PUSH1 32 PUSH0
+        // RETURN: reads slot 0, then the slots it points to and the next one
+        rpc.deploy(
+            contract,
+            MockAccount {
+                code: bytes!("5f54805450600101545f5260205ff3"),
+                storage: [(0, 5), (5, 7), (6, 8)]
+                    .into_iter()
+                    .map(|(slot, value)| (B256::from(U256::from(slot)), U256::from(value)))
+                    .collect(),
+                ..Default::default()
+            },
+        );
+        let verified_client =
+            VerifiedRpcClient::with_backend(rpc.clone()).with_chain_id(MOCK_CHAIN_ID);
+        let trusted_block = rpc.blocks[&MOCK_CHAIN_LENGTH].trusted_block();
+        verified_client.state.add_trusted_blocks(&[trusted_block]);
+
+        let request = TransactionRequest::default()
+            .from(mock_signer().address())
+            .to(contract);
+        let output = verified_client
+            .call(&request, None)
+            .await
+            .expect("failed to VerifiedRpcClient.call");
+        assert_eq!(output[..], B256::from(U256::from(8))[..]);
+
+        // The sender, the contract and the beneficiary, then slots 0 and 1, then 5 and 6
+        let proofs = rpc
+            .take_requests()
+            .into_iter()
+            .filter(|method| *method == "eth_getProof")
+            .count();
+        assert_eq!(proofs, 5);
+    }
+
+    /// Expected to run the code delegated to by the authorization list of the call, once Prague
+    /// is active
+    #[tokio::test]
+    async fn test_verified_rpc_client_call_authorization_list_success() {
+        let mut rpc = mock_chain(MOCK_CHAIN_LENGTH);
+        rpc.activate_prague();
+        let verified_client =
+            VerifiedRpcClient::with_backend(rpc.clone()).with_chain_id(MOCK_CHAIN_ID);
+        let trusted_block = rpc.blocks[&MOCK_CHAIN_LENGTH].trusted_block();
+        verified_client.state.add_trusted_blocks(&[trusted_block]);
+
+        // [testing] a fresh account delegating to the counter contract
+        let authority = PrivateKeySigner::random();
+        let authorization = Authorization {
+            chain_id: MOCK_CHAIN_ID,
+            address: MOCK_CONTRACT,
+            nonce: 0,
+        };
+        let signature = authority
+            .sign_hash_sync(&authorization.signature_hash())
+            .unwrap();
+        let request = TransactionRequest {
+            authorization_list: Some(vec![authorization.into_signed(signature)]),
+            ..TransactionRequest::default()
+                .from(mock_signer().address())
+                .to(authority.address())
+        };
+
+        // its own counter is unset, it returns one
+        let output = verified_client
+            .call(&request, None)
+            .await
+            .expect("failed to VerifiedRpcClient.call");
+        assert_eq!(output[..], B256::from(U256::from(1))[..]);
+
+        // [testing] authorizations are rejected before Prague
+        let rpc = mock_chain(MOCK_CHAIN_LENGTH);
+        let verified_client =
+            VerifiedRpcClient::with_backend(rpc.clone()).with_chain_id(MOCK_CHAIN_ID);
+        verified_client
+            .state
+            .add_trusted_blocks(&[rpc.blocks[&MOCK_CHAIN_LENGTH].trusted_block()]);
+        let result = verified_client.call(&request, None).await;
+        assert!(result.is_err_and(|e| matches!(e, RpcError::Evm(EvmError::InvalidCall(_)))));
+    }
+
+    /// Expected to execute calls on the configured chain, and to reject calls for another one
+    #[tokio::test]
+    async fn test_verified_rpc_client_call_chain_id_success() {
+        let (rpc, verified_client, trusted_block) = setup().await;
+
+        // [testing] CHAINID PUSH0 MSTORE PUSH1 32 PUSH0 RETURN
+        let request = TransactionRequest {
+            to: Some(TxKind::Create),
+            input: bytes!("465f5260205ff3").into(),
+            ..TransactionRequest::default().from(mock_signer().address())
+        };
+        let output = verified_client
+            .call(&request, None)
+            .await
+            .expect("failed to VerifiedRpcClient.call");
+        assert_eq!(output[..], B256::from(U256::from(MOCK_CHAIN_ID))[..]);
+
+        let result = verified_client
+            .call(
+                &TransactionRequest {
+                    chain_id: Some(1),
+                    ..request.clone()
+                },
+                None,
+            )
+            .await;
+        assert!(result.is_err_and(|e| matches!(e, RpcError::Evm(EvmError::InvalidCall(_)))));
+
+        // [testing] the chain id is never taken from the request or the backend
+        let verified_client = VerifiedRpcClient::with_backend(rpc);
+        verified_client.state.add_trusted_blocks(&[trusted_block]);
+        let result = verified_client
+            .call(
+                &TransactionRequest {
+                    chain_id: Some(MOCK_CHAIN_ID),
+                    ..request
+                },
+                None,
+            )
+            .await;
+        assert!(result.is_err_and(|e| matches!(e, RpcError::UnknownChainId)));
+    }
+
+    /// Expected to fail with a clear error instead of loading proofs without limit
+    #[tokio::test]
+    async fn test_verified_rpc_client_call_error_state_limit_exceeded() {
+        let (_, verified_client, _) = setup().await;
+
+        // [testing] read the balance of addresses 0x1100 down to 0x1:
+        // PUSH2 0x1100 JUMPDEST DUP1 BALANCE POP PUSH1 1 SWAP1 SUB DUP1 PUSH1 3 JUMPI STOP
+        let request = TransactionRequest {
+            to: Some(TxKind::Create),
+            input: bytes!("6111005b8031506001900380600357").into(),
+            ..TransactionRequest::default().from(mock_signer().address())
+        };
+        let result = verified_client.call(&request, None).await;
+
+        assert!(result.is_err_and(|e| matches!(e, RpcError::Evm(EvmError::StateLimitExceeded(_)))));
+    }
+
+    /// Expected to lower gas limits above the cap to it
+    #[tokio::test]
+    async fn test_verified_rpc_client_call_gas_cap_success() {
+        let (_, verified_client, _) = setup().await;
+
+        // [testing] GAS PUSH0 MSTORE PUSH1 32 PUSH0 RETURN
+        let request = TransactionRequest {
+            to: Some(TxKind::Create),
+            input: bytes!("5a5f5260205ff3").into(),
+            gas: Some(u64::MAX),
+            ..TransactionRequest::default().from(mock_signer().address())
+        };
+        let output = verified_client
+            .call(&request, None)
+            .await
+            .expect("failed to VerifiedRpcClient.call");
+
+        assert!(U256::from_be_slice(&output) < U256::from(evm::RPC_GAS_CAP));
+    }
+
+    /// Expected to pass
+    #[tokio::test]
+    async fn test_verified_rpc_client_estimate_gas_success() {
+        let (_, verified_client, _) = setup().await;
+
+        let transfer = TransactionRequest::default()
+            .from(mock_signer().address())
+            .to(Address::from_str(ADDRESS).unwrap())
+            .value(U256::from(1));
+        let gas = verified_client
+            .estimate_gas(&transfer, None)
+            .await
+            .expect("failed to VerifiedRpcClient.estimate_gas");
+        assert_eq!(gas, 21_000);
+
+        let request = TransactionRequest::default()
+            .from(mock_signer().address())
+            .to(MOCK_CONTRACT);
+        let gas = verified_client
+            .estimate_gas(&request, None)
+            .await
+            .expect("failed to VerifiedRpcClient.estimate_gas");
+
+        // the estimate is the lowest gas limit the call succeeds with
+        let result = verified_client
+            .call(&request.clone().gas_limit(gas), None)
+            .await;
+        assert!(result.is_ok());
+        let result = verified_client
+            .call(&request.gas_limit(gas - 1), None)
+            .await;
+        assert!(result.is_err_and(|e| matches!(e, RpcError::Evm(EvmError::Halted(_)))));
+    }
+
+    /// Expected to pass
+    #[tokio::test]
+    async fn test_verified_rpc_client_create_access_list_success() {
+        let (_, verified_client, _) = setup().await;
+
+        let request = TransactionRequest::default()
+            .from(mock_signer().address())
+            .to(MOCK_CONTRACT);
+        let result = verified_client
+            .create_access_list(&request, None)
+            .await
+            .expect("failed to VerifiedRpcClient.create_access_list");
+
+        // the contract only accesses its counter
+        assert_eq!(
+            result.access_list,
+            AccessList(vec![AccessListItem {
+                address: MOCK_CONTRACT,
+                storage_keys: vec![B256::ZERO],
+            }])
+        );
+        assert!(result.error.is_none());
+
+        let gas = verified_client
+            .estimate_gas(&request.access_list(result.access_list), None)
+            .await
+            .expect("failed to VerifiedRpcClient.estimate_gas");
+        assert!(gas >= result.gas_used.to::<u64>());
+    }
+
+    /// Expected to settle on the accesses of both branches of a call that branches on the gas
+    /// left, which declaring either of them lowers
+    #[tokio::test]
+    async fn test_verified_rpc_client_create_access_list_gas_dependent_success() {
+        let (_, verified_client, _) = setup().await;
+        let (high, low) = (Address::repeat_byte(0xaa), Address::repeat_byte(0xbb));
+
+        // [testing] GAS PUSH2 <threshold> LT PUSH1 31 JUMPI PUSH20 <low> BALANCE STOP
+        // JUMPDEST PUSH20 <high> BALANCE STOP: reads `high` above the threshold, `low` below
+        let initcode = |threshold: u16| {
+            [
+                &[0x5a, 0x61][..],
+                &threshold.to_be_bytes(),
+                &[0x10, 0x60, 0x1f, 0x57, 0x73],
+                low.as_slice(),
+                &[0x31, 0x00, 0x5b, 0x73],
+                high.as_slice(),
+                &[0x31, 0x00],
+            ]
+            .concat()
+        };
+        let calldata_cost = |code: &[u8]| {
+            let zeros = code.iter().filter(|byte| **byte == 0).count() as u64;
+            zeros * 4 + (code.len() as u64 - zeros) * 16 + code.len().div_ceil(32) as u64 * 2
+        };
+        let request = |code: Vec<u8>| TransactionRequest {
+            to: Some(TxKind::Create),
+            input: Bytes::from(code).into(),
+            gas: Some(100_000),
+            ..TransactionRequest::default().from(mock_signer().address())
+        };
+
+        // [testing] GAS PUSH0 MSTORE PUSH1 32 PUSH0 RETURN, to learn the gas left at start
+        let probe = bytes!("5a5f5260205ff3");
+        let output = verified_client
+            .call(&request(probe.to_vec()), None)
+            .await
+            .expect("failed to VerifiedRpcClient.call");
+        let gas_left = U256::from_be_slice(&output).to::<u64>() + calldata_cost(&probe);
+        // [testing] declaring any address lowers the gas left by 2400 below the threshold
+        let threshold = gas_left - calldata_cost(&initcode(0xffff)) - 1_200;
+
+        let result = verified_client
+            .create_access_list(&request(initcode(threshold as u16)), None)
+            .await
+            .expect("failed to VerifiedRpcClient.create_access_list");
+
+        let addresses: Vec<Address> = result
+            .access_list
+            .0
+            .iter()
+            .map(|item| item.address)
+            .collect();
+        assert!(addresses.contains(&high) && addresses.contains(&low));
+        assert!(result.error.is_none());
+    }
+
+    /// Expected to return a JSON-RPC error for a call the sender cannot afford
+    #[tokio::test]
+    async fn test_server_call_error_transaction_rejected() {
+        let (_, verified_client, _) = setup().await;
+
+        let request = format!(
+            r#"{{"jsonrpc":"2.0","id":1,"method":"eth_call","params":[{{"from":"{ADDRESS}","to":"{MOCK_CONTRACT}","value":"0xffffffffffffffffffffffff"}},"latest"]}}"#
+        );
+        let response = server::handle_request(&verified_client, request.as_bytes())
+            .await
+            .expect("expected a response");
+
+        assert_eq!(response["error"]["code"], server::TRANSACTION_REJECTED);
+    }
+
+    /// Expected to return the error response of the execution RPC to a raw transaction as is
+    #[tokio::test]
+    async fn test_server_send_raw_transaction_error_rejected() {
+        let mut rpc = mock_chain(MOCK_CHAIN_LENGTH);
+        rpc.tx_rejection = Some("nonce too low");
+        let verified_client = VerifiedRpcClient::with_backend(rpc);
+
+        let request =
+            r#"{"jsonrpc":"2.0","id":1,"method":"eth_sendRawTransaction","params":["0x01"]}"#;
+        let response = server::handle_request(&verified_client, request.as_bytes())
+            .await
+            .expect("expected a response");
+
+        assert_eq!(
+            response["error"],
+            serde_json::json!({ "code": -32000, "message": "nonce too low" })
+        );
+    }
+
+    /// Expected to pass
+    #[tokio::test]
+    async fn test_server_get_balance_success() {
+        let (rpc, verified_client, _) = setup().await;
+
+        let addr = Address::from_str(ADDRESS).expect("failed to parse address");
+        let balance = rpc
+            .get_balance(addr, None)
+            .await
+            .expect("failed to MockRpc.get_balance");
+
+        let request = format!(
+            r#"{{"jsonrpc":"2.0","id":1,"method":"eth_getBalance","params":["{ADDRESS}","latest"]}}"#
+        );
+        let response = server::handle_request(&verified_client, request.as_bytes())
+            .await
+            .expect("expected a response");
+
+        assert_eq!(response["id"], 1);
+        assert_eq!(response["result"], format!("{balance:#x}"));
+    }
+
+    /// Expected to answer `null` for unknown transactions, receipts, blocks and indexes
+    #[tokio::test]
+    async fn test_server_not_found_success() {
+        let (_, verified_client, trusted_block) = setup().await;
+
+        let unknown = B256::repeat_byte(1);
+        let request = format!(
+            r#"[
+                {{"jsonrpc":"2.0","id":1,"method":"eth_getTransactionReceipt","params":["{unknown}"]}},
+                {{"jsonrpc":"2.0","id":2,"method":"eth_getTransactionByHash","params":["{unknown}"]}},
+                {{"jsonrpc":"2.0","id":3,"method":"eth_getBlockByHash","params":["{unknown}",false]}},
+                {{"jsonrpc":"2.0","id":4,"method":"eth_getTransactionByBlockHashAndIndex","params":["{0}","0xff"]}},
+                {{"jsonrpc":"2.0","id":5,"method":"eth_getTransactionByBlockNumberAndIndex","params":["latest","0xff"]}}
+            ]"#,
+            trusted_block.hash
+        );
+        let response = server::handle_request(&verified_client, request.as_bytes())
+            .await
+            .expect("expected a response");
+
+        for response in response.as_array().expect("expected a batch response") {
+            assert_eq!(response["result"], serde_json::Value::Null, "{response}");
+            assert!(response.get("error").is_none(), "{response}");
+        }
+    }
+
+    /// Expected to accept storage slots as short hex quantities
+    #[tokio::test]
+    async fn test_server_get_storage_at_short_slot_success() {
+        let (rpc, verified_client, _) = setup().await;
+
+        let value = rpc
+            .get_storage_at(MOCK_CONTRACT, B256::ZERO, None)
+            .await
+            .expect("failed to MockRpc.get_storage_at");
+
+        let request = format!(
+            r#"[
+                {{"jsonrpc":"2.0","id":1,"method":"eth_getStorageAt","params":["{MOCK_CONTRACT}","0x0","latest"]}},
+                {{"jsonrpc":"2.0","id":2,"method":"eth_getStorageAt","params":["{MOCK_CONTRACT}","{}","latest"]}}
+            ]"#,
+            B256::ZERO
+        );
+        let response = server::handle_request(&verified_client, request.as_bytes())
+            .await
+            .expect("expected a response");
+
+        assert_eq!(response[0]["result"], B256::from(value).to_string());
+        assert_eq!(response[1]["result"], response[0]["result"]);
+    }
+
+    /// Expected to serve the chain id and head from the configuration and the trusted chain,
+    /// and to forward raw transactions
+    #[tokio::test]
+    async fn test_server_local_methods_success() {
+        let (rpc, verified_client, trusted_block) = setup().await;
+        rpc.take_requests();
+
+        let request = br#"[
+            {"jsonrpc":"2.0","id":1,"method":"eth_chainId","params":[]},
+            {"jsonrpc":"2.0","id":2,"method":"net_version","params":[]},
+            {"jsonrpc":"2.0","id":3,"method":"eth_blockNumber","params":[]}
+        ]"#;
+        let response = server::handle_request(&verified_client, request)
+            .await
+            .expect("expected a response");
+        assert_eq!(response[0]["result"], format!("{MOCK_CHAIN_ID:#x}"));
+        assert_eq!(response[1]["result"], MOCK_CHAIN_ID.to_string());
+        assert_eq!(
+            response[2]["result"],
+            format!("{:#x}", trusted_block.number)
+        );
+
+        let tx = bytes!("02f86a827a69");
+        let request = format!(
+            r#"{{"jsonrpc":"2.0","id":4,"method":"eth_sendRawTransaction","params":["{tx}"]}}"#
+        );
+        let response = server::handle_request(&verified_client, request.as_bytes())
+            .await
+            .expect("expected a response");
+        assert_eq!(response["result"], keccak256(&tx).to_string());
+        assert_eq!(rpc.take_requests(), ["eth_sendRawTransaction"]);
+
+        // [testing] without configuration, nothing is taken from the execution RPC
+        let verified_client = VerifiedRpcClient::with_backend(rpc);
+        let request = br#"[
+            {"jsonrpc":"2.0","id":1,"method":"eth_chainId","params":[]},
+            {"jsonrpc":"2.0","id":2,"method":"eth_blockNumber","params":[]}
+        ]"#;
+        let response = server::handle_request(&verified_client, request)
+            .await
+            .expect("expected a response");
+        assert_eq!(response[0]["error"]["code"], server::INTERNAL_ERROR);
+        assert_eq!(response[1]["error"]["code"], server::UNTRUSTED_BLOCK);
+    }
+
+    /// Expected to return spec-compliant JSON-RPC errors without contacting the RPC
+    #[tokio::test]
+    async fn test_server_json_rpc_errors() {
+        let verified_client = VerifiedRpcClient::with_backend(MockRpc::default());
+
+        let response = server::handle_request(&verified_client, b"{not json")
+            .await
+            .expect("expected a response");
+        assert_eq!(response["error"]["code"], server::PARSE_ERROR);
+        assert_eq!(response["id"], serde_json::Value::Null);
+
+        let request = br#"{"jsonrpc":"2.0","id":7,"method":"eth_gasPrice","params":[]}"#;
+        let response = server::handle_request(&verified_client, request)
+            .await
+            .expect("expected a response");
+        assert_eq!(response["error"]["code"], server::METHOD_NOT_FOUND);
+        assert_eq!(response["id"], 7);
+
+        let request = br#"{"jsonrpc":"2.0","id":"a","method":"eth_getBalance","params":["0x01"]}"#;
+        let response = server::handle_request(&verified_client, request)
+            .await
+            .expect("expected a response");
+        assert_eq!(response["error"]["code"], server::INVALID_PARAMS);
+        assert_eq!(response["id"], "a");
+
+        // notifications are never answered
+        let request = br#"{"jsonrpc":"2.0","method":"eth_gasPrice","params":[]}"#;
+        assert!(server::handle_request(&verified_client, request)
+            .await
+            .is_none());
+    }
+
+    /// Expected to answer each call of a batch, leaving out the notifications
+    #[tokio::test]
+    async fn test_server_batch_success() {
+        let (rpc, verified_client, _) = setup().await;
+
+        let addr = Address::from_str(ADDRESS).expect("failed to parse address");
+        let balance = rpc
+            .get_balance(addr, None)
+            .await
+            .expect("failed to MockRpc.get_balance");
+
+        let request = format!(
+            r#"[
+                {{"jsonrpc":"2.0","id":1,"method":"eth_getBalance","params":["{ADDRESS}"]}},
+                {{"jsonrpc":"2.0","id":2,"method":"eth_gasPrice","params":[]}},
+                {{"jsonrpc":"2.0","method":"eth_getBalance","params":["{ADDRESS}"]}},
+                42
+            ]"#
+        );
+        let response = server::handle_request(&verified_client, request.as_bytes())
+            .await
+            .expect("expected a response");
+
+        let responses = response.as_array().expect("expected a batch response");
+        assert_eq!(responses.len(), 3);
+        assert_eq!(responses[0]["id"], 1);
+        assert_eq!(responses[0]["result"], format!("{balance:#x}"));
+        assert_eq!(responses[1]["id"], 2);
+        assert_eq!(responses[1]["error"]["code"], server::METHOD_NOT_FOUND);
+        assert_eq!(responses[2]["error"]["code"], server::INVALID_REQUEST);
+
+        let response = server::handle_request(&verified_client, b"[]")
+            .await
+            .expect("expected a response");
+        assert_eq!(response["error"]["code"], server::INVALID_REQUEST);
+
+        let call = r#"{"jsonrpc":"2.0","id":1,"method":"eth_chainId","params":[]}"#;
+        let request = format!("[{}]", vec![call; server::MAX_BATCH_SIZE + 1].join(","));
+        let response = server::handle_request(&verified_client, request.as_bytes())
+            .await
+            .expect("expected a response");
+        assert_eq!(response["error"]["code"], server::INVALID_REQUEST);
+
+        // a batch of notifications only is never answered
+        let request = br#"[{"jsonrpc":"2.0","method":"eth_sendRawTransaction","params":[]}]"#;
+        assert!(server::handle_request(&verified_client, request)
+            .await
+            .is_none());
+    }
+
+    /// Expected to pass by retrying on the honest provider, demoting the one serving a forged proof
+    #[tokio::test]
+    async fn test_failover_rpc_invalid_proof_success() {
+        let (rpc, _, trusted_block) = setup().await;
+
+        // [testing] the first provider drops the account, serving it zeroed with an exclusion proof
+        let addr = Address::from_str(ADDRESS).expect("failed to parse address");
+        let mut forged = rpc.clone();
+        let state = &mut forged.blocks.get_mut(&trusted_block.number).unwrap().state;
+        state.accounts.remove(&addr);
+
+        let verified_client =
+            VerifiedRpcClient::with_backend(FailoverRpc::with_backends(vec![forged, rpc.clone()]));
+        verified_client.state.add_trusted_blocks(&[trusted_block]);
+
+        let balance = verified_client
+            .retry_invalid(|| verified_client.get_balance(addr, None))
+            .await
+            .expect("failed to VerifiedRpcClient.get_balance");
+        assert_eq!(balance, rpc.get_balance(addr, None).await.unwrap());
+
+        let scores = verified_client.backend().scores();
+        assert!(scores[0] < scores[1]);
+        assert_eq!(scores[0], -INVALID_DATA_PENALTY + SUCCESS_REWARD);
+
+        // [testing] the demoted provider is no longer asked first
+        verified_client
+            .get_balance(addr, None)
+            .await
+            .expect("failed to VerifiedRpcClient.get_balance");
+    }
+
+    /// Expected to blame only the provider that served the invalid data, whatever the other
+    /// requests served concurrently
+    #[tokio::test]
+    async fn test_failover_rpc_invalid_proof_concurrent_success() {
+        let (rpc, _, trusted_block) = setup().await;
+
+        let addr = Address::from_str(ADDRESS).expect("failed to parse address");
+        let mut forged = rpc.clone();
+        let state = &mut forged.blocks.get_mut(&trusted_block.number).unwrap().state;
+        state.accounts.remove(&addr);
+
+        let verified_client =
+            VerifiedRpcClient::with_backend(FailoverRpc::with_backends(vec![forged, rpc.clone()]));
+        verified_client.state.add_trusted_blocks(&[trusted_block]);
+
+        let (balance, code) = tokio::join!(
+            verified_client.retry_invalid(|| verified_client.get_balance(addr, None)),
+            verified_client.retry_invalid(|| verified_client.get_code(MOCK_CONTRACT, None)),
+        );
+        assert_eq!(
+            balance.expect("failed to VerifiedRpcClient.get_balance"),
+            rpc.get_balance(addr, None).await.unwrap()
+        );
+        code.expect("failed to VerifiedRpcClient.get_code");
+
+        let scores = verified_client.backend().scores();
+        assert!(scores[0] < 0 && scores[0] > -2 * INVALID_DATA_PENALTY);
+        assert!(scores[1] > 0);
+    }
+
+    /// Expected to pass by failing over to the next provider when one lacks the data,
+    /// which is then penalized
+    #[tokio::test]
+    async fn test_failover_rpc_missing_data_success() {
+        let (rpc, _, trusted_block) = setup().await;
+
+        // [testing] the first provider answers null for every block
+        let mut lacking = rpc.clone();
+        lacking.blocks.clear();
+
+        let failover_rpc = FailoverRpc::with_backends(vec![lacking.clone(), rpc.clone()]);
+        failover_rpc
+            .get_block_by_hash(trusted_block.hash, false)
+            .await
+            .expect("failed to FailoverRpc.get_block_by_hash");
+        assert_eq!(failover_rpc.scores(), [-ERROR_PENALTY, SUCCESS_REWARD]);
+
+        let verified_client =
+            VerifiedRpcClient::with_backend(FailoverRpc::with_backends(vec![lacking, rpc]));
+        verified_client.state.add_trusted_blocks(&[trusted_block]);
+        verified_client
+            .retry_invalid(|| verified_client.get_block_by_hash(trusted_block.hash, false))
+            .await
+            .expect("failed to VerifiedRpcClient.get_block_by_hash");
+        assert_eq!(
+            verified_client.backend().scores(),
+            [-ERROR_PENALTY, SUCCESS_REWARD]
+        );
+    }
+
+    /// Expected to fail without penalizing the provider, the client asked for a block off
+    /// the trusted chain
+    #[tokio::test]
+    async fn test_failover_rpc_unlinked_block_error() {
+        let (rpc, _, trusted_block) = setup().await;
+
+        let verified_client =
+            VerifiedRpcClient::with_backend(FailoverRpc::with_backends(vec![rpc.clone(), rpc]));
+        // [testing] another block is trusted at the same height
+        verified_client.state.add_trusted_blocks(&[TrustedBlock {
+            hash: B256::repeat_byte(1),
+            ..trusted_block
+        }]);
+
+        let result = verified_client
+            .retry_invalid(|| verified_client.get_block_by_hash(trusted_block.hash, false))
+            .await;
+
+        assert!(result.is_err_and(|e| matches!(
+            e,
+            RpcError::Verification(VerificationError::UnlinkedBlock { .. })
+        )));
+        assert_eq!(verified_client.backend().scores(), [SUCCESS_REWARD, 0]);
+    }
+
+    /// Expected to pass by failing over to the next provider when one is unreachable
+    #[tokio::test]
+    async fn test_failover_rpc_transport_error_success() {
+        let (rpc, verified_client, trusted_block) = setup().await;
+
+        let url = format!(
+            "http://{}",
+            serve_locally(server::router(Arc::new(verified_client))).await
+        );
+        let failover_rpc = FailoverRpc::new(&["http://127.0.0.1:1", &url])
+            .expect("failed to create FailoverRpc")
+            .with_timeout(Duration::from_secs(5));
+
+        let addr = Address::from_str(ADDRESS).expect("failed to parse address");
+        let tag = Some(BlockNumberOrTag::Number(trusted_block.number));
+        let balance = failover_rpc
+            .get_balance(addr, tag)
+            .await
+            .expect("failed to FailoverRpc.get_balance");
+
+        assert_eq!(balance, rpc.get_balance(addr, tag).await.unwrap());
+        assert_eq!(failover_rpc.scores(), [-ERROR_PENALTY, SUCCESS_REWARD]);
+    }
+
+    /// Expected to return a rejected transaction as is, without rebroadcasting it to the
+    /// other providers nor penalizing the one that rejected it
+    #[tokio::test]
+    async fn test_failover_rpc_error_response_error() {
+        let mut rejecting = mock_chain(MOCK_CHAIN_LENGTH);
+        rejecting.tx_rejection = Some("nonce too low");
+        // [testing] a separate chain, not sharing the request log of the first one
+        let honest = mock_chain(MOCK_CHAIN_LENGTH);
+
+        let failover_rpc = FailoverRpc::with_backends(vec![rejecting.clone(), honest.clone()]);
+        let result = failover_rpc.send_raw_transaction(&bytes!("01")).await;
+
+        assert!(result.is_err_and(|e| !e.is_retryable()));
+        assert_eq!(rejecting.take_requests(), ["eth_sendRawTransaction"]);
+        assert!(honest.take_requests().is_empty());
+        assert_eq!(failover_rpc.scores(), [0, 0]);
+    }
+
+    /// Expected to fail over only on transport failures, timeouts and provider-side error codes
+    #[test]
+    fn test_rpc_error_is_retryable() {
+        use alloy::rpc::json_rpc::ErrorPayload;
+        use alloy::transports::TransportError;
+
+        let error = |code, message: &str| RpcError::Transport {
+            method: "eth_call".to_string(),
+            source: TransportError::err_resp(ErrorPayload {
+                code,
+                message: message.to_string().into(),
+                data: None,
+            }),
+        };
+
+        assert!(error(-32005, "limit exceeded").is_retryable());
+        assert!(error(-32603, "internal error").is_retryable());
+        assert!(RpcError::Timeout {
+            method: "eth_call".to_string()
+        }
+        .is_retryable());
+        assert!(!error(-32000, "nonce too low").is_retryable());
+        assert!(!error(-32602, "invalid argument 0").is_retryable());
+        assert!(!RpcError::MissingData("Block not found".to_string()).is_retryable());
+    }
+
+    /// Serve `router` on a local port, returning its address
+    async fn serve_locally(router: axum::Router) -> std::net::SocketAddr {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("failed to bind");
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router).await });
+
+        addr
+    }
+
+    /// Expected to send concurrent calls as a single HTTP batch, with per-call results
+    #[tokio::test]
+    async fn test_http_rpc_batch_success() {
+        let (rpc, verified_client, trusted_block) = setup().await;
+
+        // [testing] serve the proxy locally, counting the HTTP requests it receives
+        let http_requests = Arc::new(AtomicUsize::new(0));
+        let counter = http_requests.clone();
+        let router = server::router(Arc::new(verified_client)).layer(axum::middleware::from_fn(
+            move |request, next: axum::middleware::Next| {
+                counter.fetch_add(1, Ordering::SeqCst);
+                next.run(request)
+            },
+        ));
+        let url = format!("http://{}", serve_locally(router).await);
+
+        let http_rpc = HttpRpc::new(&url).expect("failed to create HttpRpc");
+        let addr = Address::from_str(ADDRESS).expect("failed to parse address");
+        let tag = Some(BlockNumberOrTag::Number(trusted_block.number));
+        let untrusted = Some(BlockNumberOrTag::Number(trusted_block.number + 1));
+
+        let (balance, code, block) = tokio::join!(
+            http_rpc.get_balance(addr, tag),
+            http_rpc.get_code(MOCK_CONTRACT, tag),
+            http_rpc.get_block_by_number(untrusted, false),
+        );
+
+        assert_eq!(http_requests.load(Ordering::SeqCst), 1);
+        assert_eq!(
+            balance.expect("failed to HttpRpc.get_balance"),
+            rpc.get_balance(addr, tag).await.unwrap()
+        );
+        assert_eq!(
+            code.expect("failed to HttpRpc.get_code"),
+            rpc.get_code(MOCK_CONTRACT, tag).await.unwrap()
+        );
+        assert!(block.is_err());
+    }
+
+    /// Expected to build accounts from balance, nonce and code, as the proxy (serving as the
+    /// untrusted RPC) lacks `eth_getProof` and is made to reject `eth_getAccount`
+    #[tokio::test]
+    async fn test_http_rpc_get_account_unsupported_success() {
+        use axum::response::IntoResponse;
+
+        let (rpc, verified_client, trusted_block) = setup().await;
+
+        // [testing] reject eth_getAccount calls like most providers do
+        let router = server::router(Arc::new(verified_client)).layer(axum::middleware::from_fn(
+            |request: axum::extract::Request, next: axum::middleware::Next| async move {
+                let (parts, body) = request.into_parts();
+                let body = axum::body::to_bytes(body, usize::MAX).await.unwrap();
+                let call: serde_json::Value = serde_json::from_slice(&body).unwrap();
+                // a batch with such a call is rejected whole, its calls are then sent one by one
+                let calls = call.as_array().cloned().unwrap_or_else(|| vec![call.clone()]);
+                if calls.iter().any(|call| call["method"] == "eth_getAccount") {
+                    let error = serde_json::json!({
+                        "jsonrpc": "2.0",
+                        "id": call["id"],
+                        "error": { "code": -32601, "message": "the method eth_getAccount does not exist/is not available" },
+                    });
+                    return axum::Json(error).into_response();
+                }
+                next.run(axum::extract::Request::from_parts(parts, body.into()))
+                    .await
+            },
+        ));
+        let url = format!("http://{}", serve_locally(router).await);
+
+        let http_rpc = HttpRpc::new(&url).expect("failed to create HttpRpc");
+        let tag = Some(BlockNumberOrTag::Number(trusted_block.number));
+        for addr in [Address::from_str(ADDRESS).unwrap(), MOCK_CONTRACT] {
+            let account = rpc.get_account(addr, None, tag).await.unwrap();
+            let built_account = http_rpc
+                .get_account(addr, None, tag)
+                .await
+                .expect("failed to HttpRpc.get_account");
+
+            // the storage root of the contract is unknown, the account without code has none
+            if addr == MOCK_CONTRACT {
+                assert_eq!(
+                    built_account,
+                    Account {
+                        storage_root: B256::ZERO,
+                        ..account
+                    }
+                );
+            } else {
+                assert_eq!(built_account, account);
+            }
+        }
+
+        assert_eq!(
+            http_rpc.detect_capabilities().await,
+            Capabilities {
+                get_account: false,
+                get_proof: false,
+                get_block_receipts: true,
+            }
+        );
+    }
+
+    /// Expected to detect only the methods supported by every provider, and to fetch
+    /// receipts one by one from the start
+    #[tokio::test]
+    async fn test_verified_rpc_client_detect_capabilities_success() {
+        let (rpc, _, trusted_block) = setup().await;
+        let mut partial_rpc = rpc.clone();
+        partial_rpc.unsupported.insert("eth_getBlockReceipts");
+
+        let verified_client =
+            VerifiedRpcClient::with_backend(FailoverRpc::with_backends(vec![partial_rpc, rpc]));
+        verified_client.state.add_trusted_blocks(&[trusted_block]);
+
+        // [testing] not served until detected
+        let request = br#"{"jsonrpc":"2.0","id":1,"method":"proxy_capabilities","params":[]}"#;
+        let response = server::handle_request(&verified_client, request)
+            .await
+            .expect("expected a response");
+        assert_eq!(response["result"], serde_json::Value::Null);
+
+        assert_eq!(
+            verified_client.detect_capabilities().await,
+            Capabilities {
+                get_block_receipts: false,
+                ..Capabilities::default()
+            }
+        );
+
+        let response = server::handle_request(&verified_client, request)
+            .await
+            .expect("expected a response");
+        assert_eq!(
+            response["result"],
+            serde_json::json!({
+                "eth_getAccount": true,
+                "eth_getProof": true,
+                "eth_getBlockReceipts": false,
+            })
+        );
+
+        // [testing] the first provider is never asked for eth_getBlockReceipts, so it
+        // serves every request without failing over
+        let tag = Some(BlockNumberOrTag::Number(trusted_block.number));
+        verified_client
+            .get_block_receipts(tag)
+            .await
+            .expect("failed to VerifiedRpcClient.get_block_receipts");
+        let scores = verified_client.backend().scores();
+        assert!(scores[0] > 0);
+        assert_eq!(scores[1], 0);
+    }
+
+    fn mock_state(address: Address, slot: B256, value: U256) -> MockState {
+        let mut state = MockState::default();
+        state.accounts.insert(
+            address,
+            MockAccount {
+                nonce: 1,
+                balance: U256::from(1_000_000),
+                storage: [(slot, value)].into_iter().collect(),
+                ..Default::default()
+            },
+        );
+        state
+    }
+
+    /// Expected to pass for an empty account that exists in the state trie (before EIP-161),
+    /// proven by its inclusion rather than its absence
+    #[test]
+    fn test_verify_account_proof_empty_account_success() {
+        let addr = Address::from_str(ADDRESS).expect("failed to parse address");
+        let empty = Address::repeat_byte(0x11);
+        let mut state = mock_state(addr, B256::ZERO, U256::from(1));
+        state.accounts.insert(empty, MockAccount::default());
+
+        let proof = state.get_proof(empty, &[B256::ZERO]);
+        verify_account_proof(&proof, &state.state_root()).expect("invalid account proof");
+        verify_storage_proof(&proof).expect("invalid storage proof");
+        assert_eq!(proof_to_account(&proof), Account::default());
+
+        // as well as an absent account, by its exclusion proof
+        let proof = state.get_proof(Address::repeat_byte(0x22), &[]);
+        verify_account_proof(&proof, &state.state_root()).expect("invalid account proof");
+    }
+
+    /// Expected to fail because the provider serves an existing account as empty, along with
+    /// its inclusion proof
+    #[test]
+    fn test_verify_account_proof_error_forged_empty_account() {
+        let addr = Address::from_str(ADDRESS).expect("failed to parse address");
+        let state = mock_state(addr, B256::ZERO, U256::from(1));
+
+        let mut forged_proof = state.get_proof(addr, &[]);
+        forged_proof.nonce = 0;
+        forged_proof.balance = U256::ZERO;
+        forged_proof.storage_hash = EMPTY_ROOT_HASH;
+        let result = verify_account_proof(&forged_proof, &state.state_root());
+
+        assert!(result.is_err_and(|e| matches!(e, VerificationError::InvalidAccountProof { .. })));
+    }
+
+    /// Expected to hash a recorded post-Prague header, committing to its requests hash
+    #[test]
+    fn test_verify_block_header_prague_success() {
+        let header = prague_header();
+
+        let consensus_header =
+            verify_block_header(&header, &header.hash).expect("failed to verify_block_header");
+
+        assert!(consensus_header.requests_hash.is_some());
+        assert_eq!(evm::spec_id(&consensus_header, 1), Some(SpecId::PRAGUE));
+    }
+
+    /// Expected to hash recorded mainnet headers, before London and after Cancun, and to fail
+    /// once they are tampered with
+    #[test]
+    fn test_verify_block_header_mainnet_success() {
+        for mut header in mainnet_headers() {
+            let consensus_header =
+                verify_block_header(&header, &header.hash).expect("failed to verify_block_header");
+            assert_eq!(consensus_header.number, header.number);
+
+            header.gas_used += 1;
+            let result = verify_block_header(&header, &header.hash);
+            assert!(result.is_err_and(|e| matches!(e, VerificationError::BlockHashMismatch { .. })));
+        }
+    }
+
+    /// Expected to infer the fork of recorded mainnet headers, before the merge from their
+    /// number only, which is unknown on other chains
+    #[test]
+    fn test_spec_id_mainnet_success() {
+        let [pre_london, cancun] = mainnet_headers()
+            .into_iter()
+            .map(|header| verify_block_header(&header, &header.hash).unwrap())
+            .collect::<Vec<_>>()
+            .try_into()
+            .unwrap();
+
+        assert_eq!(evm::spec_id(&pre_london, 1), Some(SpecId::MUIR_GLACIER));
+        assert_eq!(evm::spec_id(&pre_london, MOCK_CHAIN_ID), None);
+        assert_eq!(evm::spec_id(&cancun, 1), Some(SpecId::CANCUN));
+        assert_eq!(evm::spec_id(&cancun, MOCK_CHAIN_ID), Some(SpecId::CANCUN));
+    }
+
+    /// Expected to verify the nodes of a recorded mainnet account proof, against the root its
+    /// first node hashes to since the header of its block is not recorded
+    #[test]
+    fn test_verify_account_proof_mainnet_success() {
+        let proof = mainnet_account_proof();
+        let state_root = keccak256(&proof.account_proof[0]);
+
+        verify_account_proof(&proof, &state_root).expect("invalid account proof");
+        verify_storage_proof(&proof).expect("invalid storage proof");
+
+        // [testing] the provider inflates the balance
+        let mut forged_proof = proof.clone();
+        forged_proof.balance += U256::from(1);
+        let result = verify_account_proof(&forged_proof, &state_root);
+        assert!(result.is_err_and(|e| matches!(e, VerificationError::InvalidAccountProof { .. })));
+    }
+
+    /// Expected to verify an absent account and its zero slot through recorded mainnet trie
+    /// nodes, and to fail when the provider claims otherwise
+    #[test]
+    fn test_verify_account_proof_mainnet_absent_success() {
+        let state_root = keccak256(&mainnet_account_proof().account_proof[0]);
+        let proof = mainnet_absent_account_proof();
+
+        verify_account_proof(&proof, &state_root).expect("invalid account proof");
+        verify_storage_proof(&proof).expect("invalid storage proof");
+        assert_eq!(proof_to_account(&proof), Account::default());
+
+        // [testing] the provider claims the absent account holds a balance
+        let mut forged_proof = proof.clone();
+        forged_proof.balance = U256::from(1);
+        let result = verify_account_proof(&forged_proof, &state_root);
+        assert!(result.is_err_and(|e| matches!(e, VerificationError::InvalidAccountProof { .. })));
+
+        // [testing] the provider claims a value in a slot of its empty storage
+        let mut forged_proof = proof;
+        forged_proof.storage_proof[0].value = U256::from(1);
+        let result = verify_storage_proof(&forged_proof);
+        assert!(result.is_err_and(|e| matches!(e, VerificationError::InvalidStorageProof { .. })));
+    }
+
+    /// Expected to pass
+    #[test]
+    fn test_verify_storage_proof_success() {
+        let addr = Address::from_str(ADDRESS).expect("failed to parse address");
+        let slot = B256::with_last_byte(1);
+        let state = mock_state(addr, slot, U256::from(42));
+
+        let proof = state.get_proof(addr, &[slot]);
+
+        verify_account_proof(&proof, &state.state_root()).expect("invalid account proof");
+        verify_storage_proof(&proof).expect("invalid storage proof");
+        assert_eq!(proof.storage_proof[0].value, U256::from(42));
+    }
+
+    /// Expected to fail because the storage trie is forged by the provider
+    #[test]
+    fn test_verify_storage_proof_error_forged_storage_root() {
+        let addr = Address::from_str(ADDRESS).expect("failed to parse address");
+        let slot = B256::with_last_byte(1);
+        let trusted_state = mock_state(addr, slot, U256::from(42));
+        let forged_state = mock_state(addr, slot, U256::from(1337));
+
+        // [testing] a self-consistent proof for a storage trie that does not exist on chain
+        let forged_proof = forged_state.get_proof(addr, &[slot]);
+        assert_ne!(
+            forged_proof.storage_hash,
+            trusted_state.get_proof(addr, &[]).storage_hash
+        );
+
+        // the storage proof alone is satisfied by the forged storage root ...
+        assert!(verify_storage_proof(&forged_proof).is_ok());
+
+        // ... but the account proof anchors the storage root to the trusted state root
+        let result = verify_account_proof(&forged_proof, &trusted_state.state_root());
+
+        assert!(result.is_err_and(|e| matches!(e, VerificationError::InvalidAccountProof { .. })));
+    }
+
+    /// Expected to fail because the provider hides a stored value behind a zero
+    #[test]
+    fn test_verify_storage_proof_error_forged_zero_value() {
+        let addr = Address::from_str(ADDRESS).expect("failed to parse address");
+        let slot = B256::with_last_byte(1);
+        let state = mock_state(addr, slot, U256::from(42));
+
+        // [testing] claim the slot is empty, with the inclusion proof of its value
+        let mut proof = state.get_proof(addr, &[slot]);
+        proof.storage_proof[0].value = U256::ZERO;
+
+        let result = verify_storage_proof(&proof);
+
+        assert!(result.is_err_and(|e| matches!(e, VerificationError::InvalidStorageProof { .. })));
+    }
+
+    /// Expected to pass
+    #[test]
+    fn test_verify_code_hash_success() {
+        let addr = Address::from_str(ADDRESS).expect("failed to parse address");
+        let mut state = MockState::default();
+        state.accounts.insert(
+            addr,
+            MockAccount {
+                code: bytes!("6080604052"),
+                ..Default::default()
+            },
+        );
+
+        let proof = state.get_proof(addr, &[]);
+
+        verify_account_proof(&proof, &state.state_root()).expect("invalid account proof");
+        verify_code_hash(&proof, &state.code(addr)).expect("invalid code");
+    }
+
+    /// Expected to fail because an account without code (`KECCAK_EMPTY`) cannot have code
+    #[test]
+    fn test_verify_code_hash_error_code_for_empty_code_hash() {
+        let addr = Address::from_str(ADDRESS).expect("failed to parse address");
+        let state = mock_state(addr, B256::ZERO, U256::ZERO);
+
+        let proof = state.get_proof(addr, &[]);
+        verify_account_proof(&proof, &state.state_root()).expect("invalid account proof");
+
+        // [testing] provider injects code for an EOA
+        let result = verify_code_hash(&proof, &bytes!("6080604052"));
+
+        assert!(result.is_err_and(|e| matches!(e, VerificationError::CodeHashMismatch { .. })));
+    }
+
+    /// Expected to fail because the in-memory backend serves a forged storage trie
+    #[tokio::test]
+    async fn test_verified_rpc_client_get_storage_at_error_forged_storage_root() {
+        let addr = Address::from_str(ADDRESS).expect("failed to parse address");
+        let slot = B256::with_last_byte(1);
+        let trusted_state = mock_state(addr, slot, U256::from(42));
+
+        // [testing] the backend serves a state that differs from the trusted block
+        let mut rpc = MockRpc::default();
+        rpc.insert_block(MockBlock {
+            block: Block {
+                header: Header {
+                    number: 1,
+                    state_root: trusted_state.state_root(),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            state: mock_state(addr, slot, U256::from(1337)),
+            ..Default::default()
+        });
+        let trusted_block = rpc.blocks[&1].trusted_block();
+
+        let verified_client = VerifiedRpcClient::with_backend(rpc);
+        verified_client.state.add_trusted_blocks(&[trusted_block]);
+
+        let result = verified_client
+            .get_storage_at(addr, slot, Some(BlockNumberOrTag::Number(1)))
+            .await;
+
+        assert!(result.is_err_and(|e| matches!(
+            e,
+            RpcError::Verification(VerificationError::InvalidAccountProof { .. })
+        )));
+    }
+
+    /// Client trusting only block 5 of the mock chain
+    async fn setup_head_sync() -> (MockRpc, Arc<VerifiedRpcClient<MockRpc>>) {
+        let rpc = mock_chain(MOCK_CHAIN_LENGTH);
+        let verified_client = VerifiedRpcClient::with_backend(rpc.clone());
+        verified_client
+            .trust_block_hash(rpc.blocks[&5].block.header.hash)
+            .await
+            .expect("failed to VerifiedRpcClient.trust_block_hash");
+
+        (rpc, Arc::new(verified_client))
+    }
+
+    /// Expected to pass
+    #[tokio::test]
+    async fn test_head_sync_poll_success() {
+        let (rpc, verified_client) = setup_head_sync().await;
+        let addr = Address::from_str(ADDRESS).expect("failed to parse address");
+
+        // [testing] latest resolves to the latest trusted block, not the RPC head
+        let balance = verified_client
+            .get_balance(addr, Some(BlockNumberOrTag::Latest))
+            .await
+            .expect("failed to VerifiedRpcClient.get_balance");
+        assert_eq!(
+            balance,
+            rpc.get_balance(addr, Some(BlockNumberOrTag::Number(5)))
+                .await
+                .unwrap()
+        );
+
+        let head = HeadSync::new(
+            verified_client.clone(),
+            Duration::from_secs(12),
+            trust_on_first_use,
+        )
+        .poll()
+        .await
+        .expect("failed to HeadSync.poll");
+
+        assert_eq!(head, Some(rpc.blocks[&MOCK_CHAIN_LENGTH].trusted_block()));
+        assert_eq!(
+            verified_client
+                .state
+                .get_trusted_block(MOCK_CHAIN_LENGTH - 1),
+            Some(rpc.blocks[&(MOCK_CHAIN_LENGTH - 1)].trusted_block())
+        );
+        let balance = verified_client
+            .get_balance(addr, Some(BlockNumberOrTag::Latest))
+            .await
+            .expect("failed to VerifiedRpcClient.get_balance");
+        assert_eq!(
+            balance,
+            rpc.get_balance(addr, Some(BlockNumberOrTag::Latest))
+                .await
+                .unwrap()
+        );
+    }
+
+    /// Expected to fail because the head does not descend from the trusted block
+    #[tokio::test]
+    async fn test_head_sync_poll_error_unlinked_block() {
+        let rpc = mock_chain(MOCK_CHAIN_LENGTH);
+        let verified_client = Arc::new(VerifiedRpcClient::with_backend(rpc.clone()));
+        // [testing] trust a block 5 from another chain
+        verified_client.state.add_trusted_blocks(&[TrustedBlock {
+            hash: B256::repeat_byte(1),
+            ..rpc.blocks[&5].trusted_block()
+        }]);
+
+        let result = HeadSync::new(
+            verified_client.clone(),
+            Duration::from_secs(12),
+            trust_on_first_use,
+        )
+        .poll()
+        .await;
+
+        assert!(result.is_err_and(|e| matches!(
+            e,
+            RpcError::Verification(VerificationError::UnlinkedBlock { .. })
+        )));
+        assert_eq!(
+            verified_client
+                .state
+                .latest_trusted_block()
+                .map(|b| b.number),
+            Some(5)
+        );
+    }
+
+    /// Expected to fail because an ancestor of the head is not numbered as the parent of its child
+    #[tokio::test]
+    async fn test_head_sync_poll_error_skipped_ancestor() {
+        let mut rpc = mock_chain(MOCK_CHAIN_LENGTH);
+        let trusted_blocks: Vec<TrustedBlock> =
+            (1..=5).map(|n| rpc.blocks[&n].trusted_block()).collect();
+        // [testing] link block 7 to block 5, skipping block 6
+        rpc.relink(7, 5);
+        let verified_client = Arc::new(VerifiedRpcClient::with_backend(rpc));
+        verified_client.state.add_trusted_blocks(&trusted_blocks);
+
+        let result = HeadSync::new(
+            verified_client.clone(),
+            Duration::from_secs(12),
+            trust_on_first_use,
+        )
+        .poll()
+        .await;
+
+        assert!(result.is_err_and(|e| matches!(
+            e,
+            RpcError::Verification(VerificationError::InvalidHeader { .. })
+        )));
+        assert_eq!(
+            verified_client
+                .state
+                .latest_trusted_block()
+                .map(|b| b.number),
+            Some(5)
+        );
+    }
+
+    /// Expected to replace the trusted blocks reorged out by the new head, at its height or below
+    #[tokio::test]
+    async fn test_head_sync_poll_reorg_success() {
+        let rpc = mock_chain(MOCK_CHAIN_LENGTH);
+        let trusted_blocks: Vec<TrustedBlock> = (5..=MOCK_CHAIN_LENGTH)
+            .map(|n| rpc.blocks[&n].trusted_block())
+            .collect();
+
+        for from in [MOCK_CHAIN_LENGTH, 7] {
+            let mut fork = rpc.clone();
+            fork.fork(from);
+            let verified_client = Arc::new(VerifiedRpcClient::with_backend(fork.clone()));
+            verified_client.state.add_trusted_blocks(&trusted_blocks);
+            let mut heads = verified_client.state.subscribe_heads();
+
+            let head = HeadSync::new(
+                verified_client.clone(),
+                Duration::from_secs(12),
+                trust_on_first_use,
+            )
+            .poll()
+            .await
+            .expect("failed to HeadSync.poll");
+
+            let fork_head = fork.blocks[&MOCK_CHAIN_LENGTH].trusted_block();
+            assert_eq!(head, Some(fork_head));
+            assert_eq!(heads.try_recv().ok(), Some(fork_head));
+            for number in 5..=MOCK_CHAIN_LENGTH {
+                assert_eq!(
+                    verified_client.state.get_trusted_block(number),
+                    Some(fork.blocks[&number].trusted_block())
+                );
+            }
+        }
+    }
+
+    /// Expected to fail because the new head reorgs out the finalized block
+    #[tokio::test]
+    async fn test_head_sync_poll_error_finalized_reorg() {
+        let rpc = mock_chain(MOCK_CHAIN_LENGTH);
+        let mut fork = rpc.clone();
+        fork.fork(7);
+
+        let verified_client = Arc::new(VerifiedRpcClient::with_backend(fork));
+        verified_client
+            .state
+            .set_finalized_block(rpc.blocks[&7].trusted_block());
+        verified_client
+            .state
+            .add_trusted_blocks(&[rpc.blocks[&MOCK_CHAIN_LENGTH].trusted_block()]);
+
+        let result = HeadSync::new(
+            verified_client.clone(),
+            Duration::from_secs(12),
+            trust_on_first_use,
+        )
+        .poll()
+        .await;
+
+        assert!(result.is_err_and(|e| matches!(
+            e,
+            RpcError::Verification(VerificationError::UnlinkedBlock { .. })
+        )));
+        assert_eq!(
+            verified_client.state.latest_trusted_block(),
+            Some(rpc.blocks[&MOCK_CHAIN_LENGTH].trusted_block())
+        );
+    }
+
+    /// Expected to trust the head from the honest provider, demoting the one forging it
+    #[tokio::test]
+    async fn test_failover_rpc_forged_head_success() {
+        let rpc = mock_chain(MOCK_CHAIN_LENGTH);
+
+        // [testing] the first provider serves a forged state root for the head
+        let mut forged = rpc.clone();
+        let head = forged.blocks.get_mut(&MOCK_CHAIN_LENGTH).unwrap();
+        head.block.header.state_root = B256::ZERO;
+
+        let verified_client = Arc::new(VerifiedRpcClient::with_backend(
+            FailoverRpc::with_backends(vec![forged, rpc.clone()]),
+        ));
+        verified_client
+            .state
+            .add_trusted_blocks(&[rpc.blocks[&5].trusted_block()]);
+
+        let head = HeadSync::new(
+            verified_client.clone(),
+            Duration::from_secs(12),
+            trust_on_first_use,
+        )
+        .poll()
+        .await
+        .expect("failed to HeadSync.poll");
+
+        assert_eq!(head, Some(rpc.blocks[&MOCK_CHAIN_LENGTH].trusted_block()));
+        let scores = verified_client.backend().scores();
+        assert!(scores[0] < -INVALID_DATA_PENALTY / 2);
+        assert!(scores[1] > 0);
+    }
+
+    /// Expected to pass without trusting the head rejected by the trust callback
+    #[tokio::test]
+    async fn test_head_sync_poll_rejected_by_trust_callback() {
+        let (_, verified_client) = setup_head_sync().await;
+
+        let head = HeadSync::new(verified_client.clone(), Duration::from_secs(12), |header| {
+            header.number < 5
+        })
+        .poll()
+        .await
+        .expect("failed to HeadSync.poll");
+
+        assert_eq!(head, None);
+        assert_eq!(
+            verified_client
+                .state
+                .latest_trusted_block()
+                .map(|b| b.number),
+            Some(5)
+        );
+    }
+
+    /// Expected to keep following the head by polling once the pushed heads stream ended
+    #[tokio::test]
+    async fn test_head_sync_spawn_with_heads_ended_success() {
+        let (rpc, verified_client) = setup_head_sync().await;
+        let mut heads = verified_client.state.subscribe_heads();
+
+        // [testing] a subscription that ends before pushing any head
+        let task = HeadSync::new(
+            verified_client.clone(),
+            Duration::from_millis(10),
+            trust_on_first_use,
+        )
+        .spawn_with_heads(futures::stream::empty());
+
+        let head = tokio::time::timeout(Duration::from_secs(5), heads.recv())
+            .await
+            .expect("no head polled after the stream ended")
+            .expect("failed to receive head");
+        assert_eq!(head, rpc.blocks[&MOCK_CHAIN_LENGTH].trusted_block());
+
+        task.abort();
+    }
+
+    /// Expected to pass over WebSocket, notifying the verified heads and logs as they get trusted
+    #[tokio::test]
+    async fn test_pubsub_rpc_ws_subscribe_success() {
+        let (rpc, verified_client) = setup_head_sync().await;
+
+        let url = format!(
+            "ws://{}",
+            serve_locally(server::router(verified_client.clone())).await
+        );
+        let ws_rpc = PubSubRpc::connect_ws(&url)
+            .await
+            .expect("failed to PubSubRpc.connect_ws");
+
+        let addr = Address::from_str(ADDRESS).expect("failed to parse address");
+        let tag = Some(BlockNumberOrTag::Number(5));
+        assert_eq!(
+            ws_rpc
+                .get_balance(addr, tag)
+                .await
+                .expect("failed to PubSubRpc.get_balance"),
+            rpc.get_balance(addr, tag).await.unwrap()
+        );
+
+        let filter = Filter::new()
+            .address(MOCK_CONTRACT)
+            .event_signature(TRANSFER_TOPIC);
+        let mut heads = ws_rpc
+            .subscribe_new_heads()
+            .await
+            .expect("failed to PubSubRpc.subscribe_new_heads");
+        let mut logs = ws_rpc
+            .subscribe_logs(&filter)
+            .await
+            .expect("failed to PubSubRpc.subscribe_logs");
+
+        // [testing] trust the blocks 6 to 8 at once
+        HeadSync::new(
+            verified_client.clone(),
+            Duration::from_secs(12),
+            trust_on_first_use,
+        )
+        .poll()
+        .await
+        .expect("failed to HeadSync.poll");
+
+        let verified_logs = verified_client
+            .get_logs(&filter.clone().from_block(6).to_block(MOCK_CHAIN_LENGTH))
+            .await
+            .expect("failed to VerifiedRpcClient.get_logs");
+        for number in 6..=MOCK_CHAIN_LENGTH {
+            let head = heads.recv().await.expect("failed to receive head");
+            assert_eq!(head, rpc.blocks[&number].block.header);
+
+            let log = logs.recv().await.expect("failed to receive log");
+            assert_eq!(log, verified_logs[(number - 6) as usize]);
+        }
+
+        // subscriptions need a WebSocket connection
+        let request = br#"{"jsonrpc":"2.0","id":1,"method":"eth_subscribe","params":["newHeads"]}"#;
+        let response = server::handle_request(&verified_client, request)
+            .await
+            .expect("expected a response");
+        assert_eq!(response["error"]["code"], server::METHOD_NOT_FOUND);
+    }
+
+    /// Expected to fetch each new header once, without its transactions, for all subscribers
+    #[tokio::test]
+    async fn test_subscriptions_new_heads_success() {
+        let (rpc, verified_client) = setup_head_sync().await;
+
+        // [testing] subscribe on two connections
+        let (sender, mut notifications) = tokio::sync::mpsc::channel(server::MAX_PENDING_MESSAGES);
+        let connections = [
+            Subscriptions::new(verified_client.clone(), sender.clone()),
+            Subscriptions::new(verified_client.clone(), sender),
+        ];
+        for subscriptions in &connections {
+            subscriptions.subscribe(SubscriptionKind::NewHeads);
+        }
+
+        rpc.take_requests();
+        verified_client
+            .state
+            .add_trusted_blocks(&[rpc.blocks[&6].trusted_block()]);
+        for _ in &connections {
+            let notification = notifications.recv().await.expect("no notification");
+            let head: Header =
+                serde_json::from_value(notification["params"]["result"].clone()).unwrap();
+            assert_eq!(head, rpc.blocks[&6].block.header);
+        }
+
+        assert_eq!(rpc.take_requests(), ["eth_getBlockByHash"]);
+    }
+
+    /// Expected to close a subscription whose notifications are not read
+    #[tokio::test]
+    async fn test_subscriptions_lagging_subscriber_closed() {
+        let (rpc, verified_client) = setup_head_sync().await;
+
+        // [testing] room for a single pending notification
+        let (sender, mut notifications) = tokio::sync::mpsc::channel(1);
+        let subscriptions = Subscriptions::new(verified_client.clone(), sender);
+        subscriptions.subscribe(SubscriptionKind::NewHeads);
+
+        let trusted_blocks: Vec<TrustedBlock> = (6..MOCK_CHAIN_LENGTH)
+            .map(|n| rpc.blocks[&n].trusted_block())
+            .collect();
+        verified_client.state.add_trusted_blocks(&trusted_blocks);
+
+        let notification = notifications.recv().await.expect("no notification");
+        let head: Header =
+            serde_json::from_value(notification["params"]["result"].clone()).unwrap();
+        assert_eq!(head, rpc.blocks[&6].block.header);
+
+        // [testing] the subscription was closed on the notification of block 7, which the
+        // subscriber is told about instead, and the next head isn't notified
+        let notification = notifications.recv().await.expect("no notification");
+        assert_eq!(
+            notification["params"]["error"]["code"],
+            server::LIMIT_EXCEEDED
+        );
+        verified_client
+            .state
+            .add_trusted_blocks(&[rpc.blocks[&MOCK_CHAIN_LENGTH].trusted_block()]);
+        let next = tokio::time::timeout(Duration::from_millis(100), notifications.recv()).await;
+        assert!(next.is_err());
+    }
+
+    /// Expected to notify the blocks replacing the notified ones on a reorg
+    #[tokio::test]
+    async fn test_subscriptions_new_heads_reorg_success() {
+        let rpc = mock_chain(MOCK_CHAIN_LENGTH);
+        let mut fork = rpc.clone();
+        fork.fork(7);
+
+        let verified_client = Arc::new(VerifiedRpcClient::with_backend(fork.clone()));
+        verified_client
+            .state
+            .add_trusted_blocks(&[rpc.blocks[&5].trusted_block()]);
+
+        let (sender, mut notifications) = tokio::sync::mpsc::channel(server::MAX_PENDING_MESSAGES);
+        let subscriptions = Subscriptions::new(verified_client.clone(), sender);
+        subscriptions.subscribe(SubscriptionKind::NewHeads);
+
+        // [testing] notify the blocks 6 to 8 of the original chain, then reorg 7 and 8
+        let trusted_blocks: Vec<TrustedBlock> = (6..=MOCK_CHAIN_LENGTH)
+            .map(|n| rpc.blocks[&n].trusted_block())
+            .collect();
+        verified_client.state.add_trusted_blocks(&trusted_blocks);
+        for number in 6..=MOCK_CHAIN_LENGTH {
+            let notification = notifications.recv().await.expect("no notification");
+            let head: Header =
+                serde_json::from_value(notification["params"]["result"].clone()).unwrap();
+            assert_eq!(head, rpc.blocks[&number].block.header);
+        }
+        HeadSync::new(
+            verified_client.clone(),
+            Duration::from_secs(12),
+            trust_on_first_use,
+        )
+        .poll()
+        .await
+        .expect("failed to HeadSync.poll");
+
+        for number in 7..=MOCK_CHAIN_LENGTH {
+            let notification = notifications.recv().await.expect("no notification");
+            let head: Header =
+                serde_json::from_value(notification["params"]["result"].clone()).unwrap();
+            assert_eq!(head, fork.blocks[&number].block.header);
+        }
+    }
+
+    /// Expected to notify the logs of the reorged blocks as removed, then those replacing them
+    #[tokio::test]
+    async fn test_subscriptions_logs_reorg_success() {
+        let rpc = mock_chain(MOCK_CHAIN_LENGTH);
+        let mut fork = rpc.clone();
+        fork.fork(7);
+
+        let verified_client = Arc::new(VerifiedRpcClient::with_backend(fork.clone()));
+        verified_client
+            .state
+            .add_trusted_blocks(&[rpc.blocks[&5].trusted_block()]);
+
+        let (sender, mut notifications) = tokio::sync::mpsc::channel(server::MAX_PENDING_MESSAGES);
+        let subscriptions = Subscriptions::new(verified_client.clone(), sender);
+        let filter = Filter::new().event_signature(TRANSFER_TOPIC);
+        subscriptions.subscribe(SubscriptionKind::Logs(Box::new(filter)));
+        async fn recv_log(
+            notifications: &mut tokio::sync::mpsc::Receiver<serde_json::Value>,
+        ) -> Log {
+            let notification = notifications.recv().await.expect("no notification");
+            serde_json::from_value(notification["params"]["result"].clone()).unwrap()
+        }
+
+        // [testing] notify the logs of the blocks 6 to 8 of the original chain, then reorg
+        // 7 and 8
+        let trusted_blocks: Vec<TrustedBlock> = (6..=MOCK_CHAIN_LENGTH)
+            .map(|n| rpc.blocks[&n].trusted_block())
+            .collect();
+        verified_client.state.add_trusted_blocks(&trusted_blocks);
+        let mut notified = Vec::new();
+        for number in 6..=MOCK_CHAIN_LENGTH {
+            let log = recv_log(&mut notifications).await;
+            assert_eq!(log.block_hash, Some(rpc.blocks[&number].block.header.hash));
+            assert!(!log.removed);
+            notified.push(log);
+        }
+        HeadSync::new(
+            verified_client.clone(),
+            Duration::from_secs(12),
+            trust_on_first_use,
+        )
+        .poll()
+        .await
+        .expect("failed to HeadSync.poll");
+
+        for log in notified[1..].iter().rev() {
+            let removed = recv_log(&mut notifications).await;
+            assert!(removed.removed);
+            assert_eq!(removed.inner, log.inner);
+            assert_eq!(removed.block_hash, log.block_hash);
+        }
+        for number in 7..=MOCK_CHAIN_LENGTH {
+            let log = recv_log(&mut notifications).await;
+            assert_eq!(log.block_hash, Some(fork.blocks[&number].block.header.hash));
+            assert!(!log.removed);
+        }
+    }
+
+    /// Expected to pass over IPC, with requests split or packed across reads
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_pubsub_rpc_ipc_success() {
+        use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+        let (rpc, verified_client) = setup_head_sync().await;
+
+        let path = std::env::temp_dir().join(format!("verified-proxy-{}.ipc", std::process::id()));
+        let server = {
+            let (client, path) = (verified_client.clone(), path.clone());
+            tokio::spawn(async move { server::serve_ipc(client, &path).await })
+        };
+        while !path.exists() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        let addr = Address::from_str(ADDRESS).expect("failed to parse address");
+        let tag = Some(BlockNumberOrTag::Number(5));
+        let balance = rpc.get_balance(addr, tag).await.unwrap();
+
+        // [testing] two requests, the second one split across writes
+        let request = format!(
+            r#"{{"jsonrpc":"2.0","id":1,"method":"eth_getBalance","params":["{ADDRESS}","0x5"]}}
+            {{"jsonrpc":"2.0","id":2,"method":"eth_getBalance","params":["{ADDRESS}","0x5"]}}"#
+        );
+        let (first, second) = request.split_at(request.len() - 10);
+        let mut stream = tokio::net::UnixStream::connect(&path)
+            .await
+            .expect("failed to connect");
+        stream.write_all(first.as_bytes()).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        stream.write_all(second.as_bytes()).await.unwrap();
+
+        // [testing] requests are handled concurrently, their responses come in any order
+        let mut lines = BufReader::new(stream).lines();
+        let mut ids = Vec::new();
+        for _ in 1..=2 {
+            let line = lines
+                .next_line()
+                .await
+                .unwrap()
+                .expect("expected a response");
+            let response: serde_json::Value = serde_json::from_str(&line).unwrap();
+            assert_eq!(response["result"], format!("{balance:#x}"));
+            ids.extend(response["id"].as_u64());
+        }
+        ids.sort();
+        assert_eq!(ids, [1, 2]);
+
+        let ipc_rpc = PubSubRpc::connect_ipc(&path)
+            .await
+            .expect("failed to PubSubRpc.connect_ipc");
+        assert_eq!(
+            ipc_rpc
+                .get_balance(addr, tag)
+                .await
+                .expect("failed to PubSubRpc.get_balance"),
+            balance
+        );
+
+        let mut heads = ipc_rpc
+            .subscribe_new_heads()
+            .await
+            .expect("failed to PubSubRpc.subscribe_new_heads");
+        HeadSync::new(
+            verified_client.clone(),
+            Duration::from_secs(12),
+            trust_on_first_use,
+        )
+        .poll()
+        .await
+        .expect("failed to HeadSync.poll");
+        let head = heads.recv().await.expect("failed to receive head");
+        assert_eq!(head, rpc.blocks[&6].block.header);
+
+        server.abort();
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    /// Expected to parse values split inside strings, and to close connections sending a
+    /// message larger than the limit
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_server_ipc_message_size_limit() {
+        use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+
+        let (_, verified_client) = setup_head_sync().await;
+
+        let path =
+            std::env::temp_dir().join(format!("verified-proxy-limit-{}.ipc", std::process::id()));
+        let server = {
+            let (client, path) = (verified_client.clone(), path.clone());
+            tokio::spawn(async move { server::serve_ipc(client, &path).await })
+        };
+        while !path.exists() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        // [testing] a request split right after a brace in one of its strings
+        let request = r#"{"jsonrpc":"2.0","id":"{}","method":"eth_chainId","params":[]}"#;
+        let (first, second) = request.split_at(24);
+        let mut stream = BufReader::new(
+            tokio::net::UnixStream::connect(&path)
+                .await
+                .expect("failed to connect"),
+        );
+        stream.write_all(first.as_bytes()).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        stream.write_all(second.as_bytes()).await.unwrap();
+        let mut line = String::new();
+        stream.read_line(&mut line).await.unwrap();
+        let response: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(response["id"], "{}");
+
+        // [testing] an object that never ends
+        let message = format!(r#"{{"data":"{}"#, "0".repeat(server::MAX_IPC_MESSAGE_SIZE));
+        let _ = stream.write_all(message.as_bytes()).await;
+        let mut rest = Vec::new();
+        let read = tokio::time::timeout(Duration::from_secs(5), stream.read_to_end(&mut rest))
+            .await
+            .expect("expected the connection to be closed");
+        assert!(read.is_err() || rest.is_empty());
+
+        server.abort();
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(feature = "consensus")]
+    mod consensus {
+        use super::*;
+
+        use crate::consensus::test_utils::{
+            mock_beacon_chain, mock_beacon_chain_with_config, mock_electra_chain_config,
+            serve_mock_beacon_api, MockCommittee,
+        };
+        use crate::consensus::{
+            ConsensusClient, CURRENT_SYNC_COMMITTEE_GINDEX, EXECUTION_PAYLOAD_GINDEX,
+            FINALIZED_ROOT_GINDEX, NEXT_SYNC_COMMITTEE_GINDEX, SYNC_COMMITTEE_SIZE,
+        };
+        use crate::errors::ConsensusError;
+
+        /// Expected to pass
+        #[tokio::test]
+        async fn test_consensus_client_sync_trusted_blocks_success() {
+            let rpc = mock_chain(MOCK_CHAIN_LENGTH);
+            let chain = mock_beacon_chain(&rpc);
+            let mut consensus_client = ConsensusClient::new(
+                &serve_mock_beacon_api(chain.clone()).await,
+                chain.checkpoint,
+                chain.config,
+            );
+
+            let verified_client = VerifiedRpcClient::with_backend(rpc.clone());
+            consensus_client
+                .sync_trusted_blocks(&verified_client)
+                .await
+                .expect("failed to ConsensusClient.sync_trusted_blocks");
+
+            let finalized_block = rpc.blocks[&(MOCK_CHAIN_LENGTH - 2)].trusted_block();
+            assert_eq!(
+                consensus_client.finalized_block_hash(),
+                Some(finalized_block.hash)
+            );
+            assert_eq!(
+                verified_client.state.finalized_block(),
+                Some(finalized_block)
+            );
+            assert_eq!(
+                verified_client.state.latest_trusted_block(),
+                Some(rpc.blocks[&MOCK_CHAIN_LENGTH].trusted_block())
+            );
+
+            let addr = Address::from_str(ADDRESS).expect("failed to parse address");
+            let balance = verified_client
+                .get_balance(addr, Some(BlockNumberOrTag::Number(MOCK_CHAIN_LENGTH)))
+                .await
+                .expect("failed to VerifiedRpcClient.get_balance");
+            assert_eq!(
+                balance,
+                rpc.get_balance(addr, Some(BlockNumberOrTag::Number(MOCK_CHAIN_LENGTH)))
+                    .await
+                    .unwrap()
+            );
+        }
+
+        /// Expected to pass with the beacon state proofs of Electra, one level deeper
+        #[tokio::test]
+        async fn test_consensus_client_sync_trusted_blocks_electra_success() {
+            let rpc = mock_chain(MOCK_CHAIN_LENGTH);
+            let chain = mock_beacon_chain_with_config(&rpc, mock_electra_chain_config());
+            assert_eq!(chain.bootstrap.current_sync_committee_branch.len(), 6);
+            let mut consensus_client = ConsensusClient::new(
+                &serve_mock_beacon_api(chain.clone()).await,
+                chain.checkpoint,
+                chain.config,
+            );
+
+            let verified_client = VerifiedRpcClient::with_backend(rpc.clone());
+            consensus_client
+                .sync_trusted_blocks(&verified_client)
+                .await
+                .expect("failed to ConsensusClient.sync_trusted_blocks");
+
+            assert_eq!(
+                verified_client.state.finalized_block(),
+                Some(rpc.blocks[&(MOCK_CHAIN_LENGTH - 2)].trusted_block())
+            );
+            assert_eq!(
+                verified_client.state.latest_trusted_block(),
+                Some(rpc.blocks[&MOCK_CHAIN_LENGTH].trusted_block())
+            );
+        }
+
+        /// Expected to match the generalized indices of the spec, before and after Electra
+        #[test]
+        fn test_consensus_gindices_success() {
+            let gindex =
+                |(depth, index): (usize, usize), offset: usize| (1 << (depth + offset)) + index;
+
+            assert_eq!(gindex(EXECUTION_PAYLOAD_GINDEX, 0), 25);
+            assert_eq!(gindex(FINALIZED_ROOT_GINDEX, 0), 105);
+            assert_eq!(gindex(CURRENT_SYNC_COMMITTEE_GINDEX, 0), 54);
+            assert_eq!(gindex(NEXT_SYNC_COMMITTEE_GINDEX, 0), 55);
+            assert_eq!(gindex(FINALIZED_ROOT_GINDEX, 1), 169);
+            assert_eq!(gindex(CURRENT_SYNC_COMMITTEE_GINDEX, 1), 86);
+            assert_eq!(gindex(NEXT_SYNC_COMMITTEE_GINDEX, 1), 87);
+        }
+
+        /// Expected to drop the trusted blocks of the previous optimistic head once the
+        /// light client follows another fork, the blocks synced on demand from it included
+        #[tokio::test]
+        async fn test_consensus_client_sync_trusted_blocks_reorg_success() {
+            let rpc = mock_chain(MOCK_CHAIN_LENGTH);
+            let mut fork = rpc.clone();
+            fork.fork(MOCK_CHAIN_LENGTH - 1);
+            let verified_client = VerifiedRpcClient::with_backend(fork.clone());
+
+            for execution in [&rpc, &fork] {
+                let chain = mock_beacon_chain(execution);
+                let mut consensus_client = ConsensusClient::new(
+                    &serve_mock_beacon_api(chain.clone()).await,
+                    chain.checkpoint,
+                    chain.config,
+                );
+                consensus_client
+                    .sync_trusted_blocks(&verified_client)
+                    .await
+                    .expect("failed to ConsensusClient.sync_trusted_blocks");
+            }
+
+            for number in MOCK_CHAIN_LENGTH - 2..=MOCK_CHAIN_LENGTH {
+                assert_eq!(
+                    verified_client.state.get_trusted_block(number),
+                    Some(fork.blocks[&number].trusted_block())
+                );
+            }
+        }
+
+        /// Expected to ignore both the optimistic head and the finality of updates
+        /// signed by less than half of the highest participation
+        #[tokio::test]
+        async fn test_consensus_client_sync_weak_updates_success() {
+            let rpc = mock_chain(MOCK_CHAIN_LENGTH);
+            let mut chain = mock_beacon_chain(&rpc);
+            // [testing] sign the latest updates by a third of the committee
+            let committee = MockCommittee::new(1);
+            let update = &mut chain.finality_update;
+            update.sync_aggregate = committee.sign_by(
+                &chain.config,
+                &update.attested_header.beacon,
+                update.signature_slot,
+                SYNC_COMMITTEE_SIZE / 3,
+            );
+            let update = &mut chain.optimistic_update;
+            update.sync_aggregate = committee.sign_by(
+                &chain.config,
+                &update.attested_header.beacon,
+                update.signature_slot,
+                SYNC_COMMITTEE_SIZE / 3,
+            );
+            let mut consensus_client = ConsensusClient::new(
+                &serve_mock_beacon_api(chain.clone()).await,
+                chain.checkpoint,
+                chain.config,
+            );
+
+            let verified_client = VerifiedRpcClient::with_backend(rpc.clone());
+            consensus_client
+                .sync_trusted_blocks(&verified_client)
+                .await
+                .expect("failed to ConsensusClient.sync_trusted_blocks");
+
+            assert_eq!(
+                consensus_client.finalized_block_hash(),
+                Some(chain.updates[0].finalized_header.execution.block_hash)
+            );
+            assert_eq!(
+                consensus_client.optimistic_block_hash(),
+                Some(chain.updates[0].attested_header.execution.block_hash)
+            );
+            assert_eq!(
+                verified_client.state.latest_trusted_block(),
+                Some(rpc.blocks[&3].trusted_block())
+            );
+        }
+
+        /// Expected to fail
+        #[tokio::test]
+        async fn test_consensus_client_bootstrap_error_invalid_checkpoint() {
+            let chain = mock_beacon_chain(&mock_chain(MOCK_CHAIN_LENGTH));
+            let mut consensus_client = ConsensusClient::new(
+                &serve_mock_beacon_api(chain.clone()).await,
+                B256::repeat_byte(1),
+                chain.config,
+            );
+
+            let result = consensus_client.bootstrap().await;
+
+            assert!(result.is_err_and(|e| matches!(e, ConsensusError::InvalidCheckpoint { .. })));
+        }
+
+        /// Expected to fail
+        #[tokio::test]
+        async fn test_consensus_client_sync_error_invalid_signature() {
+            let mut chain = mock_beacon_chain(&mock_chain(MOCK_CHAIN_LENGTH));
+            // [testing] claim a participant that did not sign
+            let mut bits = chain
+                .finality_update
+                .sync_aggregate
+                .sync_committee_bits
+                .to_vec();
+            bits[0] = 0xfe;
+            chain.finality_update.sync_aggregate.sync_committee_bits = bits.into();
+            let mut consensus_client = ConsensusClient::new(
+                &serve_mock_beacon_api(chain.clone()).await,
+                chain.checkpoint,
+                chain.config,
+            );
+
+            let result = consensus_client.sync().await;
+
+            assert!(result.is_err_and(|e| matches!(e, ConsensusError::InvalidSignature)));
+            assert_ne!(
+                consensus_client.finalized_block_hash(),
+                Some(chain.finality_update.finalized_header.execution.block_hash)
+            );
+        }
+
+        /// Expected to fail
+        #[tokio::test]
+        async fn test_consensus_client_sync_error_forged_execution_payload() {
+            let mut chain = mock_beacon_chain(&mock_chain(MOCK_CHAIN_LENGTH));
+            // [testing] point the finalized block at a forged state
+            chain.finality_update.finalized_header.execution.state_root = B256::repeat_byte(1);
+            let mut consensus_client = ConsensusClient::new(
+                &serve_mock_beacon_api(chain.clone()).await,
+                chain.checkpoint,
+                chain.config,
+            );
+
+            let result = consensus_client.sync().await;
+
+            assert!(result.is_err_and(|e| matches!(
+                e,
+                ConsensusError::InvalidMerkleBranch("execution payload")
+            )));
+        }
+
+        /// Expected to fail without panicking, the extra data being over the SSZ limit
+        #[tokio::test]
+        async fn test_consensus_client_sync_error_extra_data_too_long() {
+            let mut chain = mock_beacon_chain(&mock_chain(MOCK_CHAIN_LENGTH));
+            // [testing] serve an execution payload with oversized extra data
+            chain.finality_update.finalized_header.execution.extra_data = vec![0; 33].into();
+            let mut consensus_client = ConsensusClient::new(
+                &serve_mock_beacon_api(chain.clone()).await,
+                chain.checkpoint,
+                chain.config,
+            );
+
+            let result = consensus_client.sync().await;
+
+            assert!(result.is_err_and(|e| matches!(e, ConsensusError::ExtraDataTooLong(33))));
+        }
+    }
 }
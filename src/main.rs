@@ -1,6 +1,270 @@
-use eyre::{Ok, Result};
+use std::net::{IpAddr, SocketAddr};
+#[cfg(unix)]
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use alloy::primitives::B256;
+use alloy::pubsub::SubscriptionStream;
+use alloy::rpc::types::{BlockNumberOrTag, Header};
+use clap::Parser;
+use eyre::Result;
+
+use rusty_verified_eth_rpc_proxy::common::ExecutionRpc;
+#[cfg(feature = "consensus")]
+use rusty_verified_eth_rpc_proxy::consensus::{ChainConfig, ConsensusClient};
+use rusty_verified_eth_rpc_proxy::failover_rpc::FailoverRpc;
+use rusty_verified_eth_rpc_proxy::head_sync::{trust_on_first_use, HeadSync};
+use rusty_verified_eth_rpc_proxy::pubsub_rpc::PubSubRpc;
+use rusty_verified_eth_rpc_proxy::server;
+use rusty_verified_eth_rpc_proxy::verified_rpc_client::VerifiedRpcClient;
+
+const SLOT_DURATION: Duration = Duration::from_secs(12);
+
+/// Verified Ethereum JSON-RPC proxy
+#[derive(Debug, Parser)]
+#[command(version, about)]
+struct Args {
+    /// URL of the untrusted execution JSON-RPC provider, over HTTP or WebSocket (`ws://`, `wss://`),
+    /// or path of its IPC socket. Several comma-separated HTTP URLs fail over to one another.
+    #[arg(
+        long,
+        env = "EXECUTION_RPC_URL",
+        required = true,
+        value_delimiter = ','
+    )]
+    execution_rpc: Vec<String>,
+
+    /// Address to listen on
+    #[arg(long, default_value = "127.0.0.1")]
+    host: IpAddr,
+
+    /// HTTP and WebSocket port to listen on
+    #[arg(long, env = "PORT", default_value_t = 8545)]
+    port: u16,
+
+    /// Path of a Unix domain socket to serve JSON-RPC on too
+    #[cfg(unix)]
+    #[arg(long)]
+    ipc_path: Option<PathBuf>,
+
+    /// Block number to mark as trusted at startup (defaults to the latest block)
+    #[arg(long, conflicts_with = "trusted_block_hash")]
+    trusted_block: Option<u64>,
+
+    /// Hash of the block to mark as trusted at startup, its header is verified against it
+    #[arg(long)]
+    trusted_block_hash: Option<B256>,
+
+    /// Without a light client, follow the chain head of the execution RPC: any new head
+    /// descending from the starting block is trusted (trust-on-first-use)
+    #[arg(long)]
+    trust_execution_heads: bool,
+
+    /// Chain id calls are executed with, checked against the execution RPC
+    /// (defaults to the chain of the light client, or else to the one of the execution RPC)
+    #[arg(long, env = "CHAIN_ID")]
+    chain_id: Option<u64>,
+
+    /// URL of the untrusted beacon API, trusted blocks are then acquired by the light client
+    #[cfg(feature = "consensus")]
+    #[arg(long, env = "CONSENSUS_RPC_URL", requires = "checkpoint")]
+    consensus_rpc: Option<String>,
+
+    /// Weak subjectivity checkpoint, the root of a recent finalized beacon block
+    #[cfg(feature = "consensus")]
+    #[arg(long, env = "CHECKPOINT")]
+    checkpoint: Option<B256>,
+}
 
 #[tokio::main]
 async fn main() -> Result<()> {
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            tracing_subscriber::EnvFilter::try_from_default_env().unwrap_or_else(|_| "info".into()),
+        )
+        .init();
+
+    let args = Args::parse();
+
+    let [rpc] = args.execution_rpc.as_slice() else {
+        let rpcs: Vec<&str> = args.execution_rpc.iter().map(String::as_str).collect();
+        let backend = FailoverRpc::new(&rpcs)?;
+        return run(&args, VerifiedRpcClient::with_backend(backend), None).await;
+    };
+
+    if rpc.starts_with("ws://") || rpc.starts_with("wss://") {
+        let backend = PubSubRpc::connect_ws(rpc).await?;
+        let heads = backend.subscribe_new_heads().await?.into_stream();
+        run(&args, VerifiedRpcClient::with_backend(backend), Some(heads)).await
+    } else if rpc.contains("://") {
+        run(&args, VerifiedRpcClient::new(rpc)?, None).await
+    } else {
+        let backend = PubSubRpc::connect_ipc(rpc).await?;
+        let heads = backend.subscribe_new_heads().await?.into_stream();
+        run(&args, VerifiedRpcClient::with_backend(backend), Some(heads)).await
+    }
+}
+
+/// Follow the trusted chain and serve the proxy, over IPC too if configured.
+/// New heads are pushed by `heads` if the execution RPC supports subscriptions, polled otherwise.
+async fn run<B: ExecutionRpc + 'static>(
+    args: &Args,
+    verified_client: VerifiedRpcClient<B>,
+    heads: Option<SubscriptionStream<Header>>,
+) -> Result<()> {
+    let chain_id = resolve_chain_id(args, &verified_client).await?;
+    let verified_client = Arc::new(verified_client.with_chain_id(chain_id));
+
+    let capabilities = verified_client.detect_capabilities().await;
+    tracing::info!("execution RPC capabilities: {capabilities}");
+    if !capabilities.get_proof {
+        tracing::warn!("the execution RPC doesn't support eth_getProof, no state can be verified");
+    }
+
+    if !start_trusted_chain(args, verified_client.clone()).await? {
+        if !args.trust_execution_heads {
+            eyre::bail!(
+                "no trust source for new heads: pass --consensus-rpc and --checkpoint, \
+                 or --trust-execution-heads to follow the execution RPC"
+            );
+        }
+        tracing::warn!(
+            "following the execution RPC head without a light client: any new head descending \
+             from the starting block is trusted (trust-on-first-use), a malicious execution RPC \
+             can serve a forged chain"
+        );
+
+        // New heads are trusted once linked to the starting block
+        let head_sync = HeadSync::new(verified_client.clone(), SLOT_DURATION, trust_on_first_use);
+        match heads {
+            Some(heads) => head_sync.spawn_with_heads(heads),
+            None => head_sync.spawn(),
+        };
+    }
+
+    let addr = SocketAddr::new(args.host, args.port);
+    #[cfg(unix)]
+    if let Some(ipc_path) = &args.ipc_path {
+        tokio::try_join!(
+            server::serve(verified_client.clone(), addr),
+            server::serve_ipc(verified_client, ipc_path),
+        )?;
+        return Ok(());
+    }
+
+    server::serve(verified_client, addr).await
+}
+
+/// The chain id from the configuration, checked against the one served by the execution RPC
+async fn resolve_chain_id<B: ExecutionRpc>(
+    args: &Args,
+    verified_client: &VerifiedRpcClient<B>,
+) -> Result<u64> {
+    let served = verified_client.backend().get_chain_id().await?;
+
+    let expected = args.chain_id;
+    #[cfg(feature = "consensus")]
+    let expected = match (expected, &args.consensus_rpc) {
+        (_, None) => expected,
+        (Some(chain_id), Some(_)) if chain_id != ChainConfig::mainnet().chain_id => {
+            eyre::bail!("chain id {chain_id} differs from the light client chain")
+        }
+        (_, Some(_)) => Some(ChainConfig::mainnet().chain_id),
+    };
+
+    match expected {
+        Some(chain_id) if chain_id != served => {
+            eyre::bail!("the execution RPC serves chain {served}, expected chain {chain_id}")
+        }
+        Some(chain_id) => Ok(chain_id),
+        None => {
+            tracing::warn!(
+                "executing calls on chain {served} as reported by the execution RPC, \
+                 set --chain-id to check it"
+            );
+            Ok(served)
+        }
+    }
+}
+
+/// Acquire the first trusted blocks, from the light client if configured.
+/// Returns `false` if the following heads must be acquired from the execution RPC.
+async fn start_trusted_chain<B: ExecutionRpc + 'static>(
+    args: &Args,
+    verified_client: Arc<VerifiedRpcClient<B>>,
+) -> Result<bool> {
+    #[cfg(feature = "consensus")]
+    if start_light_client(args, verified_client.clone()).await? {
+        return Ok(true);
+    }
+
+    trust_starting_block(args, &verified_client).await?;
+
+    Ok(false)
+}
+
+/// Sync the light client and keep following the beacon chain in the background.
+/// Returns `false` when no beacon API is configured.
+#[cfg(feature = "consensus")]
+async fn start_light_client<B: ExecutionRpc + 'static>(
+    args: &Args,
+    verified_client: Arc<VerifiedRpcClient<B>>,
+) -> Result<bool> {
+    let (Some(consensus_rpc), Some(checkpoint)) = (&args.consensus_rpc, args.checkpoint) else {
+        return Ok(false);
+    };
+
+    let mut consensus_client =
+        ConsensusClient::new(consensus_rpc, checkpoint, ChainConfig::mainnet());
+    consensus_client
+        .sync_trusted_blocks(&verified_client)
+        .await?;
+    tracing::info!(
+        "light client synced, finalized block {:?}",
+        consensus_client.finalized_block_hash()
+    );
+
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(SLOT_DURATION).await;
+            if let Err(err) = consensus_client.sync_trusted_blocks(&verified_client).await {
+                tracing::warn!("light client sync failed: {err}");
+            }
+        }
+    });
+
+    Ok(true)
+}
+
+/// Trust the starting block, either from its hash or as reported by the execution RPC itself
+async fn trust_starting_block<B: ExecutionRpc>(
+    args: &Args,
+    verified_client: &VerifiedRpcClient<B>,
+) -> Result<()> {
+    if let Some(hash) = args.trusted_block_hash {
+        verified_client
+            .retry_invalid(|| verified_client.trust_block_hash(hash))
+            .await?;
+        return Ok(());
+    }
+
+    let tag = args
+        .trusted_block
+        .map_or(BlockNumberOrTag::Latest, BlockNumberOrTag::Number);
+    let block = verified_client
+        .backend()
+        .get_block_by_number(Some(tag), false)
+        .await?;
+    tracing::warn!(
+        "trusting block {} ({}) as reported by the execution RPC",
+        block.header.number,
+        block.header.hash
+    );
+
+    verified_client
+        .retry_invalid(|| verified_client.trust_block_hash(block.header.hash))
+        .await?;
+
     Ok(())
 }
@@ -1,13 +1,29 @@
-use alloy::consensus::constants::KECCAK_EMPTY;
-use alloy::consensus::Account;
-use alloy::primitives::{keccak256, Bytes, B256};
+use alloy::consensus::constants::{EMPTY_ROOT_HASH, KECCAK_EMPTY};
+use alloy::consensus::{Account, Header as ConsensusHeader, SignableTransaction, TxEnvelope};
+use alloy::eips::eip2718::Encodable2718;
+use alloy::primitives::{keccak256, BlockHash, BlockNumber, Bytes, B256};
 use alloy::rlp;
-use alloy::rpc::types::EIP1186AccountProofResponse;
+use alloy::rpc::types::{EIP1186AccountProofResponse, Header, Transaction, TransactionReceipt};
 use alloy_trie::proof::verify_proof as mpt_verify_proof;
 use alloy_trie::Nibbles;
-use eyre::{eyre, Ok, Result};
+
+use crate::errors::VerificationError;
+
+/// Whether the proof is for an empty account, most likely one that does not exist.
+/// Providers return such an account zeroed (some zero its hashes as well), with a proof
+/// of its absence from the state trie, or of its inclusion if it exists while empty.
+pub fn is_absent_account(proof: &EIP1186AccountProofResponse) -> bool {
+    proof.nonce == 0
+        && proof.balance.is_zero()
+        && (proof.storage_hash == EMPTY_ROOT_HASH || proof.storage_hash.is_zero())
+        && (proof.code_hash == KECCAK_EMPTY || proof.code_hash.is_zero())
+}
 
 pub fn proof_to_account(proof: &EIP1186AccountProofResponse) -> Account {
+    if is_absent_account(proof) {
+        return Account::default();
+    }
+
     Account {
         nonce: proof.nonce,
         balance: proof.balance,
@@ -21,7 +37,7 @@ pub fn verify_rpc_proof(
     proof: &EIP1186AccountProofResponse,
     code: &Bytes,
     state_root: &B256,
-) -> Result<()> {
+) -> Result<(), VerificationError> {
     verify_account_proof(proof, state_root)?;
     verify_storage_proof(proof)?;
     verify_code_hash(proof, code)?;
@@ -29,52 +45,174 @@ pub fn verify_rpc_proof(
     Ok(())
 }
 
-pub fn verify_account_proof(proof: &EIP1186AccountProofResponse, state_root: &B256) -> Result<()> {
+pub fn verify_account_proof(
+    proof: &EIP1186AccountProofResponse,
+    state_root: &B256,
+) -> Result<(), VerificationError> {
     let account_key = Nibbles::unpack(keccak256(proof.address));
-    let account = proof_to_account(proof);
-    let account_encoded_value = rlp::encode(account);
-
-    mpt_verify_proof(
-        *state_root,
-        account_key,
-        account_encoded_value.into(),
-        &proof.account_proof,
-    )
-    .map_err(|e| eyre!("Failed to verify account proof: {e}"))
+    let verify = |expected| {
+        mpt_verify_proof(
+            *state_root,
+            account_key.clone(),
+            expected,
+            &proof.account_proof,
+        )
+        .map_err(Box::new)
+    };
+
+    let result = if is_absent_account(proof) {
+        // Empty accounts are removed from the state trie since EIP-161, but they may still be
+        // in it (before Spurious Dragon, or on chains without it): either proof holds
+        verify(None).or_else(|e| verify(Some(rlp::encode(Account::default()))).map_err(|_| e))
+    } else {
+        verify(Some(rlp::encode(proof_to_account(proof))))
+    };
+
+    result.map_err(|source| VerificationError::InvalidAccountProof {
+        address: proof.address,
+        source,
+    })
 }
 
-pub fn verify_storage_proof(proof: &EIP1186AccountProofResponse) -> Result<()> {
+pub fn verify_storage_proof(proof: &EIP1186AccountProofResponse) -> Result<(), VerificationError> {
+    let storage_root = proof_to_account(proof).storage_root;
+
     for storage_proof in &proof.storage_proof {
         let key = storage_proof.key.0;
         let key_hash = keccak256(key);
         let key_nibbles = Nibbles::unpack(key_hash);
-        let encoded_value = rlp::encode(storage_proof.value);
+        // Zero slots are not stored in the trie, they must be proven absent
+        let expected = (!storage_proof.value.is_zero()).then(|| rlp::encode(storage_proof.value));
 
-        mpt_verify_proof(
-            proof.storage_hash,
-            key_nibbles,
-            encoded_value.into(),
-            &storage_proof.proof,
-        )
-        .map_err(|e| eyre!("Failed to verify storage proof: {e}"))?;
+        mpt_verify_proof(storage_root, key_nibbles, expected, &storage_proof.proof).map_err(
+            |source| VerificationError::InvalidStorageProof {
+                address: proof.address,
+                slot: key,
+                source: Box::new(source),
+            },
+        )?;
     }
 
     Ok(())
 }
 
-pub fn verify_code_hash(proof: &EIP1186AccountProofResponse, code: &Bytes) -> Result<()> {
-    if proof.code_hash != KECCAK_EMPTY {
-        let code_hash = keccak256(&code);
-
-        if proof.code_hash != code_hash {
-            return Err(eyre!(
-                "Code hash mismatch for address {:?}: expected {:?}, got {:?}",
-                proof.address,
-                proof.code_hash,
-                code_hash
-            ));
+pub fn verify_code_hash(
+    proof: &EIP1186AccountProofResponse,
+    code: &Bytes,
+) -> Result<(), VerificationError> {
+    // Note: keccak256 of empty code is `KECCAK_EMPTY`,
+    // so any code returned for an account without code is rejected here as well
+    let expected = proof_to_account(proof).code_hash;
+    let code_hash = keccak256(code);
+
+    if expected != code_hash {
+        return Err(VerificationError::CodeHashMismatch {
+            address: proof.address,
+            expected,
+            got: code_hash,
+        });
+    }
+
+    Ok(())
+}
+
+/// Verify that the RLP encoding of `header` hashes to the trusted `hash`.
+/// The `hash` field of the RPC header is ignored, it is as untrusted as the rest.
+pub fn verify_block_header(
+    header: &Header,
+    hash: &BlockHash,
+) -> Result<ConsensusHeader, VerificationError> {
+    let consensus_header = ConsensusHeader::try_from(header.clone()).map_err(|e| {
+        VerificationError::InvalidHeader {
+            hash: *hash,
+            reason: e.to_string(),
         }
+    })?;
+
+    let got = consensus_header.hash_slow();
+    if got != *hash {
+        return Err(VerificationError::BlockHashMismatch {
+            expected: *hash,
+            got,
+        });
+    }
+
+    Ok(consensus_header)
+}
+
+/// Verify the fields the RPC derives for the transaction at `index` of the trusted
+/// block (hash, sender and position), returning its EIP-2718 encoding for the
+/// transactions root.
+pub fn verify_transaction(
+    tx: &Transaction,
+    block_hash: &BlockHash,
+    block_number: BlockNumber,
+    index: usize,
+) -> Result<Vec<u8>, VerificationError> {
+    let invalid = || VerificationError::InvalidTransaction {
+        tx_hash: tx.hash,
+        block_number,
+    };
+
+    let envelope = transaction_envelope(tx, block_number)?;
+    let from = envelope.recover_signer().map_err(|_| invalid())?;
+
+    if *envelope.tx_hash() != tx.hash
+        || from != tx.from
+        || tx.block_hash != Some(*block_hash)
+        || tx.block_number != Some(block_number)
+        || tx.transaction_index != Some(index as u64)
+    {
+        return Err(invalid());
+    }
+
+    Ok(envelope.encoded_2718())
+}
+
+/// Verify the fields the RPC derives for the receipt of the transaction `tx_hash` at
+/// `index` of the trusted block (transaction hash and position), none of which the
+/// receipts root commits to.
+pub fn verify_receipt(
+    receipt: &TransactionReceipt,
+    tx_hash: B256,
+    block_hash: &BlockHash,
+    block_number: BlockNumber,
+    index: usize,
+) -> Result<(), VerificationError> {
+    if receipt.transaction_hash != tx_hash
+        || receipt.block_hash != Some(*block_hash)
+        || receipt.block_number != Some(block_number)
+        || receipt.transaction_index != Some(index as u64)
+    {
+        return Err(VerificationError::InvalidReceipt {
+            tx_hash,
+            block_number,
+        });
     }
 
     Ok(())
 }
+
+/// The signed transaction of `tx`, as committed to by the transactions root.
+/// None of the fields the RPC derives are checked, nor is the signature.
+pub fn transaction_envelope(
+    tx: &Transaction,
+    block_number: BlockNumber,
+) -> Result<TxEnvelope, VerificationError> {
+    let envelope =
+        TxEnvelope::try_from(tx.clone()).map_err(|_| VerificationError::InvalidTransaction {
+            tx_hash: tx.hash,
+            block_number,
+        })?;
+
+    Ok(match envelope {
+        // the chain id of legacy transactions is part of their signature, providers report
+        // 0 or none for the ones predating EIP-155
+        TxEnvelope::Legacy(signed) => {
+            let (mut legacy, signature, _) = signed.into_parts();
+            legacy.chain_id = signature.v().chain_id();
+            legacy.into_signed(signature).into()
+        }
+        envelope => envelope,
+    })
+}
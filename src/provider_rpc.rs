@@ -0,0 +1,477 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use alloy::consensus::constants::EMPTY_ROOT_HASH;
+use alloy::consensus::Account;
+use alloy::primitives::{keccak256, Address, BlockHash, Bytes, B256, U256, U64};
+use alloy::providers::{Provider, RootProvider};
+use alloy::rpc::json_rpc::{RpcParam, RpcReturn};
+use alloy::rpc::types::{
+    AccessListResult, Block, BlockNumberOrTag, BlockTransactionsKind, EIP1186AccountProofResponse,
+    Filter, Log, Transaction, TransactionReceipt, TransactionRequest,
+};
+use alloy::transports::Transport;
+
+use crate::common::{Capabilities, ExecutionRpc, RpcVerifiableMethods};
+use crate::errors::RpcError;
+
+/// Untrusted RPC reached through an alloy provider, over any transport
+/// (see [`HttpRpc`](crate::http_rpc::HttpRpc) and [`PubSubRpc`](crate::pubsub_rpc::PubSubRpc)).
+pub struct ProviderRpc<T> {
+    provider: RootProvider<T>,
+    /// Set once the RPC rejected `eth_getAccount`, accounts are then built from other methods
+    account_unsupported: AtomicBool,
+    /// Set once the RPC rejected `eth_getProof`
+    proof_unsupported: AtomicBool,
+}
+
+impl<T: Transport + Clone> ProviderRpc<T> {
+    pub fn with_provider(provider: RootProvider<T>) -> Self {
+        Self {
+            provider,
+            account_unsupported: AtomicBool::new(false),
+            proof_unsupported: AtomicBool::new(false),
+        }
+    }
+
+    pub fn provider(&self) -> &RootProvider<T> {
+        &self.provider
+    }
+
+    pub async fn raw_request<P: RpcParam, R: RpcReturn>(
+        &self,
+        method: &str,
+        params: P,
+    ) -> Result<R, RpcError> {
+        let response = self
+            .provider
+            .raw_request(method.to_string().into(), params)
+            .await
+            .map_err(|source| RpcError::Transport {
+                method: method.to_string(),
+                source,
+            })?;
+
+        Ok(response)
+    }
+
+    /// RPC method: `eth_getProof`, without storage slots
+    async fn get_account_proof(
+        &self,
+        address: Address,
+        tag: BlockNumberOrTag,
+    ) -> Result<EIP1186AccountProofResponse, RpcError> {
+        self.provider
+            .get_proof(address, vec![])
+            .block_id(tag.into())
+            .await
+            .map_err(|source| RpcError::Transport {
+                method: "eth_getProof".to_string(),
+                source,
+            })
+    }
+}
+
+/// Whether the RPC didn't reject the method of the call, whatever else went wrong
+fn is_supported<R>(result: &Result<R, RpcError>) -> bool {
+    !matches!(result, Err(e) if e.is_unsupported_method())
+}
+
+impl<T: Transport + Clone> RpcVerifiableMethods for ProviderRpc<T> {
+    async fn get_account(
+        &self,
+        address: Address,
+        _: Option<&[B256]>,
+        tag: Option<BlockNumberOrTag>,
+    ) -> Result<Account, RpcError> {
+        let tag = tag.unwrap_or(BlockNumberOrTag::Latest);
+
+        if !self.account_unsupported.load(Ordering::Relaxed) {
+            match self.raw_request("eth_getAccount", (address, tag)).await {
+                Err(e) if e.is_unsupported_method() => {
+                    tracing::info!(
+                        "eth_getAccount is not supported, building accounts from other methods: {e}"
+                    );
+                    self.account_unsupported.store(true, Ordering::Relaxed);
+                }
+                result => return result,
+            }
+        }
+
+        if !self.proof_unsupported.load(Ordering::Relaxed) {
+            match self.get_account_proof(address, tag).await {
+                Ok(proof) => {
+                    return Ok(Account {
+                        nonce: proof.nonce,
+                        balance: proof.balance,
+                        storage_root: proof.storage_hash,
+                        code_hash: proof.code_hash,
+                    })
+                }
+                Err(e) if e.is_unsupported_method() => {
+                    tracing::info!("eth_getProof is not supported: {e}");
+                    self.proof_unsupported.store(true, Ordering::Relaxed);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        let tag = Some(tag);
+        let (balance, nonce, code) = tokio::try_join!(
+            self.get_balance(address, tag),
+            self.get_nonce(address, tag),
+            self.get_code(address, tag),
+        )?;
+
+        // The storage root is only served along with a proof. An account without code has no
+        // storage, otherwise it is left zeroed (see `RpcVerifiableMethods::get_account`)
+        let storage_root = if code.is_empty() {
+            EMPTY_ROOT_HASH
+        } else {
+            B256::ZERO
+        };
+        Ok(Account {
+            nonce,
+            balance,
+            storage_root,
+            code_hash: keccak256(code),
+        })
+    }
+
+    async fn get_balance(
+        &self,
+        address: Address,
+        tag: Option<BlockNumberOrTag>,
+    ) -> Result<U256, RpcError> {
+        let tag = tag.unwrap_or(BlockNumberOrTag::Latest);
+
+        let balance = self.raw_request("eth_getBalance", (address, tag)).await?;
+
+        Ok(balance)
+    }
+
+    async fn get_nonce(
+        &self,
+        address: Address,
+        tag: Option<BlockNumberOrTag>,
+    ) -> Result<u64, RpcError> {
+        let tag = tag.unwrap_or(BlockNumberOrTag::Latest);
+
+        let nonce: U64 = self
+            .raw_request("eth_getTransactionCount", (address, tag))
+            .await?;
+
+        Ok(nonce.to())
+    }
+
+    async fn get_code(
+        &self,
+        address: Address,
+        tag: Option<BlockNumberOrTag>,
+    ) -> Result<Bytes, RpcError> {
+        let tag = tag.unwrap_or(BlockNumberOrTag::Latest);
+
+        let code = self.raw_request("eth_getCode", (address, tag)).await?;
+
+        Ok(code)
+    }
+
+    async fn get_storage_at(
+        &self,
+        address: Address,
+        slot: B256,
+        tag: Option<BlockNumberOrTag>,
+    ) -> Result<U256, RpcError> {
+        let tag = tag.unwrap_or(BlockNumberOrTag::Latest);
+        let slot = Into::<U256>::into(slot);
+
+        let storage_value = self
+            .raw_request("eth_getStorageAt", (address, slot, tag))
+            .await?;
+
+        Ok(storage_value)
+    }
+
+    async fn get_transaction_receipt(&self, tx_hash: B256) -> Result<TransactionReceipt, RpcError> {
+        let receipt = self
+            .provider
+            .get_transaction_receipt(tx_hash)
+            .await
+            .map_err(|source| RpcError::Transport {
+                method: "eth_getTransactionReceipt".to_string(),
+                source,
+            })?
+            .ok_or_else(|| {
+                RpcError::MissingData(format!("Transaction receipt not found for {tx_hash}"))
+            })?;
+
+        Ok(receipt)
+    }
+
+    async fn get_block_receipts(
+        &self,
+        tag: Option<BlockNumberOrTag>,
+    ) -> Result<Vec<TransactionReceipt>, RpcError> {
+        let tag = tag.unwrap_or(BlockNumberOrTag::Latest);
+
+        let receipts = self
+            .provider
+            .get_block_receipts(tag.into())
+            .await
+            .map_err(|source| RpcError::Transport {
+                method: "eth_getBlockReceipts".to_string(),
+                source,
+            })?
+            .ok_or_else(|| RpcError::MissingData(format!("Block receipts not found for {tag}")))?;
+
+        Ok(receipts)
+    }
+
+    async fn get_block_by_number(
+        &self,
+        tag: Option<BlockNumberOrTag>,
+        full: bool,
+    ) -> Result<Block, RpcError> {
+        let tag = tag.unwrap_or(BlockNumberOrTag::Latest);
+
+        let block = self
+            .provider
+            .get_block_by_number(tag, full)
+            .await
+            .map_err(|source| RpcError::Transport {
+                method: "eth_getBlockByNumber".to_string(),
+                source,
+            })?
+            .ok_or_else(|| RpcError::MissingData(format!("Block not found for {tag}")))?;
+
+        Ok(block)
+    }
+
+    async fn get_block_by_hash(&self, hash: BlockHash, full: bool) -> Result<Block, RpcError> {
+        let kind = if full {
+            BlockTransactionsKind::Full
+        } else {
+            BlockTransactionsKind::Hashes
+        };
+        let block = self
+            .provider
+            .get_block_by_hash(hash, kind)
+            .await
+            .map_err(|source| RpcError::Transport {
+                method: "eth_getBlockByHash".to_string(),
+                source,
+            })?
+            .ok_or_else(|| RpcError::MissingData(format!("Block not found for {hash}")))?;
+
+        Ok(block)
+    }
+
+    async fn get_transaction_by_hash(&self, tx_hash: B256) -> Result<Transaction, RpcError> {
+        let tx = self
+            .provider
+            .get_transaction_by_hash(tx_hash)
+            .await
+            .map_err(|source| RpcError::Transport {
+                method: "eth_getTransactionByHash".to_string(),
+                source,
+            })?
+            .ok_or_else(|| RpcError::MissingData(format!("Transaction not found for {tx_hash}")))?;
+
+        Ok(tx)
+    }
+
+    async fn get_transaction_by_block_number_and_index(
+        &self,
+        tag: Option<BlockNumberOrTag>,
+        index: u64,
+    ) -> Result<Transaction, RpcError> {
+        let tag = tag.unwrap_or(BlockNumberOrTag::Latest);
+
+        let tx: Option<Transaction> = self
+            .raw_request(
+                "eth_getTransactionByBlockNumberAndIndex",
+                (tag, U64::from(index)),
+            )
+            .await?;
+
+        tx.ok_or_else(|| {
+            RpcError::MissingData(format!("Transaction {index} not found in block {tag}"))
+        })
+    }
+
+    async fn get_transaction_by_block_hash_and_index(
+        &self,
+        hash: BlockHash,
+        index: u64,
+    ) -> Result<Transaction, RpcError> {
+        let tx: Option<Transaction> = self
+            .raw_request(
+                "eth_getTransactionByBlockHashAndIndex",
+                (hash, U64::from(index)),
+            )
+            .await?;
+
+        tx.ok_or_else(|| {
+            RpcError::MissingData(format!("Transaction {index} not found in block {hash}"))
+        })
+    }
+
+    async fn get_logs(&self, filter: &Filter) -> Result<Vec<Log>, RpcError> {
+        let logs = self
+            .provider
+            .get_logs(filter)
+            .await
+            .map_err(|source| RpcError::Transport {
+                method: "eth_getLogs".to_string(),
+                source,
+            })?;
+
+        Ok(logs)
+    }
+
+    async fn call(
+        &self,
+        request: &TransactionRequest,
+        tag: Option<BlockNumberOrTag>,
+    ) -> Result<Bytes, RpcError> {
+        let tag = tag.unwrap_or(BlockNumberOrTag::Latest);
+
+        let output = self
+            .provider
+            .call(request)
+            .block(tag.into())
+            .await
+            .map_err(|source| RpcError::Transport {
+                method: "eth_call".to_string(),
+                source,
+            })?;
+
+        Ok(output)
+    }
+
+    async fn estimate_gas(
+        &self,
+        request: &TransactionRequest,
+        tag: Option<BlockNumberOrTag>,
+    ) -> Result<u64, RpcError> {
+        let tag = tag.unwrap_or(BlockNumberOrTag::Latest);
+
+        let gas = self
+            .provider
+            .estimate_gas(request)
+            .block(tag.into())
+            .await
+            .map_err(|source| RpcError::Transport {
+                method: "eth_estimateGas".to_string(),
+                source,
+            })?;
+
+        Ok(gas)
+    }
+
+    async fn create_access_list(
+        &self,
+        request: &TransactionRequest,
+        tag: Option<BlockNumberOrTag>,
+    ) -> Result<AccessListResult, RpcError> {
+        let tag = tag.unwrap_or(BlockNumberOrTag::Latest);
+
+        let access_list = self
+            .provider
+            .create_access_list(request)
+            .block_id(tag.into())
+            .await
+            .map_err(|source| RpcError::Transport {
+                method: "eth_createAccessList".to_string(),
+                source,
+            })?;
+
+        Ok(access_list)
+    }
+}
+
+impl<T: Transport + Clone> ExecutionRpc for ProviderRpc<T> {
+    async fn get_proof(
+        &self,
+        address: Address,
+        slots: &[B256],
+        block_number: u64,
+    ) -> Result<EIP1186AccountProofResponse, RpcError> {
+        let proof = self
+            .provider
+            .get_proof(address, slots.to_vec())
+            .block_id(block_number.into())
+            .await
+            .map_err(|source| RpcError::Transport {
+                method: "eth_getProof".to_string(),
+                source,
+            })?;
+
+        Ok(proof)
+    }
+
+    async fn get_chain_id(&self) -> Result<u64, RpcError> {
+        self.provider
+            .get_chain_id()
+            .await
+            .map_err(|source| RpcError::Transport {
+                method: "eth_chainId".to_string(),
+                source,
+            })
+    }
+
+    async fn send_raw_transaction(&self, tx: &Bytes) -> Result<B256, RpcError> {
+        self.raw_request("eth_sendRawTransaction", (tx,)).await
+    }
+
+    async fn get_block_number(&self, tag: Option<BlockNumberOrTag>) -> Result<u64, RpcError> {
+        let tag = tag.unwrap_or(BlockNumberOrTag::Latest);
+
+        // if tag is a number, simply return it
+        if tag.is_number() {
+            return Ok(tag.as_number().unwrap());
+        }
+
+        // if tag is latest, fetch the latest block number using eth_blockNumber method
+        if tag.is_latest() {
+            let block_number =
+                self.provider
+                    .get_block_number()
+                    .await
+                    .map_err(|source| RpcError::Transport {
+                        method: "eth_blockNumber".to_string(),
+                        source,
+                    })?;
+            return Ok(block_number);
+        }
+
+        // otherwise, fetch the block using eth_getBlockByNumber method
+        let block = self.get_block_by_number(tag.into(), false).await?;
+
+        Ok(block.header.number)
+    }
+
+    async fn detect_capabilities(&self) -> Capabilities {
+        let tag = BlockNumberOrTag::Latest;
+        let (account, proof, receipts) = tokio::join!(
+            self.raw_request::<_, Account>("eth_getAccount", (Address::ZERO, tag)),
+            self.get_account_proof(Address::ZERO, tag),
+            // The receipts of the genesis block are the cheapest to serve
+            self.raw_request::<_, Option<Vec<TransactionReceipt>>>(
+                "eth_getBlockReceipts",
+                (BlockNumberOrTag::Earliest,)
+            ),
+        );
+
+        let capabilities = Capabilities {
+            get_account: is_supported(&account),
+            get_proof: is_supported(&proof),
+            get_block_receipts: is_supported(&receipts),
+        };
+        self.account_unsupported
+            .store(!capabilities.get_account, Ordering::Relaxed);
+        self.proof_unsupported
+            .store(!capabilities.get_proof, Ordering::Relaxed);
+
+        capabilities
+    }
+}
@@ -0,0 +1,69 @@
+use std::path::PathBuf;
+
+use alloy::primitives::B256;
+use alloy::providers::{IpcConnect, ProviderBuilder, WsConnect};
+use alloy::pubsub::{PubSubFrontend, Subscription};
+use alloy::rpc::json_rpc::{RpcParam, RpcReturn};
+use alloy::rpc::types::{Filter, Header, Log};
+
+use crate::errors::RpcError;
+use crate::provider_rpc::ProviderRpc;
+
+/// Untrusted RPC over WebSocket, or over IPC for a node running on the same host.
+/// Both can also push new heads and logs.
+///
+/// Pushed data is as untrusted as any response, it must be verified before use.
+pub type PubSubRpc = ProviderRpc<PubSubFrontend>;
+
+impl PubSubRpc {
+    /// Connect to the WebSocket URL `rpc`
+    pub async fn connect_ws(rpc: &str) -> Result<Self, RpcError> {
+        let provider = ProviderBuilder::new()
+            .on_ws(WsConnect::new(rpc))
+            .await
+            .map_err(|source| RpcError::Transport {
+                method: "connect".to_string(),
+                source,
+            })?;
+
+        Ok(Self::with_provider(provider))
+    }
+
+    /// Connect to the IPC socket (or named pipe) at `path`
+    pub async fn connect_ipc(path: impl Into<PathBuf>) -> Result<Self, RpcError> {
+        let provider = ProviderBuilder::new()
+            .on_ipc(IpcConnect::new(path.into()))
+            .await
+            .map_err(|source| RpcError::Transport {
+                method: "connect".to_string(),
+                source,
+            })?;
+
+        Ok(Self::with_provider(provider))
+    }
+
+    /// RPC method: `eth_subscribe` to `newHeads`
+    pub async fn subscribe_new_heads(&self) -> Result<Subscription<Header>, RpcError> {
+        self.subscribe(("newHeads",)).await
+    }
+
+    /// RPC method: `eth_subscribe` to `logs`
+    pub async fn subscribe_logs(&self, filter: &Filter) -> Result<Subscription<Log>, RpcError> {
+        self.subscribe(("logs", filter)).await
+    }
+
+    async fn subscribe<P: RpcParam, R: RpcReturn>(
+        &self,
+        params: P,
+    ) -> Result<Subscription<R>, RpcError> {
+        let id: B256 = self.raw_request("eth_subscribe", params).await?;
+
+        self.provider()
+            .get_subscription(id)
+            .await
+            .map_err(|source| RpcError::Transport {
+                method: "eth_subscribe".to_string(),
+                source,
+            })
+    }
+}
@@ -0,0 +1,764 @@
+use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use alloy::primitives::{Address, B256, U256, U64};
+use alloy::rpc::types::{BlockNumberOrTag, Filter, TransactionRequest};
+use axum::body::Bytes;
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::State as AxumState;
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::post;
+use axum::Router;
+use eyre::Result;
+use futures::{SinkExt, StreamExt};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::mpsc;
+use tokio::task::JoinSet;
+
+use crate::common::{ExecutionRpc, RpcVerifiableMethods};
+use crate::errors::{EvmError, RpcError, VerificationError};
+use crate::subscription::{SubscriptionKind, Subscriptions};
+use crate::verified_rpc_client::VerifiedRpcClient;
+
+/// JSON-RPC 2.0 error codes.
+/// Ref: https://www.jsonrpc.org/specification#error_object
+pub const PARSE_ERROR: i64 = -32700;
+pub const INVALID_REQUEST: i64 = -32600;
+pub const METHOD_NOT_FOUND: i64 = -32601;
+pub const INVALID_PARAMS: i64 = -32602;
+pub const INTERNAL_ERROR: i64 = -32603;
+
+/// Max number of calls in a JSON-RPC batch, larger batches are rejected as a whole
+pub const MAX_BATCH_SIZE: usize = 100;
+
+/// Max number of calls of a batch handled at once
+pub const MAX_CONCURRENT_BATCH_CALLS: usize = 16;
+
+/// Max number of requests of a single WebSocket or IPC connection handled at once
+pub const MAX_CONCURRENT_CONNECTION_REQUESTS: usize = 16;
+
+/// Max size of a JSON-RPC message read from an IPC connection, as axum's default HTTP body
+/// limit. The connection is closed once a message exceeds it.
+pub const MAX_IPC_MESSAGE_SIZE: usize = 2 * 1024 * 1024;
+
+/// Max number of responses and notifications waiting to be written to a WebSocket or IPC
+/// connection. Subscriptions whose notifications don't fit are closed.
+pub const MAX_PENDING_MESSAGES: usize = 1024;
+
+/// Error code of a reverted call, with the revert data as error data (geth convention).
+pub const EXECUTION_REVERTED: i64 = 3;
+
+/// Server error codes (-32000 to -32099) for failures talking to or verifying the untrusted RPC.
+/// Ref: https://eips.ethereum.org/EIPS/eip-1474#error-codes
+pub const RESOURCE_NOT_FOUND: i64 = -32001;
+pub const RESOURCE_UNAVAILABLE: i64 = -32002;
+pub const TRANSACTION_REJECTED: i64 = -32003;
+pub const LIMIT_EXCEEDED: i64 = -32005;
+pub const VM_EXECUTION_ERROR: i64 = -32015;
+pub const UNTRUSTED_BLOCK: i64 = -32040;
+pub const INVALID_ACCOUNT_PROOF: i64 = -32041;
+pub const INVALID_STORAGE_PROOF: i64 = -32042;
+pub const CODE_HASH_MISMATCH: i64 = -32043;
+pub const RECEIPTS_ROOT_MISMATCH: i64 = -32044;
+pub const INVALID_RECEIPT: i64 = -32045;
+pub const INVALID_BLOCK_HEADER: i64 = -32046;
+pub const TRANSACTIONS_ROOT_MISMATCH: i64 = -32047;
+pub const INVALID_TRANSACTION: i64 = -32048;
+pub const WITHDRAWALS_ROOT_MISMATCH: i64 = -32049;
+pub const OMMERS_HASH_MISMATCH: i64 = -32050;
+
+#[derive(Debug, Deserialize)]
+struct JsonRpcRequest {
+    jsonrpc: String,
+    /// `None` if the request is a notification
+    #[serde(default, deserialize_with = "deserialize_some")]
+    id: Option<Value>,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct JsonRpcError {
+    pub code: i64,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
+}
+
+impl JsonRpcError {
+    pub fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            data: None,
+        }
+    }
+
+    pub fn with_data(mut self, data: impl Serialize) -> Self {
+        self.data = serde_json::to_value(data).ok();
+        self
+    }
+}
+
+impl From<RpcError> for JsonRpcError {
+    fn from(e: RpcError) -> Self {
+        // Error responses of the untrusted RPC are returned as served, wallets key on their
+        // code, message and data (e.g. a rejected transaction)
+        if let Some(error) = e.as_error_resp() {
+            return Self {
+                code: error.code,
+                message: error.message.to_string(),
+                data: error
+                    .data
+                    .as_ref()
+                    .and_then(|data| serde_json::from_str(data.get()).ok()),
+            };
+        }
+
+        let code = match &e {
+            RpcError::InvalidUrl(_) | RpcError::UnknownChainId => INTERNAL_ERROR,
+            RpcError::Transport { .. } | RpcError::Timeout { .. } => RESOURCE_UNAVAILABLE,
+            RpcError::MissingData(_) => RESOURCE_NOT_FOUND,
+            RpcError::InvalidParams(_) => INVALID_PARAMS,
+            RpcError::Verification(e) => match e {
+                VerificationError::UntrustedBlock(_)
+                | VerificationError::UnlinkedBlock { .. }
+                | VerificationError::UnresolvedTag(_) => UNTRUSTED_BLOCK,
+                VerificationError::InvalidAccountProof { .. } => INVALID_ACCOUNT_PROOF,
+                VerificationError::InvalidStorageProof { .. } => INVALID_STORAGE_PROOF,
+                VerificationError::CodeHashMismatch { .. } => CODE_HASH_MISMATCH,
+                VerificationError::ReceiptsRootMismatch { .. } => RECEIPTS_ROOT_MISMATCH,
+                VerificationError::InvalidReceipt { .. } => INVALID_RECEIPT,
+                VerificationError::TransactionsRootMismatch { .. } => TRANSACTIONS_ROOT_MISMATCH,
+                VerificationError::InvalidTransaction { .. } => INVALID_TRANSACTION,
+                VerificationError::WithdrawalsRootMismatch { .. } => WITHDRAWALS_ROOT_MISMATCH,
+                VerificationError::OmmersHashMismatch { .. } => OMMERS_HASH_MISMATCH,
+                VerificationError::InvalidHeader { .. }
+                | VerificationError::BlockHashMismatch { .. } => INVALID_BLOCK_HEADER,
+            },
+            RpcError::Evm(EvmError::Reverted(output)) => {
+                return Self::new(EXECUTION_REVERTED, e.to_string()).with_data(output)
+            }
+            RpcError::Evm(EvmError::Halted(_)) => VM_EXECUTION_ERROR,
+            RpcError::Evm(EvmError::InvalidCall(_)) => TRANSACTION_REJECTED,
+            RpcError::Evm(EvmError::StateLimitExceeded(_)) => LIMIT_EXCEEDED,
+        };
+
+        Self::new(code, e.to_string())
+    }
+}
+
+/// Distinguishes an explicit `"id": null` from a missing `id`.
+fn deserialize_some<'de, D: serde::Deserializer<'de>>(d: D) -> Result<Option<Value>, D::Error> {
+    Value::deserialize(d).map(Some)
+}
+
+fn success_response(id: Value, result: Value) -> Value {
+    serde_json::json!({ "jsonrpc": "2.0", "id": id, "result": result })
+}
+
+fn error_response(id: Value, error: JsonRpcError) -> Value {
+    serde_json::json!({ "jsonrpc": "2.0", "id": id, "error": error })
+}
+
+/// Deserialize the required positional parameter at `index`.
+fn param<T: DeserializeOwned>(params: &Value, index: usize) -> Result<T, JsonRpcError> {
+    let value = params
+        .get(index)
+        .cloned()
+        .ok_or_else(|| JsonRpcError::new(INVALID_PARAMS, format!("Missing param {index}")))?;
+
+    serde_json::from_value(value)
+        .map_err(|e| JsonRpcError::new(INVALID_PARAMS, format!("Invalid param {index}: {e}")))
+}
+
+/// Deserialize the optional positional parameter at `index`.
+fn opt_param<T: DeserializeOwned>(params: &Value, index: usize) -> Result<Option<T>, JsonRpcError> {
+    match params.get(index) {
+        None | Some(Value::Null) => Ok(None),
+        Some(_) => param(params, index).map(Some),
+    }
+}
+
+fn to_result<T: Serialize>(value: T) -> Result<Value, JsonRpcError> {
+    serde_json::to_value(value).map_err(|e| JsonRpcError::new(INTERNAL_ERROR, e.to_string()))
+}
+
+/// The result of a lookup, `null` if no provider has the object (e.g. the receipt of a
+/// pending transaction), as the spec returns for unknown hashes and indexes
+fn or_null<T: Serialize>(result: Result<T, RpcError>) -> Result<Value, JsonRpcError> {
+    match result {
+        Err(RpcError::MissingData(_)) => Ok(Value::Null),
+        result => to_result(result?),
+    }
+}
+
+/// Route a single JSON-RPC call to the matching verified method.
+pub async fn dispatch<B: ExecutionRpc>(
+    client: &VerifiedRpcClient<B>,
+    method: &str,
+    params: &Value,
+) -> Result<Value, JsonRpcError> {
+    if !params.is_array() && !params.is_null() {
+        return Err(JsonRpcError::new(
+            INVALID_PARAMS,
+            "Params must be a positional array",
+        ));
+    }
+
+    match method {
+        "eth_getAccount" => {
+            let address: Address = param(params, 0)?;
+            let tag: Option<BlockNumberOrTag> = opt_param(params, 1)?;
+            to_result(
+                client
+                    .retry_invalid(|| client.get_account(address, None, tag))
+                    .await?,
+            )
+        }
+        "eth_getBalance" => {
+            let address: Address = param(params, 0)?;
+            let tag: Option<BlockNumberOrTag> = opt_param(params, 1)?;
+            to_result(
+                client
+                    .retry_invalid(|| client.get_balance(address, tag))
+                    .await?,
+            )
+        }
+        "eth_getTransactionCount" => {
+            let address: Address = param(params, 0)?;
+            let tag: Option<BlockNumberOrTag> = opt_param(params, 1)?;
+            let nonce = client
+                .retry_invalid(|| client.get_nonce(address, tag))
+                .await?;
+            to_result(format!("{nonce:#x}"))
+        }
+        "eth_getCode" => {
+            let address: Address = param(params, 0)?;
+            let tag: Option<BlockNumberOrTag> = opt_param(params, 1)?;
+            to_result(
+                client
+                    .retry_invalid(|| client.get_code(address, tag))
+                    .await?,
+            )
+        }
+        "eth_getStorageAt" => {
+            let address: Address = param(params, 0)?;
+            // short hex slots (e.g. "0x0") are accepted too, as by geth
+            let slot = B256::from(param::<U256>(params, 1)?);
+            let tag: Option<BlockNumberOrTag> = opt_param(params, 2)?;
+            let value = client
+                .retry_invalid(|| client.get_storage_at(address, slot, tag))
+                .await?;
+            // storage values are returned as 32-byte words
+            to_result(B256::from(value))
+        }
+        "eth_getTransactionReceipt" => {
+            let tx_hash: B256 = param(params, 0)?;
+            or_null(
+                client
+                    .retry_invalid(|| client.get_transaction_receipt(tx_hash))
+                    .await,
+            )
+        }
+        "eth_getBlockReceipts" => {
+            let tag: Option<BlockNumberOrTag> = opt_param(params, 0)?;
+            to_result(
+                client
+                    .retry_invalid(|| client.get_block_receipts(tag))
+                    .await?,
+            )
+        }
+        "eth_getBlockByNumber" => {
+            let tag: Option<BlockNumberOrTag> = opt_param(params, 0)?;
+            let full: Option<bool> = opt_param(params, 1)?;
+            to_result(
+                client
+                    .retry_invalid(|| client.get_block_by_number(tag, full.unwrap_or_default()))
+                    .await?,
+            )
+        }
+        "eth_getBlockByHash" => {
+            let hash: B256 = param(params, 0)?;
+            let full: Option<bool> = opt_param(params, 1)?;
+            or_null(
+                client
+                    .retry_invalid(|| client.get_block_by_hash(hash, full.unwrap_or_default()))
+                    .await,
+            )
+        }
+        "eth_getTransactionByHash" => {
+            let tx_hash: B256 = param(params, 0)?;
+            or_null(
+                client
+                    .retry_invalid(|| client.get_transaction_by_hash(tx_hash))
+                    .await,
+            )
+        }
+        "eth_getTransactionByBlockNumberAndIndex" => {
+            let tag: Option<BlockNumberOrTag> = opt_param(params, 0)?;
+            let index: U64 = param(params, 1)?;
+            or_null(
+                client
+                    .retry_invalid(|| {
+                        client.get_transaction_by_block_number_and_index(tag, index.to())
+                    })
+                    .await,
+            )
+        }
+        "eth_getTransactionByBlockHashAndIndex" => {
+            let hash: B256 = param(params, 0)?;
+            let index: U64 = param(params, 1)?;
+            or_null(
+                client
+                    .retry_invalid(|| {
+                        client.get_transaction_by_block_hash_and_index(hash, index.to())
+                    })
+                    .await,
+            )
+        }
+        "eth_call" => {
+            let request: TransactionRequest = param(params, 0)?;
+            let tag: Option<BlockNumberOrTag> = opt_param(params, 1)?;
+            to_result(client.retry_invalid(|| client.call(&request, tag)).await?)
+        }
+        "eth_estimateGas" => {
+            let request: TransactionRequest = param(params, 0)?;
+            let tag: Option<BlockNumberOrTag> = opt_param(params, 1)?;
+            let gas = client
+                .retry_invalid(|| client.estimate_gas(&request, tag))
+                .await?;
+            to_result(format!("{gas:#x}"))
+        }
+        "eth_createAccessList" => {
+            let request: TransactionRequest = param(params, 0)?;
+            let tag: Option<BlockNumberOrTag> = opt_param(params, 1)?;
+            to_result(
+                client
+                    .retry_invalid(|| client.create_access_list(&request, tag))
+                    .await?,
+            )
+        }
+        "eth_getLogs" => {
+            let filter: Filter = param(params, 0)?;
+            to_result(client.retry_invalid(|| client.get_logs(&filter)).await?)
+        }
+        // Served from the configuration and the trusted chain, never by the execution RPC
+        "eth_chainId" => to_result(U64::from(client.chain_id()?)),
+        "net_version" => to_result(client.chain_id()?.to_string()),
+        "eth_blockNumber" => {
+            let number = client
+                .state
+                .resolve_block_number(Some(BlockNumberOrTag::Latest))
+                .map_err(RpcError::from)?;
+            to_result(U64::from(number))
+        }
+        // Forwarded as is, only the hash of the transaction is computed locally
+        "eth_sendRawTransaction" => {
+            let tx: alloy::primitives::Bytes = param(params, 0)?;
+            to_result(client.send_raw_transaction(&tx).await?)
+        }
+        // The optional methods detected at the execution RPC, `null` until detected
+        "proxy_capabilities" => to_result(client.capabilities()),
+        _ => Err(JsonRpcError::new(
+            METHOD_NOT_FOUND,
+            format!("Method {method} is not supported"),
+        )),
+    }
+}
+
+/// Handle a raw JSON-RPC request body, either a single call or a batch of calls.
+///
+/// Returns `None` if no response must be sent (i.e. the request was a notification,
+/// or a batch of notifications only).
+pub async fn handle_request<B: ExecutionRpc + 'static>(
+    client: &VerifiedRpcClient<B>,
+    body: &[u8],
+) -> Option<Value> {
+    handle_message(client, body, None).await
+}
+
+/// Handle a raw JSON-RPC request body, on a connection supporting `subscriptions` if any
+async fn handle_message<B: ExecutionRpc + 'static>(
+    client: &VerifiedRpcClient<B>,
+    body: &[u8],
+    subscriptions: Option<&Subscriptions<B>>,
+) -> Option<Value> {
+    let value: Value = match serde_json::from_slice(body) {
+        Ok(value) => value,
+        Err(e) => {
+            return Some(error_response(
+                Value::Null,
+                JsonRpcError::new(PARSE_ERROR, e.to_string()),
+            ))
+        }
+    };
+
+    handle_value(client, value, subscriptions).await
+}
+
+/// Handle a parsed JSON-RPC request, either a single call or a batch of calls
+async fn handle_value<B: ExecutionRpc + 'static>(
+    client: &VerifiedRpcClient<B>,
+    value: Value,
+    subscriptions: Option<&Subscriptions<B>>,
+) -> Option<Value> {
+    match value {
+        Value::Array(calls) if calls.is_empty() => Some(error_response(
+            Value::Null,
+            JsonRpcError::new(INVALID_REQUEST, "Empty batch"),
+        )),
+        Value::Array(calls) if calls.len() > MAX_BATCH_SIZE => Some(error_response(
+            Value::Null,
+            JsonRpcError::new(
+                INVALID_REQUEST,
+                format!("Batch of {} calls exceeds {MAX_BATCH_SIZE}", calls.len()),
+            ),
+        )),
+        // The calls of a batch are handled concurrently, each with its own response
+        Value::Array(calls) => {
+            let responses: Vec<Value> = futures::stream::iter(calls)
+                .map(|call| handle_call(client, call, subscriptions))
+                .buffered(MAX_CONCURRENT_BATCH_CALLS)
+                .filter_map(futures::future::ready)
+                .collect()
+                .await;
+
+            (!responses.is_empty()).then_some(Value::Array(responses))
+        }
+        value => handle_call(client, value, subscriptions).await,
+    }
+}
+
+async fn handle_call<B: ExecutionRpc + 'static>(
+    client: &VerifiedRpcClient<B>,
+    value: Value,
+    subscriptions: Option<&Subscriptions<B>>,
+) -> Option<Value> {
+    let request: JsonRpcRequest = match serde_json::from_value(value) {
+        Ok(request) => request,
+        Err(e) => {
+            return Some(error_response(
+                Value::Null,
+                JsonRpcError::new(INVALID_REQUEST, e.to_string()),
+            ))
+        }
+    };
+
+    let id = request.id.clone().unwrap_or(Value::Null);
+    if request.jsonrpc != "2.0" {
+        return Some(error_response(
+            id,
+            JsonRpcError::new(INVALID_REQUEST, "Unsupported jsonrpc version"),
+        ));
+    }
+
+    let result = match (request.method.as_str(), subscriptions) {
+        ("eth_subscribe" | "eth_unsubscribe", None) => Err(JsonRpcError::new(
+            METHOD_NOT_FOUND,
+            "Subscriptions require a WebSocket connection",
+        )),
+        ("eth_subscribe", Some(subscriptions)) => subscribe(subscriptions, &request.params),
+        ("eth_unsubscribe", Some(subscriptions)) => {
+            param(&request.params, 0).map(|id| Value::Bool(subscriptions.unsubscribe(id)))
+        }
+        (method, _) => dispatch(client, method, &request.params).await,
+    };
+
+    // Notifications are executed but never answered
+    request.id.as_ref()?;
+
+    Some(match result {
+        Ok(result) => success_response(id, result),
+        Err(error) => error_response(id, error),
+    })
+}
+
+/// RPC method: `eth_subscribe`, to `newHeads` or `logs`
+fn subscribe<B: ExecutionRpc + 'static>(
+    subscriptions: &Subscriptions<B>,
+    params: &Value,
+) -> Result<Value, JsonRpcError> {
+    let kind = match param::<String>(params, 0)?.as_str() {
+        "newHeads" => SubscriptionKind::NewHeads,
+        "logs" => SubscriptionKind::Logs(Box::new(opt_param(params, 1)?.unwrap_or_default())),
+        kind => {
+            return Err(JsonRpcError::new(
+                INVALID_PARAMS,
+                format!("Subscription {kind} is not supported"),
+            ))
+        }
+    };
+
+    to_result(subscriptions.subscribe(kind))
+}
+
+async fn http_handler<B: ExecutionRpc + 'static>(
+    AxumState(client): AxumState<Arc<VerifiedRpcClient<B>>>,
+    body: Bytes,
+) -> Response {
+    match handle_request(&client, &body).await {
+        Some(response) => (
+            [(header::CONTENT_TYPE, "application/json")],
+            response.to_string(),
+        )
+            .into_response(),
+        None => StatusCode::NO_CONTENT.into_response(),
+    }
+}
+
+async fn ws_handler<B: ExecutionRpc + 'static>(
+    AxumState(client): AxumState<Arc<VerifiedRpcClient<B>>>,
+    ws: WebSocketUpgrade,
+) -> Response {
+    ws.on_upgrade(|socket| ws_connection(client, socket))
+}
+
+/// Serve the requests of a WebSocket connection, along with its subscription notifications
+async fn ws_connection<B: ExecutionRpc + 'static>(
+    client: Arc<VerifiedRpcClient<B>>,
+    socket: WebSocket,
+) {
+    let (mut sink, mut stream) = socket.split();
+    let (sender, mut messages) = mpsc::channel::<Value>(MAX_PENDING_MESSAGES);
+    let subscriptions = Arc::new(Subscriptions::new(client.clone(), sender.clone()));
+
+    let write = async move {
+        while let Some(message) = messages.recv().await {
+            if sink.send(Message::Text(message.to_string())).await.is_err() {
+                return;
+            }
+        }
+    };
+    let read = async move {
+        let mut requests = JoinSet::new();
+        loop {
+            let body = match stream.next().await {
+                Some(Ok(Message::Text(text))) => Bytes::from(text),
+                Some(Ok(Message::Binary(body))) => Bytes::from(body),
+                // Pings are answered by axum
+                Some(Ok(Message::Ping(_) | Message::Pong(_))) => continue,
+                Some(Ok(Message::Close(_)) | Err(_)) | None => return,
+            };
+
+            let (client, subscriptions) = (client.clone(), subscriptions.clone());
+            spawn_request(&mut requests, &sender, async move {
+                handle_message(&client, &body, Some(&subscriptions)).await
+            })
+            .await;
+        }
+    };
+
+    // The requests in flight are aborted along with the connection
+    tokio::select! {
+        _ = write => {}
+        _ = read => {}
+    }
+}
+
+/// Handle a request of a connection in its own task, so that a slow request doesn't hold up
+/// the others nor the notifications, and send its response to `responses`.
+/// Up to [`MAX_CONCURRENT_CONNECTION_REQUESTS`] requests of a connection are handled at once,
+/// the connection is not read further until one of them completes.
+async fn spawn_request(
+    requests: &mut JoinSet<()>,
+    responses: &mpsc::Sender<Value>,
+    request: impl Future<Output = Option<Value>> + Send + 'static,
+) {
+    if requests.len() >= MAX_CONCURRENT_CONNECTION_REQUESTS {
+        requests.join_next().await;
+    }
+
+    let responses = responses.clone();
+    requests.spawn(async move {
+        if let Some(response) = request.await {
+            let _ = responses.send(response).await;
+        }
+    });
+}
+
+/// JSON-RPC over HTTP POST, and over WebSocket (with subscriptions) on the same path
+pub fn router<B: ExecutionRpc + 'static>(client: Arc<VerifiedRpcClient<B>>) -> Router {
+    Router::new()
+        .route("/", post(http_handler::<B>).get(ws_handler::<B>))
+        .with_state(client)
+}
+
+/// Serve the JSON-RPC proxy over HTTP and WebSocket on `addr` until the process is stopped.
+pub async fn serve<B: ExecutionRpc + 'static>(
+    client: Arc<VerifiedRpcClient<B>>,
+    addr: SocketAddr,
+) -> Result<()> {
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    tracing::info!(
+        "JSON-RPC proxy listening on http://{0} and ws://{0}",
+        listener.local_addr()?
+    );
+
+    axum::serve(listener, router(client)).await?;
+
+    Ok(())
+}
+
+/// Serve the JSON-RPC proxy over IPC, on a Unix domain socket at `path`,
+/// until the process is stopped.
+#[cfg(unix)]
+pub async fn serve_ipc<B: ExecutionRpc + 'static>(
+    client: Arc<VerifiedRpcClient<B>>,
+    path: &std::path::Path,
+) -> Result<()> {
+    use std::os::unix::fs::FileTypeExt;
+
+    // A socket left over by a previous run would fail the bind, any other file is kept
+    if std::fs::symlink_metadata(path).is_ok_and(|metadata| metadata.file_type().is_socket()) {
+        std::fs::remove_file(path)?;
+    }
+    let listener = tokio::net::UnixListener::bind(path)?;
+    tracing::info!("JSON-RPC proxy listening on {}", path.display());
+
+    loop {
+        let (stream, _) = listener.accept().await?;
+        tokio::spawn(ipc_connection(client.clone(), stream));
+    }
+}
+
+/// Serve the requests of an IPC connection, along with its subscription notifications.
+///
+/// Requests are a stream of JSON values, responses and notifications are newline-delimited.
+#[cfg(unix)]
+async fn ipc_connection<B: ExecutionRpc + 'static>(
+    client: Arc<VerifiedRpcClient<B>>,
+    stream: tokio::net::UnixStream,
+) {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let (mut reader, mut writer) = stream.into_split();
+    let (sender, mut messages) = mpsc::channel::<Value>(MAX_PENDING_MESSAGES);
+    let subscriptions = Arc::new(Subscriptions::new(client.clone(), sender.clone()));
+
+    let write = async move {
+        while let Some(message) = messages.recv().await {
+            let line = format!("{message}\n");
+            if writer.write_all(line.as_bytes()).await.is_err() {
+                return;
+            }
+        }
+    };
+    let read = async move {
+        let mut requests = JoinSet::new();
+        let mut stream = JsonStream::default();
+        loop {
+            if !matches!(reader.read_buf(&mut stream.buffer).await, Ok(n) if n > 0) {
+                return;
+            }
+
+            let values = stream.parse();
+            if stream.buffer.len() > MAX_IPC_MESSAGE_SIZE {
+                tracing::warn!("IPC message exceeds {MAX_IPC_MESSAGE_SIZE} bytes, closing");
+                return;
+            }
+            for value in values {
+                let (client, subscriptions) = (client.clone(), subscriptions.clone());
+                spawn_request(&mut requests, &sender, async move {
+                    match value {
+                        Ok(value) => handle_value(&client, value, Some(&subscriptions)).await,
+                        Err(error) => Some(error_response(Value::Null, error)),
+                    }
+                })
+                .await;
+            }
+        }
+    };
+
+    // The requests in flight are aborted along with the connection
+    tokio::select! {
+        _ = write => {}
+        _ = read => {}
+    }
+}
+
+/// JSON values read from a byte stream, split or packed across reads.
+/// Bytes are scanned once for the end of each top-level object, array or string, so that
+/// values are only parsed once complete rather than on every read.
+#[cfg(unix)]
+#[derive(Debug, Default)]
+struct JsonStream {
+    buffer: Vec<u8>,
+    /// Bytes of `buffer` scanned so far
+    scanned: usize,
+    /// End of the last complete value in `buffer`
+    complete: usize,
+    depth: usize,
+    in_string: bool,
+    escaped: bool,
+}
+
+#[cfg(unix)]
+impl JsonStream {
+    /// Take the complete JSON values out of the buffer, leaving a trailing partial value in it.
+    /// Invalid JSON fails with a parse error, and the rest of the buffer is dropped.
+    fn parse(&mut self) -> Vec<Result<Value, JsonRpcError>> {
+        self.scan();
+
+        let mut values = Vec::new();
+        let mut stream =
+            serde_json::Deserializer::from_slice(&self.buffer[..self.complete]).into_iter();
+        loop {
+            match stream.next() {
+                Some(Ok(value)) => values.push(Ok(value)),
+                Some(Err(e)) if !e.is_eof() => {
+                    values.push(Err(JsonRpcError::new(PARSE_ERROR, e.to_string())));
+                    *self = Self::default();
+                    return values;
+                }
+                Some(Err(_)) | None => break,
+            }
+        }
+
+        let consumed = stream.byte_offset();
+        self.buffer.drain(..consumed);
+        self.scanned -= consumed;
+        self.complete -= consumed;
+        values
+    }
+
+    fn scan(&mut self) {
+        for (offset, byte) in self.buffer.iter().enumerate().skip(self.scanned) {
+            if self.in_string {
+                match byte {
+                    _ if self.escaped => self.escaped = false,
+                    b'\\' => self.escaped = true,
+                    b'"' => {
+                        self.in_string = false;
+                        if self.depth == 0 {
+                            self.complete = offset + 1;
+                        }
+                    }
+                    _ => {}
+                }
+                continue;
+            }
+
+            match byte {
+                b'"' => self.in_string = true,
+                b'{' | b'[' => self.depth += 1,
+                // An unbalanced one ends a value too, for the parser to reject it
+                b'}' | b']' => {
+                    self.depth = self.depth.saturating_sub(1);
+                    if self.depth == 0 {
+                        self.complete = offset + 1;
+                    }
+                }
+                // Top-level scalars (invalid requests) and garbage are left to the parser
+                _ if self.depth == 0 && !byte.is_ascii_whitespace() => self.complete = offset + 1,
+                _ => {}
+            }
+        }
+        self.scanned = self.buffer.len();
+    }
+}
@@ -0,0 +1,205 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use alloy::primitives::{BlockNumber, B256, U256};
+use alloy::rpc::types::Filter;
+use serde_json::{json, Value};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::sync::mpsc::{self, error::TrySendError};
+use tokio::task::JoinHandle;
+
+use crate::common::{ExecutionRpc, RpcVerifiableMethods};
+use crate::errors::RpcError;
+use crate::server::LIMIT_EXCEEDED;
+use crate::verified_rpc_client::{
+    TrustedBlock, VerifiedRpcClient, MAX_LOGS_BLOCK_RANGE, MAX_REORG_DEPTH,
+};
+
+#[derive(Debug, Clone)]
+pub enum SubscriptionKind {
+    /// The header of each new trusted block
+    NewHeads,
+    /// The logs of each new trusted block matching the filter, its block range is ignored
+    Logs(Box<Filter>),
+}
+
+impl SubscriptionKind {
+    /// The verified notification results for the trusted block `number`
+    async fn results<B: ExecutionRpc>(
+        &self,
+        client: &VerifiedRpcClient<B>,
+        number: BlockNumber,
+    ) -> Result<Vec<Value>, RpcError> {
+        match self {
+            Self::NewHeads => Ok(vec![json!(client.trusted_header(number).await?)]),
+            Self::Logs(filter) => {
+                let filter = filter.as_ref().clone().from_block(number).to_block(number);
+                let logs = client.get_logs(&filter).await?;
+                Ok(logs.iter().map(|log| json!(log)).collect())
+            }
+        }
+    }
+}
+
+/// The subscriptions of a single connection, cancelled when dropped.
+///
+/// Notifications are only sent for trusted blocks, once verified like any response.
+/// A subscription whose notifications no longer fit in the channel, because the
+/// connection doesn't keep up with them, is closed with an error notification.
+/// On a reorg, the logs of the replaced blocks are notified again as `removed`.
+pub struct Subscriptions<B> {
+    client: Arc<VerifiedRpcClient<B>>,
+    notifications: mpsc::Sender<Value>,
+    tasks: Mutex<HashMap<B256, JoinHandle<()>>>,
+    /// Subscription ids only need to be unique per connection
+    next_id: AtomicU64,
+}
+
+impl<B: ExecutionRpc + 'static> Subscriptions<B> {
+    /// Subscriptions whose notifications are sent to `notifications`
+    pub fn new(client: Arc<VerifiedRpcClient<B>>, notifications: mpsc::Sender<Value>) -> Self {
+        Self {
+            client,
+            notifications,
+            tasks: Mutex::default(),
+            next_id: AtomicU64::new(1),
+        }
+    }
+
+    /// RPC method: `eth_subscribe`
+    pub fn subscribe(&self, kind: SubscriptionKind) -> B256 {
+        let id = B256::from(U256::from(self.next_id.fetch_add(1, Ordering::Relaxed)));
+
+        // Notify from the blocks trusted after this call
+        let heads = self.client.state.subscribe_heads();
+        let latest = self.client.state.latest_trusted_block();
+        let task = tokio::spawn(notify(
+            self.client.clone(),
+            kind,
+            id,
+            heads,
+            latest,
+            self.notifications.clone(),
+        ));
+
+        self.tasks.lock().unwrap().insert(id, task);
+        id
+    }
+
+    /// RPC method: `eth_unsubscribe`
+    pub fn unsubscribe(&self, id: B256) -> bool {
+        let task = self.tasks.lock().unwrap().remove(&id);
+        task.inspect(JoinHandle::abort).is_some()
+    }
+}
+
+impl<B> Drop for Subscriptions<B> {
+    fn drop(&mut self) {
+        for task in self.tasks.lock().unwrap().values() {
+            task.abort();
+        }
+    }
+}
+
+/// Send the notifications for each new trusted block, until the subscriber is gone
+async fn notify<B: ExecutionRpc>(
+    client: Arc<VerifiedRpcClient<B>>,
+    kind: SubscriptionKind,
+    id: B256,
+    mut heads: broadcast::Receiver<TrustedBlock>,
+    latest: Option<TrustedBlock>,
+    notifications: mpsc::Sender<Value>,
+) {
+    let send = |result: Value| {
+        notifications.try_send(json!({
+            "jsonrpc": "2.0",
+            "method": "eth_subscription",
+            "params": { "subscription": id, "result": result },
+        }))
+    };
+
+    // The last blocks notified with their logs, to notify the blocks replacing them on a
+    // reorg and the logs they remove
+    let mut notified: VecDeque<(TrustedBlock, Vec<Value>)> = latest
+        .into_iter()
+        .map(|block| (block, Vec::new()))
+        .collect();
+    let result = 'notify: loop {
+        let head = match heads.recv().await {
+            Ok(head) => head,
+            // The skipped heads are caught up on from the next one
+            Err(RecvError::Lagged(_)) => continue,
+            Err(RecvError::Closed) => return,
+        };
+
+        let mut removed = Vec::new();
+        while let Some((block, logs)) = notified.pop_back() {
+            if client.state.get_trusted_block(block.number) == Some(block) {
+                notified.push_back((block, logs));
+                break;
+            }
+            removed.extend(logs.into_iter().rev().map(|mut log| {
+                log["removed"] = json!(true);
+                log
+            }));
+        }
+        for log in removed {
+            if let Err(e) = send(log) {
+                break 'notify e;
+            }
+        }
+
+        // Catch up on the blocks trusted along with the head, bounded
+        let from = notified
+            .back()
+            .map_or(head.number, |(block, _)| block.number + 1)
+            .max(head.number.saturating_sub(MAX_LOGS_BLOCK_RANGE - 1));
+        for number in from..=head.number {
+            let Some(block) = client.state.get_trusted_block(number) else {
+                break;
+            };
+            let results = match client.retry_invalid(|| kind.results(&client, number)).await {
+                Ok(results) => results,
+                Err(e) => {
+                    // Not marked as notified, it is retried along with the next head
+                    tracing::warn!("subscription {id}: failed to verify block {number}: {e}");
+                    break;
+                }
+            };
+
+            for result in &results {
+                if let Err(e) = send(result.clone()) {
+                    break 'notify e;
+                }
+            }
+            let logs = match kind {
+                SubscriptionKind::NewHeads => Vec::new(),
+                SubscriptionKind::Logs(_) => results,
+            };
+            notified.push_back((block, logs));
+            if notified.len() as u64 > MAX_REORG_DEPTH {
+                notified.pop_front();
+            }
+        }
+    };
+
+    // The connection doesn't keep up with the notifications: tell the subscriber why they stop
+    // once it catches up, rather than closing the subscription silently
+    if let TrySendError::Full(_) = result {
+        tracing::warn!("subscription {id}: subscriber is lagging, closing it");
+        let _ = notifications
+            .send(json!({
+                "jsonrpc": "2.0",
+                "method": "eth_subscription",
+                "params": {
+                    "subscription": id,
+                    "error": {
+                        "code": LIMIT_EXCEEDED,
+                        "message": "subscription closed: notifications not read in time",
+                    },
+                },
+            }))
+            .await;
+    }
+}
@@ -0,0 +1,1031 @@
+//! Helpers that build real Merkle Patricia tries so that proofs can be verified offline.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::{Arc, Mutex};
+
+use alloy::consensus::constants::{EMPTY_OMMER_ROOT_HASH, EMPTY_ROOT_HASH, KECCAK_EMPTY};
+use alloy::consensus::{
+    Account, Eip658Value, Header as ConsensusHeader, Receipt, ReceiptEnvelope, ReceiptWithBloom,
+    SignableTransaction, Transaction as _, TxEip1559, TxEip2930, TxEip4844, TxEip7702, TxEnvelope,
+    TxLegacy, TxType,
+};
+use alloy::eips::eip2718::Encodable2718;
+use alloy::eips::eip2930::{AccessList, AccessListItem};
+use alloy::eips::eip4844::DATA_GAS_PER_BLOB;
+use alloy::eips::eip4895::Withdrawal;
+use alloy::eips::eip7685::EMPTY_REQUESTS_HASH;
+use alloy::eips::eip7702::Authorization;
+use alloy::network::TxSignerSync;
+use alloy::primitives::{
+    address, b256, bytes, keccak256, Address, BlockHash, BlockNumber, Bloom, Bytes,
+    Log as PrimitiveLog, TxKind, B256, U256,
+};
+use alloy::rlp;
+use alloy::rpc::json_rpc::ErrorPayload;
+use alloy::rpc::types::{
+    AccessListResult, Block, BlockNumberOrTag, BlockTransactions, EIP1186AccountProofResponse,
+    EIP1186StorageProof, Filter, FilteredParams, Header, Log, Parity, Signature as RpcSignature,
+    Transaction, TransactionReceipt, TransactionRequest,
+};
+use alloy::signers::local::PrivateKeySigner;
+use alloy::signers::SignerSync;
+use alloy::transports::TransportError;
+use alloy_trie::proof::ProofRetainer;
+use alloy_trie::{HashBuilder, Nibbles};
+
+use crate::common::{Capabilities, ExecutionRpc, RpcVerifiableMethods};
+use crate::errors::RpcError;
+use crate::utils::ordered_trie_root;
+use crate::verified_rpc_client::TrustedBlock;
+
+/// Build a trie over `leaves` (hashed key -> encoded value).
+/// Returns the trie root and the proof nodes for `target`.
+pub fn trie_proof(leaves: &BTreeMap<B256, Vec<u8>>, target: B256) -> (B256, Vec<Bytes>) {
+    let target = Nibbles::unpack(target);
+    let mut hb =
+        HashBuilder::default().with_proof_retainer(ProofRetainer::new(vec![target.clone()]));
+
+    for (key, value) in leaves {
+        hb.add_leaf(Nibbles::unpack(key), value);
+    }
+
+    let root = hb.root();
+    let proof = hb
+        .take_proof_nodes()
+        .matching_nodes_sorted(&target)
+        .into_iter()
+        .map(|(_, node)| node)
+        .collect();
+
+    (root, proof)
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MockAccount {
+    pub nonce: u64,
+    pub balance: U256,
+    pub code: Bytes,
+    pub storage: BTreeMap<B256, U256>,
+}
+
+impl MockAccount {
+    fn storage_leaves(&self) -> BTreeMap<B256, Vec<u8>> {
+        self.storage
+            .iter()
+            .filter(|(_, value)| !value.is_zero())
+            .map(|(slot, value)| (keccak256(slot), rlp::encode(value)))
+            .collect()
+    }
+
+    pub fn storage_root(&self) -> B256 {
+        trie_proof(&self.storage_leaves(), B256::ZERO).0
+    }
+
+    pub fn code_hash(&self) -> B256 {
+        if self.code.is_empty() {
+            KECCAK_EMPTY
+        } else {
+            keccak256(&self.code)
+        }
+    }
+
+    pub fn account(&self) -> Account {
+        Account {
+            nonce: self.nonce,
+            balance: self.balance,
+            storage_root: self.storage_root(),
+            code_hash: self.code_hash(),
+        }
+    }
+}
+
+/// An in-memory world state that serves `eth_getProof` responses.
+#[derive(Debug, Clone, Default)]
+pub struct MockState {
+    pub accounts: BTreeMap<Address, MockAccount>,
+}
+
+impl MockState {
+    fn account_leaves(&self) -> BTreeMap<B256, Vec<u8>> {
+        self.accounts
+            .iter()
+            .map(|(address, account)| (keccak256(address), rlp::encode(account.account())))
+            .collect()
+    }
+
+    pub fn state_root(&self) -> B256 {
+        trie_proof(&self.account_leaves(), B256::ZERO).0
+    }
+
+    pub fn code(&self, address: Address) -> Bytes {
+        self.accounts
+            .get(&address)
+            .map(|account| account.code.clone())
+            .unwrap_or_default()
+    }
+
+    /// Mimics `eth_getProof`, absent accounts are returned zeroed with an exclusion proof.
+    pub fn get_proof(&self, address: Address, slots: &[B256]) -> EIP1186AccountProofResponse {
+        let (_, account_proof) = trie_proof(&self.account_leaves(), keccak256(address));
+        let account = self.accounts.get(&address).cloned().unwrap_or_default();
+        let storage_leaves = account.storage_leaves();
+
+        let storage_proof = slots
+            .iter()
+            .map(|slot| EIP1186StorageProof {
+                key: (*slot).into(),
+                value: account.storage.get(slot).copied().unwrap_or_default(),
+                proof: trie_proof(&storage_leaves, keccak256(slot)).1,
+            })
+            .collect();
+
+        EIP1186AccountProofResponse {
+            address,
+            balance: account.balance,
+            code_hash: account.code_hash(),
+            nonce: account.nonce,
+            storage_hash: account.storage_root(),
+            account_proof,
+            storage_proof,
+        }
+    }
+}
+
+/// A block served by [`MockRpc`] together with the world state after it.
+#[derive(Debug, Clone, Default)]
+pub struct MockBlock {
+    pub block: Block,
+    pub state: MockState,
+    pub receipts: Vec<TransactionReceipt>,
+}
+
+impl MockBlock {
+    pub fn trusted_block(&self) -> TrustedBlock {
+        let header = &self.block.header;
+
+        TrustedBlock {
+            number: header.number,
+            hash: header.hash,
+            parent_hash: header.parent_hash,
+            state_root: header.state_root,
+            transactions_root: header.transactions_root,
+            receipts_root: header.receipts_root,
+            withdrawals_root: header.withdrawals_root,
+            logs_bloom: header.logs_bloom,
+        }
+    }
+}
+
+/// In-memory [`ExecutionRpc`] backend. It serves whatever it holds, honest or not.
+#[derive(Debug, Clone, Default)]
+pub struct MockRpc {
+    pub blocks: BTreeMap<BlockNumber, MockBlock>,
+    /// Proof, code, block by hash and raw transaction requests served so far, shared between
+    /// clones
+    requests: Arc<Mutex<Vec<&'static str>>>,
+    /// Methods rejected as unsupported, like many providers do for the non-standard ones
+    pub unsupported: BTreeSet<&'static str>,
+    /// Blocks replaced by [`Self::fork`], still served by hash
+    pub reorged: Vec<MockBlock>,
+    /// Raw transactions are rejected with this error message, as geth does for e.g. a stale nonce
+    pub tx_rejection: Option<&'static str>,
+}
+
+impl MockRpc {
+    pub fn insert_block(&mut self, block: MockBlock) {
+        self.blocks.insert(block.block.header.number, block);
+    }
+
+    /// Fork the chain at block `from`: its headers from then on differ by their extra data.
+    /// Only the headers are forked, their bodies are left as is.
+    pub fn fork(&mut self, from: BlockNumber) {
+        let mut parent_hash = self.blocks[&(from - 1)].block.header.hash;
+        for block in self.blocks.range_mut(from..).map(|(_, block)| block) {
+            self.reorged.push(block.clone());
+            let mut header = ConsensusHeader::try_from(block.block.header.clone()).unwrap();
+            header.parent_hash = parent_hash;
+            header.extra_data = bytes!("666f726b");
+            block.block.header = rpc_header(&header);
+            parent_hash = block.block.header.hash;
+        }
+    }
+
+    /// Add `account` to the state of the latest block, rehashing its header. Its
+    /// transactions and receipts keep the former block hash.
+    pub fn deploy(&mut self, address: Address, account: MockAccount) {
+        self.update_latest(|block, header| {
+            block.state.accounts.insert(address, account);
+            header.state_root = block.state.state_root();
+        });
+    }
+
+    /// Activate Prague at the latest block, its header committing to empty requests.
+    /// Its transactions and receipts keep the former block hash.
+    pub fn activate_prague(&mut self) {
+        self.update_latest(|_, header| header.requests_hash = Some(EMPTY_REQUESTS_HASH));
+    }
+
+    fn update_latest(&mut self, update: impl FnOnce(&mut MockBlock, &mut ConsensusHeader)) {
+        let mut entry = self.blocks.last_entry().unwrap();
+        let block = entry.get_mut();
+        let mut header = ConsensusHeader::try_from(block.block.header.clone()).unwrap();
+        update(block, &mut header);
+        block.block.header = rpc_header(&header);
+    }
+
+    /// Link block `number` to block `parent` instead of its actual parent, rehashing its
+    /// descendants
+    pub fn relink(&mut self, number: BlockNumber, parent: BlockNumber) {
+        let mut parent_hash = self.blocks[&parent].block.header.hash;
+        for block in self.blocks.range_mut(number..).map(|(_, block)| block) {
+            let mut header = ConsensusHeader::try_from(block.block.header.clone()).unwrap();
+            header.parent_hash = parent_hash;
+            block.block.header = rpc_header(&header);
+            parent_hash = block.block.header.hash;
+        }
+    }
+
+    /// The logged requests served since the last call
+    pub fn take_requests(&self) -> Vec<&'static str> {
+        std::mem::take(&mut self.requests.lock().unwrap())
+    }
+
+    /// Fail with the error geth returns for unknown methods, if `method` is unsupported
+    fn check_supported(&self, method: &'static str) -> Result<(), RpcError> {
+        if !self.unsupported.contains(method) {
+            return Ok(());
+        }
+
+        Err(RpcError::Transport {
+            method: method.to_string(),
+            source: TransportError::err_resp(ErrorPayload {
+                code: -32601,
+                message: format!("the method {method} does not exist/is not available").into(),
+                data: None,
+            }),
+        })
+    }
+
+    fn account(
+        &self,
+        address: Address,
+        tag: Option<BlockNumberOrTag>,
+    ) -> Result<Account, RpcError> {
+        let state = &self.mock_block(tag)?.state;
+        let account = state.accounts.get(&address).cloned().unwrap_or_default();
+
+        Ok(account.account())
+    }
+
+    fn mock_block(&self, tag: Option<BlockNumberOrTag>) -> Result<&MockBlock, RpcError> {
+        let tag = tag.unwrap_or(BlockNumberOrTag::Latest);
+        let block = match tag {
+            BlockNumberOrTag::Number(number) => self.blocks.get(&number),
+            BlockNumberOrTag::Earliest => self.blocks.values().next(),
+            _ => self.blocks.values().next_back(),
+        };
+
+        block.ok_or_else(|| RpcError::MissingData(format!("Block not found for {tag}")))
+    }
+}
+
+impl ExecutionRpc for MockRpc {
+    async fn get_proof(
+        &self,
+        address: Address,
+        slots: &[B256],
+        block_number: u64,
+    ) -> Result<EIP1186AccountProofResponse, RpcError> {
+        self.requests.lock().unwrap().push("eth_getProof");
+        let block = self.mock_block(Some(BlockNumberOrTag::Number(block_number)))?;
+
+        Ok(block.state.get_proof(address, slots))
+    }
+
+    async fn get_chain_id(&self) -> Result<u64, RpcError> {
+        Ok(MOCK_CHAIN_ID)
+    }
+
+    async fn send_raw_transaction(&self, tx: &Bytes) -> Result<B256, RpcError> {
+        self.check_supported("eth_sendRawTransaction")?;
+        self.requests.lock().unwrap().push("eth_sendRawTransaction");
+        if let Some(message) = self.tx_rejection {
+            return Err(RpcError::Transport {
+                method: "eth_sendRawTransaction".to_string(),
+                source: TransportError::err_resp(ErrorPayload {
+                    code: -32000,
+                    message: message.to_string().into(),
+                    data: None,
+                }),
+            });
+        }
+        Ok(keccak256(tx))
+    }
+
+    async fn get_block_number(&self, tag: Option<BlockNumberOrTag>) -> Result<u64, RpcError> {
+        match tag {
+            Some(BlockNumberOrTag::Number(number)) => Ok(number),
+            _ => Ok(self.mock_block(tag)?.block.header.number),
+        }
+    }
+
+    async fn detect_capabilities(&self) -> Capabilities {
+        Capabilities {
+            get_account: !self.unsupported.contains("eth_getAccount"),
+            get_proof: !self.unsupported.contains("eth_getProof"),
+            get_block_receipts: !self.unsupported.contains("eth_getBlockReceipts"),
+        }
+    }
+}
+
+impl RpcVerifiableMethods for MockRpc {
+    async fn get_account(
+        &self,
+        address: Address,
+        _: Option<&[B256]>,
+        tag: Option<BlockNumberOrTag>,
+    ) -> Result<Account, RpcError> {
+        self.check_supported("eth_getAccount")?;
+        self.account(address, tag)
+    }
+
+    async fn get_balance(
+        &self,
+        address: Address,
+        tag: Option<BlockNumberOrTag>,
+    ) -> Result<U256, RpcError> {
+        Ok(self.account(address, tag)?.balance)
+    }
+
+    async fn get_nonce(
+        &self,
+        address: Address,
+        tag: Option<BlockNumberOrTag>,
+    ) -> Result<u64, RpcError> {
+        Ok(self.account(address, tag)?.nonce)
+    }
+
+    async fn get_code(
+        &self,
+        address: Address,
+        tag: Option<BlockNumberOrTag>,
+    ) -> Result<Bytes, RpcError> {
+        self.requests.lock().unwrap().push("eth_getCode");
+        Ok(self.mock_block(tag)?.state.code(address))
+    }
+
+    async fn get_storage_at(
+        &self,
+        address: Address,
+        slot: B256,
+        tag: Option<BlockNumberOrTag>,
+    ) -> Result<U256, RpcError> {
+        let state = &self.mock_block(tag)?.state;
+        let value = state
+            .accounts
+            .get(&address)
+            .and_then(|account| account.storage.get(&slot).copied());
+
+        Ok(value.unwrap_or_default())
+    }
+
+    async fn get_transaction_receipt(&self, tx_hash: B256) -> Result<TransactionReceipt, RpcError> {
+        self.blocks
+            .values()
+            .flat_map(|block| &block.receipts)
+            .find(|receipt| receipt.transaction_hash == tx_hash)
+            .cloned()
+            .ok_or_else(|| {
+                RpcError::MissingData(format!("Transaction receipt not found for {tx_hash}"))
+            })
+    }
+
+    async fn get_block_receipts(
+        &self,
+        tag: Option<BlockNumberOrTag>,
+    ) -> Result<Vec<TransactionReceipt>, RpcError> {
+        self.check_supported("eth_getBlockReceipts")?;
+        Ok(self.mock_block(tag)?.receipts.clone())
+    }
+
+    async fn get_block_by_number(
+        &self,
+        tag: Option<BlockNumberOrTag>,
+        full: bool,
+    ) -> Result<Block, RpcError> {
+        let mut block = self.mock_block(tag)?.block.clone();
+        if !full {
+            block.transactions = BlockTransactions::Hashes(block.transactions.hashes().collect());
+        }
+
+        Ok(block)
+    }
+
+    async fn get_block_by_hash(&self, hash: BlockHash, full: bool) -> Result<Block, RpcError> {
+        self.requests.lock().unwrap().push("eth_getBlockByHash");
+        let mut block = self
+            .blocks
+            .values()
+            .chain(&self.reorged)
+            .find(|block| block.block.header.hash == hash)
+            .map(|block| block.block.clone())
+            .ok_or_else(|| RpcError::MissingData(format!("Block not found for {hash}")))?;
+        if !full {
+            block.transactions = BlockTransactions::Hashes(block.transactions.hashes().collect());
+        }
+
+        Ok(block)
+    }
+
+    async fn get_transaction_by_hash(&self, tx_hash: B256) -> Result<Transaction, RpcError> {
+        self.blocks
+            .values()
+            .flat_map(|block| block.block.transactions.txns())
+            .find(|tx| tx.hash == tx_hash)
+            .cloned()
+            .ok_or_else(|| RpcError::MissingData(format!("Transaction not found for {tx_hash}")))
+    }
+
+    async fn get_transaction_by_block_number_and_index(
+        &self,
+        tag: Option<BlockNumberOrTag>,
+        index: u64,
+    ) -> Result<Transaction, RpcError> {
+        let block = &self.mock_block(tag)?.block;
+
+        block
+            .transactions
+            .txns()
+            .nth(index as usize)
+            .cloned()
+            .ok_or_else(|| RpcError::MissingData(format!("Transaction {index} not found")))
+    }
+
+    async fn get_transaction_by_block_hash_and_index(
+        &self,
+        hash: BlockHash,
+        index: u64,
+    ) -> Result<Transaction, RpcError> {
+        let block = self.get_block_by_hash(hash, true).await?;
+
+        block
+            .transactions
+            .into_transactions()
+            .nth(index as usize)
+            .ok_or_else(|| RpcError::MissingData(format!("Transaction {index} not found")))
+    }
+
+    async fn get_logs(&self, filter: &Filter) -> Result<Vec<Log>, RpcError> {
+        let params = FilteredParams::new(Some(filter.clone()));
+
+        Ok(self
+            .blocks
+            .values()
+            .filter(|block| params.filter_block_range(block.block.header.number))
+            .filter(|block| params.filter_block_hash(block.block.header.hash))
+            .flat_map(|block| &block.receipts)
+            .flat_map(|receipt| receipt.inner.logs())
+            .filter(|log| {
+                params.filter_address(&log.address()) && params.filter_topics(log.topics())
+            })
+            .cloned()
+            .collect())
+    }
+
+    async fn call(
+        &self,
+        _: &TransactionRequest,
+        _: Option<BlockNumberOrTag>,
+    ) -> Result<Bytes, RpcError> {
+        Err(RpcError::MissingData(
+            "MockRpc does not execute calls".to_string(),
+        ))
+    }
+
+    async fn estimate_gas(
+        &self,
+        _: &TransactionRequest,
+        _: Option<BlockNumberOrTag>,
+    ) -> Result<u64, RpcError> {
+        Err(RpcError::MissingData(
+            "MockRpc does not execute calls".to_string(),
+        ))
+    }
+
+    async fn create_access_list(
+        &self,
+        _: &TransactionRequest,
+        _: Option<BlockNumberOrTag>,
+    ) -> Result<AccessListResult, RpcError> {
+        Err(RpcError::MissingData(
+            "MockRpc does not execute calls".to_string(),
+        ))
+    }
+}
+
+/// Chain id of the chain built by [`mock_chain`]
+pub const MOCK_CHAIN_ID: u64 = 31337;
+/// Receives the value transfers of [`mock_chain`]
+pub const MOCK_RECIPIENT: Address = address!("deadbeefdeadbeefdeadbeefdeadbeefdeadbeef");
+/// Token-like contract that logs a transfer, bumps a counter in slot 0 and returns it
+pub const MOCK_CONTRACT: Address = address!("00000000000000000000000000000000c0ffee00");
+/// Receives the beacon chain withdrawals of [`mock_chain`]
+pub const MOCK_WITHDRAWAL_ADDRESS: Address = address!("00000000000000000000000000000000000fee00");
+/// `keccak256("Transfer(address,address,uint256)")`
+pub const TRANSFER_TOPIC: B256 =
+    b256!("ddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef");
+
+const GENESIS_TIMESTAMP: u64 = 1_700_000_000;
+const BASE_FEE: u64 = 7_000_000_000;
+
+/// Sender of all transactions in [`mock_chain`]
+pub fn mock_signer() -> PrivateKeySigner {
+    PrivateKeySigner::from_bytes(&B256::with_last_byte(1)).expect("valid private key")
+}
+
+/// Delegates its code to [`MOCK_CONTRACT`] through the EIP-7702 transactions of [`mock_chain`]
+pub fn mock_authority() -> PrivateKeySigner {
+    PrivateKeySigner::from_bytes(&B256::with_last_byte(2)).expect("valid private key")
+}
+
+fn receipt_envelope<T>(tx_type: TxType, receipt: ReceiptWithBloom<T>) -> ReceiptEnvelope<T> {
+    match tx_type {
+        TxType::Legacy => ReceiptEnvelope::Legacy(receipt),
+        TxType::Eip2930 => ReceiptEnvelope::Eip2930(receipt),
+        TxType::Eip1559 => ReceiptEnvelope::Eip1559(receipt),
+        TxType::Eip4844 => ReceiptEnvelope::Eip4844(receipt),
+        TxType::Eip7702 => ReceiptEnvelope::Eip7702(receipt),
+    }
+}
+
+fn rpc_transaction(
+    envelope: &TxEnvelope,
+    from: Address,
+    header: &ConsensusHeader,
+    block_hash: B256,
+    index: u64,
+) -> Transaction {
+    let signature = match envelope {
+        TxEnvelope::Legacy(signed) => signed.signature(),
+        TxEnvelope::Eip2930(signed) => signed.signature(),
+        TxEnvelope::Eip1559(signed) => signed.signature(),
+        TxEnvelope::Eip4844(signed) => signed.signature(),
+        TxEnvelope::Eip7702(signed) => signed.signature(),
+        _ => unreachable!("unknown transaction type {}", envelope.tx_type()),
+    };
+    let legacy = envelope.tx_type() == TxType::Legacy;
+    let base_fee = header.base_fee_per_gas.unwrap_or_default();
+    // dynamic fee transactions report their effective gas price
+    let gas_price = envelope.gas_price().unwrap_or_else(|| {
+        base_fee as u128 + envelope.effective_tip_per_gas(base_fee).unwrap_or_default()
+    });
+
+    Transaction {
+        hash: *envelope.tx_hash(),
+        nonce: envelope.nonce(),
+        block_hash: Some(block_hash),
+        block_number: Some(header.number),
+        transaction_index: Some(index),
+        from,
+        to: envelope.to(),
+        value: envelope.value(),
+        gas_price: Some(gas_price),
+        gas: envelope.gas_limit(),
+        max_fee_per_gas: envelope
+            .max_priority_fee_per_gas()
+            .map(|_| envelope.max_fee_per_gas()),
+        max_priority_fee_per_gas: envelope.max_priority_fee_per_gas(),
+        max_fee_per_blob_gas: envelope.max_fee_per_blob_gas(),
+        input: Bytes::copy_from_slice(envelope.input()),
+        signature: Some(RpcSignature {
+            r: signature.r(),
+            s: signature.s(),
+            v: U256::from(signature.v().to_u64()),
+            // legacy transactions do not carry the y parity field
+            y_parity: (!legacy).then_some(Parity(signature.v().y_parity())),
+        }),
+        chain_id: envelope.chain_id(),
+        blob_versioned_hashes: envelope.blob_versioned_hashes().map(<[B256]>::to_vec),
+        access_list: envelope.access_list().cloned(),
+        transaction_type: Some(envelope.tx_type() as u8),
+        authorization_list: envelope.authorization_list().map(<[_]>::to_vec),
+    }
+}
+
+fn rpc_header(header: &ConsensusHeader) -> Header {
+    Header {
+        hash: header.hash_slow(),
+        parent_hash: header.parent_hash,
+        uncles_hash: header.ommers_hash,
+        miner: header.beneficiary,
+        state_root: header.state_root,
+        transactions_root: header.transactions_root,
+        receipts_root: header.receipts_root,
+        logs_bloom: header.logs_bloom,
+        difficulty: header.difficulty,
+        number: header.number,
+        gas_limit: header.gas_limit,
+        gas_used: header.gas_used,
+        timestamp: header.timestamp,
+        total_difficulty: Some(U256::ZERO),
+        extra_data: header.extra_data.clone(),
+        mix_hash: Some(header.mix_hash),
+        nonce: Some(header.nonce),
+        base_fee_per_gas: header.base_fee_per_gas,
+        withdrawals_root: header.withdrawals_root,
+        blob_gas_used: header.blob_gas_used,
+        excess_blob_gas: header.excess_blob_gas,
+        parent_beacon_block_root: header.parent_beacon_block_root,
+        requests_hash: header.requests_hash,
+    }
+}
+
+/// Header of a post-Prague block, as recorded from geth (`eth_getHeaderByHash`)
+pub fn prague_header() -> Header {
+    serde_json::from_str(include_str!("fixtures/prague_header.json")).unwrap()
+}
+
+/// Headers of mainnet blocks 11117104 (pre-London) and 19449567 (Cancun), as recorded from
+/// nodes (`eth_getBlockByHash`)
+pub fn mainnet_headers() -> Vec<Header> {
+    [
+        include_str!("fixtures/mainnet_pre_london_header.json"),
+        include_str!("fixtures/mainnet_cancun_header.json"),
+    ]
+    .into_iter()
+    .map(|json| serde_json::from_str(json).unwrap())
+    .collect()
+}
+
+/// `eth_getProof` of the Uniswap V3 positions NFT contract, as recorded from a mainnet node.
+/// The header of the block it was taken at is not recorded, so its state root is only the
+/// hash of its first node: it checks the decoding of real trie nodes, not the root.
+pub fn mainnet_account_proof() -> EIP1186AccountProofResponse {
+    serde_json::from_str(include_str!("fixtures/mainnet_account_proof.json")).unwrap()
+}
+
+/// Absent from the state of [`mainnet_account_proof`]: its hashed key follows the recorded
+/// path down to the 7th node, a branch without a child for its next nibble
+pub const MAINNET_ABSENT_ADDRESS: Address = address!("0000000000000000000000000000000001f9a3ff");
+
+/// The `eth_getProof` response for [`MAINNET_ABSENT_ADDRESS`] and slot 0 against the state
+/// root of [`mainnet_account_proof`]: the recorded nodes down to the branch proving the
+/// account absent, and an empty storage proof
+pub fn mainnet_absent_account_proof() -> EIP1186AccountProofResponse {
+    let recorded = mainnet_account_proof();
+
+    EIP1186AccountProofResponse {
+        address: MAINNET_ABSENT_ADDRESS,
+        balance: U256::ZERO,
+        code_hash: KECCAK_EMPTY,
+        nonce: 0,
+        storage_hash: EMPTY_ROOT_HASH,
+        account_proof: recorded.account_proof[..7].to_vec(),
+        storage_proof: vec![EIP1186StorageProof {
+            key: B256::ZERO.into(),
+            value: U256::ZERO,
+            proof: vec![],
+        }],
+    }
+}
+
+/// Transactions of all types as recorded from nodes (`eth_getTransactionByHash`): legacy and
+/// EIP-1559 from mainnet, EIP-4844 and EIP-7702 from devnets
+pub fn recorded_transactions() -> Vec<Transaction> {
+    [
+        include_str!("fixtures/mainnet_legacy_tx.json"),
+        include_str!("fixtures/mainnet_eip1559_tx.json"),
+        include_str!("fixtures/eip4844_tx.json"),
+        include_str!("fixtures/eip7702_tx.json"),
+    ]
+    .into_iter()
+    .map(|json| serde_json::from_str(json).unwrap())
+    .collect()
+}
+
+/// Build a deterministic post-Cancun chain with `len` blocks on top of a genesis block.
+///
+/// Every block has real transactions of all types, receipts, withdrawals, state and header
+/// hashes, so that all the verification paths can be exercised without network access.
+pub fn mock_chain(len: u64) -> MockRpc {
+    let signer = mock_signer();
+    let authority = mock_authority();
+
+    let mut state = MockState::default();
+    state.accounts.insert(
+        signer.address(),
+        MockAccount {
+            balance: U256::from(1_000_000_000_000_000_000_000u128),
+            ..Default::default()
+        },
+    );
+    state.accounts.insert(
+        MOCK_CONTRACT,
+        MockAccount {
+            nonce: 1,
+            // PUSH1 0x01 PUSH1 0x00 SLOAD ADD DUP1 PUSH1 0x00 SSTORE
+            // PUSH1 0x00 MSTORE PUSH1 0x20 PUSH1 0x00 RETURN
+            code: bytes!("6001600054018060005560005260206000f3"),
+            ..Default::default()
+        },
+    );
+
+    let genesis = ConsensusHeader {
+        state_root: state.state_root(),
+        timestamp: GENESIS_TIMESTAMP,
+        gas_limit: 30_000_000,
+        base_fee_per_gas: Some(BASE_FEE),
+        withdrawals_root: Some(EMPTY_ROOT_HASH),
+        blob_gas_used: Some(0),
+        excess_blob_gas: Some(0),
+        parent_beacon_block_root: Some(B256::ZERO),
+        ..Default::default()
+    };
+
+    let mut rpc = MockRpc::default();
+    rpc.insert_block(MockBlock {
+        block: Block {
+            header: rpc_header(&genesis),
+            transactions: BlockTransactions::Full(vec![]),
+            withdrawals: Some(vec![]),
+            ..Default::default()
+        },
+        state: state.clone(),
+        receipts: vec![],
+    });
+
+    let mut parent = genesis;
+    for number in 1..=len {
+        let nonce = state.accounts[&signer.address()].nonce;
+        let value = U256::from(number * 1_000_000_000);
+
+        let mut transfer = TxEip1559 {
+            chain_id: MOCK_CHAIN_ID,
+            nonce,
+            gas_limit: 21_000,
+            max_fee_per_gas: 2 * BASE_FEE as u128,
+            max_priority_fee_per_gas: 1_000_000_000,
+            to: TxKind::Call(MOCK_RECIPIENT),
+            value,
+            ..Default::default()
+        };
+        let signature = signer.sign_transaction_sync(&mut transfer).unwrap();
+        let transfer = TxEnvelope::from(transfer.into_signed(signature));
+
+        let mut call = TxLegacy {
+            chain_id: Some(MOCK_CHAIN_ID),
+            nonce: nonce + 1,
+            gas_price: 2 * BASE_FEE as u128,
+            gas_limit: 100_000,
+            to: TxKind::Call(MOCK_CONTRACT),
+            input: B256::from(value).into(),
+            ..Default::default()
+        };
+        let signature = signer.sign_transaction_sync(&mut call).unwrap();
+        let call = TxEnvelope::from(call.into_signed(signature));
+
+        let mut access_list_tx = TxEip2930 {
+            chain_id: MOCK_CHAIN_ID,
+            nonce: nonce + 2,
+            gas_price: 2 * BASE_FEE as u128,
+            gas_limit: 30_000,
+            to: TxKind::Call(MOCK_RECIPIENT),
+            access_list: AccessList(vec![AccessListItem {
+                address: MOCK_CONTRACT,
+                storage_keys: vec![B256::ZERO],
+            }]),
+            ..Default::default()
+        };
+        let signature = signer.sign_transaction_sync(&mut access_list_tx).unwrap();
+        let access_list_tx = TxEnvelope::from(access_list_tx.into_signed(signature));
+
+        // the versioned hash of a made up blob commitment, blobs are not part of blocks
+        let mut blob_versioned_hash = keccak256(number.to_be_bytes());
+        blob_versioned_hash[0] = 0x01;
+        let mut blob_tx = TxEip4844 {
+            chain_id: MOCK_CHAIN_ID,
+            nonce: nonce + 3,
+            gas_limit: 21_000,
+            max_fee_per_gas: 2 * BASE_FEE as u128,
+            max_priority_fee_per_gas: BASE_FEE as u128,
+            to: MOCK_RECIPIENT,
+            blob_versioned_hashes: vec![blob_versioned_hash],
+            max_fee_per_blob_gas: 1,
+            ..Default::default()
+        };
+        let signature = signer.sign_transaction_sync(&mut blob_tx).unwrap();
+        let blob_tx = TxEnvelope::from(blob_tx.into_signed(signature));
+
+        let authorization = Authorization {
+            chain_id: MOCK_CHAIN_ID,
+            address: MOCK_CONTRACT,
+            nonce: state
+                .accounts
+                .get(&authority.address())
+                .map_or(0, |account| account.nonce),
+        };
+        let signature = authority
+            .sign_hash_sync(&authorization.signature_hash())
+            .unwrap();
+        let mut delegation = TxEip7702 {
+            chain_id: MOCK_CHAIN_ID,
+            nonce: nonce + 4,
+            gas_limit: 50_000,
+            max_fee_per_gas: 2 * BASE_FEE as u128,
+            max_priority_fee_per_gas: BASE_FEE as u128,
+            to: MOCK_RECIPIENT,
+            authorization_list: vec![authorization.into_signed(signature)],
+            ..Default::default()
+        };
+        let signature = signer.sign_transaction_sync(&mut delegation).unwrap();
+        let delegation = TxEnvelope::from(delegation.into_signed(signature));
+
+        let withdrawals = vec![Withdrawal {
+            index: number - 1,
+            validator_index: 42,
+            address: MOCK_WITHDRAWAL_ADDRESS,
+            amount: number * 1_000,
+        }];
+
+        // apply the block to the state
+        // the access list warms up one address and one slot, the authorization creates an
+        // account. Blob gas is charged at the minimum blob base fee.
+        let gas_used = [21_000u64, 43_000, 25_300, 21_000, 46_000];
+        let blob_gas_used = DATA_GAS_PER_BLOB;
+        let fees = U256::from(gas_used.iter().sum::<u64>()) * U256::from(2 * BASE_FEE)
+            + U256::from(blob_gas_used);
+        let sender = state.accounts.get_mut(&signer.address()).unwrap();
+        sender.nonce += 5;
+        sender.balance -= value + fees;
+        let delegated = state.accounts.entry(authority.address()).or_default();
+        delegated.nonce += 1;
+        delegated.code = [&[0xef, 0x01, 0x00], MOCK_CONTRACT.as_slice()]
+            .concat()
+            .into();
+        state.accounts.entry(MOCK_RECIPIENT).or_default().balance += value;
+        state
+            .accounts
+            .get_mut(&MOCK_CONTRACT)
+            .unwrap()
+            .storage
+            .insert(B256::ZERO, U256::from(number));
+        state
+            .accounts
+            .entry(MOCK_WITHDRAWAL_ADDRESS)
+            .or_default()
+            .balance += U256::from(number * 1_000) * U256::from(1_000_000_000);
+
+        let transactions = [transfer, call, access_list_tx, blob_tx, delegation];
+        let logs = [
+            vec![],
+            vec![PrimitiveLog::new_unchecked(
+                MOCK_CONTRACT,
+                vec![
+                    TRANSFER_TOPIC,
+                    signer.address().into_word(),
+                    MOCK_RECIPIENT.into_word(),
+                ],
+                B256::from(value).into(),
+            )],
+            vec![],
+            vec![],
+            vec![],
+        ];
+
+        let mut cumulative_gas_used = 0;
+        let consensus_receipts: Vec<ReceiptEnvelope> = transactions
+            .iter()
+            .zip(logs.iter())
+            .zip(gas_used)
+            .map(|((tx, logs), gas_used)| {
+                cumulative_gas_used += gas_used as u128;
+                let receipt = ReceiptWithBloom::from(Receipt {
+                    status: Eip658Value::Eip658(true),
+                    cumulative_gas_used,
+                    logs: logs.clone(),
+                });
+                receipt_envelope(tx.tx_type(), receipt)
+            })
+            .collect();
+
+        let mut logs_bloom = Bloom::default();
+        for receipt in &consensus_receipts {
+            logs_bloom.accrue_bloom(receipt.logs_bloom());
+        }
+
+        let header = ConsensusHeader {
+            parent_hash: parent.hash_slow(),
+            ommers_hash: EMPTY_OMMER_ROOT_HASH,
+            beneficiary: MOCK_WITHDRAWAL_ADDRESS,
+            state_root: state.state_root(),
+            transactions_root: ordered_trie_root(
+                &transactions
+                    .iter()
+                    .map(|tx| tx.encoded_2718())
+                    .collect::<Vec<_>>(),
+            ),
+            receipts_root: ordered_trie_root(
+                &consensus_receipts
+                    .iter()
+                    .map(|receipt| receipt.encoded_2718())
+                    .collect::<Vec<_>>(),
+            ),
+            withdrawals_root: Some(ordered_trie_root(
+                &withdrawals.iter().map(rlp::encode).collect::<Vec<_>>(),
+            )),
+            logs_bloom,
+            number,
+            gas_limit: 30_000_000,
+            gas_used: cumulative_gas_used as u64,
+            timestamp: GENESIS_TIMESTAMP + number * 12,
+            mix_hash: B256::with_last_byte(number as u8),
+            base_fee_per_gas: Some(BASE_FEE),
+            blob_gas_used: Some(blob_gas_used),
+            excess_blob_gas: Some(0),
+            parent_beacon_block_root: Some(B256::with_last_byte(number as u8)),
+            extra_data: bytes!("6d6f636b"),
+            ..Default::default()
+        };
+        let block_hash = header.hash_slow();
+
+        let mut log_index = 0;
+        let receipts = consensus_receipts
+            .into_iter()
+            .zip(transactions.iter())
+            .zip(gas_used)
+            .enumerate()
+            .map(|(index, ((receipt, tx), gas_used))| {
+                let receipt = receipt.as_receipt_with_bloom().unwrap();
+                let logs = receipt
+                    .receipt
+                    .logs
+                    .iter()
+                    .map(|log| {
+                        log_index += 1;
+                        Log {
+                            inner: log.clone(),
+                            block_hash: Some(block_hash),
+                            block_number: Some(number),
+                            block_timestamp: Some(header.timestamp),
+                            transaction_hash: Some(*tx.tx_hash()),
+                            transaction_index: Some(index as u64),
+                            log_index: Some(log_index - 1),
+                            removed: false,
+                        }
+                    })
+                    .collect();
+                let receipt = ReceiptWithBloom {
+                    receipt: Receipt {
+                        status: receipt.receipt.status,
+                        cumulative_gas_used: receipt.receipt.cumulative_gas_used,
+                        logs,
+                    },
+                    logs_bloom: receipt.logs_bloom,
+                };
+                let inner = receipt_envelope(tx.tx_type(), receipt);
+                let blob_gas_used = tx
+                    .blob_versioned_hashes()
+                    .map(|hashes| hashes.len() as u128 * DATA_GAS_PER_BLOB as u128);
+
+                TransactionReceipt {
+                    inner,
+                    transaction_hash: *tx.tx_hash(),
+                    transaction_index: Some(index as u64),
+                    block_hash: Some(block_hash),
+                    block_number: Some(number),
+                    gas_used: gas_used as u128,
+                    effective_gas_price: 2 * BASE_FEE as u128,
+                    blob_gas_used,
+                    blob_gas_price: blob_gas_used.map(|_| 1),
+                    from: signer.address(),
+                    to: tx.to(),
+                    contract_address: None,
+                    authorization_list: tx.authorization_list().map(<[_]>::to_vec),
+                }
+            })
+            .collect();
+
+        let transactions = transactions
+            .iter()
+            .enumerate()
+            .map(|(index, tx)| {
+                rpc_transaction(tx, signer.address(), &header, block_hash, index as u64)
+            })
+            .collect();
+
+        rpc.insert_block(MockBlock {
+            block: Block {
+                header: rpc_header(&header),
+                transactions: BlockTransactions::Full(transactions),
+                withdrawals: Some(withdrawals),
+                ..Default::default()
+            },
+            state: state.clone(),
+            receipts,
+        });
+
+        parent = header;
+    }
+
+    rpc
+}
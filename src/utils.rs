@@ -45,6 +45,7 @@ pub fn encode_receipt_logs(receipt: &TransactionReceipt) -> Vec<Vec<u8>> {
 /// Compute a trie root of the collection of encoded items.
 /// Ref: https://github.com/alloy-rs/trie/blob/main/src/root.rs.
 pub fn ordered_trie_root(items: &[Vec<u8>]) -> B256 {
+    #[allow(clippy::ptr_arg)]
     fn noop_encoder(item: &Vec<u8>, buffer: &mut Vec<u8>) {
         buffer.extend_from_slice(item);
     }
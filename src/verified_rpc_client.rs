@@ -1,34 +1,89 @@
-use std::collections::HashMap;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::ops::Bound;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::RwLock;
 
-use alloy::consensus::Account;
+use alloy::consensus::constants::{EMPTY_OMMER_ROOT_HASH, KECCAK_EMPTY};
+use alloy::consensus::{Account, Header as ConsensusHeader};
+use alloy::eips::eip2718::Encodable2718;
 use alloy::eips::BlockNumberOrTag;
-use alloy::primitives::{Address, BlockHash, BlockNumber, Bytes, B256, U256};
-use alloy::rpc::types::TransactionReceipt;
-use eyre::{eyre, Ok, Result};
+use alloy::primitives::{keccak256, Address, BlockHash, BlockNumber, Bloom, Bytes, B256, U256};
+use alloy::rlp;
+use alloy::rpc::types::{
+    AccessListResult, Block, BlockTransactions, Filter, FilterBlockOption, FilteredParams, Header,
+    Log, Transaction, TransactionReceipt, TransactionRequest,
+};
+use futures::future::try_join_all;
+use futures::{StreamExt, TryStreamExt};
+use tokio::sync::broadcast;
 
-use crate::common::RpcVerifiableMethods;
+use crate::common::{Capabilities, ExecutionRpc, RpcVerifiableMethods};
+use crate::errors::{RpcError, VerificationError};
+use crate::evm::{execution_output, VerifiedEvm};
 use crate::http_rpc::HttpRpc;
-use crate::proof::{proof_to_account, verify_code_hash, verify_rpc_proof, verify_storage_proof};
+use crate::proof::{
+    proof_to_account, transaction_envelope, verify_account_proof, verify_block_header,
+    verify_code_hash, verify_receipt, verify_rpc_proof, verify_storage_proof, verify_transaction,
+};
 use crate::utils::{encode_receipt, ordered_trie_root};
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub struct TrustedBlock {
     pub number: BlockNumber,
     pub hash: BlockHash,
+    pub parent_hash: BlockHash,
     pub state_root: B256,
+    pub transactions_root: B256,
     pub receipts_root: B256,
+    pub withdrawals_root: Option<B256>,
+    pub logs_bloom: Bloom,
 }
 
-#[derive(Debug, PartialEq, Eq)]
+impl From<&ConsensusHeader> for TrustedBlock {
+    fn from(header: &ConsensusHeader) -> Self {
+        Self {
+            number: header.number,
+            hash: header.hash_slow(),
+            parent_hash: header.parent_hash,
+            state_root: header.state_root,
+            transactions_root: header.transactions_root,
+            receipts_root: header.receipts_root,
+            withdrawals_root: header.withdrawals_root,
+            logs_bloom: header.logs_bloom,
+        }
+    }
+}
+
+/// An account verified against a trusted state root, with the requested storage slots.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifiedAccount {
+    pub address: Address,
+    pub account: Account,
+    pub code: Bytes,
+    pub storage: BTreeMap<B256, U256>,
+}
+
+/// Trusted blocks, shared between the client and whatever feeds it (e.g. the light client).
+#[derive(Debug)]
 pub struct State {
-    /// A map from block number to state root
-    trusted_blocks: HashMap<BlockNumber, TrustedBlock>,
+    /// A map from block number to trusted block
+    trusted_blocks: RwLock<BTreeMap<BlockNumber, TrustedBlock>>,
+    /// Latest trusted block known to be finalized
+    finalized_block: RwLock<Option<TrustedBlock>>,
+    /// Latest trusted block known to be safe
+    safe_block: RwLock<Option<TrustedBlock>>,
+    /// Notifies the new latest trusted blocks
+    heads: broadcast::Sender<TrustedBlock>,
 }
 
 impl Default for State {
     fn default() -> Self {
         Self {
-            trusted_blocks: HashMap::new(),
+            trusted_blocks: Default::default(),
+            finalized_block: Default::default(),
+            safe_block: Default::default(),
+            heads: broadcast::channel(HEADS_CHANNEL_CAPACITY).0,
         }
     }
 }
@@ -38,89 +93,815 @@ impl State {
         Self::default()
     }
 
-    pub fn add_trusted_blocks(&mut self, blocks: &[TrustedBlock]) {
+    pub fn add_trusted_blocks(&self, blocks: &[TrustedBlock]) {
+        let mut trusted_blocks = self.trusted_blocks.write().unwrap();
+        let latest = trusted_blocks.keys().next_back().copied();
         for block in blocks {
-            self.trusted_blocks.insert(block.number, block.clone());
+            trusted_blocks.insert(block.number, *block);
+        }
+
+        let head = trusted_blocks.last_key_value().map(|(_, block)| *block);
+        if let Some(head) = head.filter(|head| latest.is_none_or(|latest| head.number > latest)) {
+            // No receiver just means no one subscribed
+            let _ = self.heads.send(head);
+        }
+    }
+
+    /// Trust `chain`, which descends from the trusted block `ancestor`, in place of the trusted
+    /// blocks after `ancestor` that it reorgs out
+    pub fn reorg_trusted_blocks(&self, ancestor: BlockNumber, chain: &[TrustedBlock]) {
+        let mut trusted_blocks = self.trusted_blocks.write().unwrap();
+        let reorged = trusted_blocks.split_off(&(ancestor + 1));
+        for block in chain {
+            trusted_blocks.insert(block.number, *block);
+        }
+
+        let mut safe_block = self.safe_block.write().unwrap();
+        if safe_block.is_some_and(|safe| safe.number > ancestor) {
+            *safe_block = None;
+        }
+
+        let head = trusted_blocks.last_key_value().map(|(_, block)| *block);
+        if let Some(head) = head.filter(|head| reorged.values().next_back() != Some(head)) {
+            let _ = self.heads.send(head);
+        }
+        if let Some((_, reorged_head)) = reorged.last_key_value() {
+            tracing::warn!(
+                "reorg of {} blocks after block {ancestor}, head {} replaced",
+                reorged.len(),
+                reorged_head.hash
+            );
+        }
+    }
+
+    /// Trust `block` in place of all the trusted blocks after the finalized block, when a trust
+    /// source moved to a chain that doesn't link to them
+    pub fn reanchor_trusted_blocks(&self, block: TrustedBlock) {
+        if let Some(finalized) = self
+            .finalized_block()
+            .filter(|finalized| finalized.number < block.number)
+        {
+            return self.reorg_trusted_blocks(finalized.number, &[block]);
         }
+
+        let reorged = std::mem::take(&mut *self.trusted_blocks.write().unwrap());
+        *self.safe_block.write().unwrap() = None;
+        self.add_trusted_blocks(&[block]);
+        tracing::warn!(
+            "dropped {} trusted blocks, re-anchored to block {} ({})",
+            reorged.len(),
+            block.number,
+            block.hash
+        );
+    }
+
+    /// Receive the new latest trusted blocks, as they get trusted
+    pub fn subscribe_heads(&self) -> broadcast::Receiver<TrustedBlock> {
+        self.heads.subscribe()
+    }
+
+    pub fn get_trusted_block(&self, number: BlockNumber) -> Option<TrustedBlock> {
+        self.trusted_blocks.read().unwrap().get(&number).copied()
+    }
+
+    pub fn latest_trusted_block(&self) -> Option<TrustedBlock> {
+        self.trusted_blocks
+            .read()
+            .unwrap()
+            .last_key_value()
+            .map(|(_, block)| *block)
+    }
+
+    pub fn finalized_block(&self) -> Option<TrustedBlock> {
+        *self.finalized_block.read().unwrap()
     }
 
-    pub fn latest_trusted_block(&self) -> Option<&TrustedBlock> {
-        self.trusted_blocks.values().max_by_key(|b| b.number)
+    /// Trust `block` and mark it as finalized
+    pub fn set_finalized_block(&self, block: TrustedBlock) {
+        self.add_trusted_blocks(&[block]);
+        *self.finalized_block.write().unwrap() = Some(block);
+    }
+
+    /// The safe block, falling back to the finalized block which is safe too
+    pub fn safe_block(&self) -> Option<TrustedBlock> {
+        self.safe_block
+            .read()
+            .unwrap()
+            .or_else(|| self.finalized_block())
+    }
+
+    /// Trust `block` and mark it as safe
+    pub fn set_safe_block(&self, block: TrustedBlock) {
+        self.add_trusted_blocks(&[block]);
+        *self.safe_block.write().unwrap() = Some(block);
+    }
+
+    /// Resolve `tag` to a block number from trusted blocks only, the untrusted RPC
+    /// would otherwise choose which block the responses are verified against.
+    pub fn resolve_block_number(
+        &self,
+        tag: Option<BlockNumberOrTag>,
+    ) -> Result<BlockNumber, VerificationError> {
+        let tag = tag.unwrap_or(BlockNumberOrTag::Latest);
+        let block = match tag {
+            BlockNumberOrTag::Number(number) => return Ok(number),
+            BlockNumberOrTag::Earliest => return Ok(0),
+            BlockNumberOrTag::Latest => self.latest_trusted_block(),
+            BlockNumberOrTag::Safe => self.safe_block(),
+            BlockNumberOrTag::Finalized => self.finalized_block(),
+            BlockNumberOrTag::Pending => None,
+        };
+
+        block
+            .map(|block| block.number)
+            .ok_or(VerificationError::UnresolvedTag(tag))
+    }
+
+    /// The closest trusted block after `number`, whose ancestors lead down to `number`
+    pub fn closest_trusted_descendant(&self, number: BlockNumber) -> Option<TrustedBlock> {
+        self.trusted_blocks
+            .read()
+            .unwrap()
+            .range((Bound::Excluded(number), Bound::Unbounded))
+            .next()
+            .map(|(_, block)| *block)
     }
 }
 
-pub struct VerifiedRpcClient {
+/// Max number of new heads buffered for a lagging subscriber
+pub const HEADS_CHANNEL_CAPACITY: usize = 64;
+
+/// Max number of ancestors fetched to reach a requested block,
+/// bounds the work a single request can trigger.
+pub const MAX_ANCESTOR_SYNC: u64 = 8192;
+
+/// Max number of trusted blocks a new head can reorg out. Finalized blocks are never reorged.
+pub const MAX_REORG_DEPTH: u64 = 64;
+
+/// Max number of blocks a single `eth_getLogs` request can span
+pub const MAX_LOGS_BLOCK_RANGE: u64 = 1024;
+
+/// Max number of blocks whose receipts and transactions `eth_getLogs` fetches at once
+pub const MAX_CONCURRENT_LOGS_BLOCKS: usize = 16;
+
+pub struct VerifiedRpcClient<B = HttpRpc> {
     pub state: State,
-    rpc: HttpRpc,
+    rpc: B,
+    /// Set once the backend rejected `eth_getBlockReceipts`, receipts are then fetched one by one
+    block_receipts_unsupported: AtomicBool,
+    /// The last header served by [`Self::trusted_header`], shared by all `newHeads` subscribers
+    last_header: tokio::sync::Mutex<Option<Header>>,
+    /// Set by [`Self::detect_capabilities`]
+    capabilities: RwLock<Option<Capabilities>>,
+    /// Set by [`Self::with_chain_id`], calls can't be executed without it
+    chain_id: Option<u64>,
 }
 
 impl VerifiedRpcClient {
-    pub fn new(rpc: &str) -> Result<Self> {
-        Ok(Self {
+    pub fn new(rpc: &str) -> Result<Self, RpcError> {
+        Ok(Self::with_backend(HttpRpc::new(rpc)?))
+    }
+}
+
+impl<B: ExecutionRpc> VerifiedRpcClient<B> {
+    /// Create a client that verifies the data served by any untrusted `backend`
+    pub fn with_backend(backend: B) -> Self {
+        Self {
             state: State::default(),
-            rpc: HttpRpc::new(rpc)?,
+            rpc: backend,
+            block_receipts_unsupported: AtomicBool::new(false),
+            last_header: tokio::sync::Mutex::default(),
+            capabilities: RwLock::default(),
+            chain_id: None,
+        }
+    }
+
+    /// Execute calls on the chain `chain_id`, which must come from the configuration
+    /// rather than from the untrusted backend
+    pub fn with_chain_id(mut self, chain_id: u64) -> Self {
+        self.chain_id = Some(chain_id);
+        self
+    }
+
+    /// The configured chain id
+    pub fn chain_id(&self) -> Result<u64, RpcError> {
+        self.chain_id.ok_or(RpcError::UnknownChainId)
+    }
+
+    /// Trust the block with the given `hash`.
+    /// Its header is fetched from the untrusted backend and verified to hash to `hash`.
+    pub async fn trust_block_hash(&self, hash: BlockHash) -> Result<TrustedBlock, RpcError> {
+        let block = self.rpc.get_block_by_hash(hash, false).await?;
+        let header = verify_block_header(&block.header, &hash)?;
+
+        let trusted_block = TrustedBlock::from(&header);
+        self.state.add_trusted_blocks(&[trusted_block]);
+
+        Ok(trusted_block)
+    }
+
+    /// The trusted block `number`.
+    /// If it is not trusted yet, its ancestors are fetched from the closest trusted
+    /// descendant and each header is verified against the `parent_hash` of its child.
+    pub async fn trusted_block(&self, number: BlockNumber) -> Result<TrustedBlock, RpcError> {
+        if let Some(trusted_block) = self.state.get_trusted_block(number) {
+            return Ok(trusted_block);
+        }
+
+        let mut child = self
+            .state
+            .closest_trusted_descendant(number)
+            .filter(|block| block.number - number <= MAX_ANCESTOR_SYNC)
+            .ok_or(VerificationError::UntrustedBlock(number))?;
+
+        while child.number > number {
+            child = self.verified_parent(&child).await?;
+            self.state.add_trusted_blocks(&[child]);
+        }
+
+        Ok(child)
+    }
+
+    /// The parent of the trusted block `child`, fetched by its `parent_hash`.
+    /// Its header is verified to hash to it and to be numbered right before `child`.
+    async fn verified_parent(&self, child: &TrustedBlock) -> Result<TrustedBlock, RpcError> {
+        let block = self.rpc.get_block_by_hash(child.parent_hash, false).await?;
+        let header = verify_block_header(&block.header, &child.parent_hash)?;
+        if header.number + 1 != child.number {
+            return Err(VerificationError::InvalidHeader {
+                hash: child.parent_hash,
+                reason: format!(
+                    "block {} cannot be the parent of block {}",
+                    header.number, child.number
+                ),
+            }
+            .into());
+        }
+
+        Ok(TrustedBlock::from(&header))
+    }
+
+    /// The header of the trusted block `number`, verified against its hash without
+    /// fetching its transactions. The last one is kept, as every subscriber to new heads
+    /// asks for the same header.
+    pub async fn trusted_header(&self, number: BlockNumber) -> Result<Header, RpcError> {
+        let trusted_block = self.trusted_block(number).await?;
+
+        // Held while fetching, so that concurrent callers wait for the same header
+        let mut last_header = self.last_header.lock().await;
+        if let Some(header) = last_header
+            .as_ref()
+            .filter(|header| header.hash == trusted_block.hash)
+        {
+            return Ok(header.clone());
+        }
+
+        let block = self
+            .rpc
+            .get_block_by_hash(trusted_block.hash, false)
+            .await?;
+        verify_block_header(&block.header, &trusted_block.hash)?;
+        let header = Header {
+            hash: trusted_block.hash,
+            ..block.header
+        };
+
+        *last_header = Some(header.clone());
+        Ok(header)
+    }
+
+    /// Trust the block `head`, at or above the latest trusted block, if it descends from
+    /// a trusted block. Its ancestors are fetched down to a trusted block and verified through
+    /// their parent hash links, only then are they all trusted. The trusted blocks they reorg
+    /// out are replaced, down to [`MAX_REORG_DEPTH`] blocks deep and never finalized ones.
+    pub async fn extend_trusted_chain(&self, head: BlockHash) -> Result<TrustedBlock, RpcError> {
+        let latest = self
+            .state
+            .latest_trusted_block()
+            .ok_or(VerificationError::UnresolvedTag(BlockNumberOrTag::Latest))?;
+
+        let block = self.rpc.get_block_by_hash(head, false).await?;
+        let head = TrustedBlock::from(&verify_block_header(&block.header, &head)?);
+        let unlinked = VerificationError::UnlinkedBlock {
+            number: head.number,
+            hash: head.hash,
+        };
+        if head.number < latest.number || head.number - latest.number > MAX_ANCESTOR_SYNC {
+            return Err(unlinked.into());
+        }
+
+        // The lowest block the new chain may replace
+        let floor = self
+            .state
+            .finalized_block()
+            .map_or(0, |finalized| finalized.number + 1)
+            .max(latest.number.saturating_sub(MAX_REORG_DEPTH - 1));
+
+        let chain = self.link_to_trusted_chain(head, floor).await?;
+        let ancestor = chain.last().unwrap().number - 1;
+        self.state.reorg_trusted_blocks(ancestor, &chain);
+
+        Ok(head)
+    }
+
+    /// Trust the finalized block `hash` of a trust source. Unless it is already trusted, the
+    /// trusted blocks after the previous finalized block are dropped, as they may descend from
+    /// a head that did not get finalized.
+    pub async fn trust_finalized_block(&self, hash: BlockHash) -> Result<TrustedBlock, RpcError> {
+        let block = self.rpc.get_block_by_hash(hash, false).await?;
+        let finalized = TrustedBlock::from(&verify_block_header(&block.header, &hash)?);
+
+        if self.state.get_trusted_block(finalized.number) != Some(finalized) {
+            self.state.reanchor_trusted_blocks(finalized);
+        }
+        self.state.set_finalized_block(finalized);
+
+        Ok(finalized)
+    }
+
+    /// Trust the block `head` of a trust source, with its ancestors down to the finalized block.
+    /// Unlike [`Self::extend_trusted_chain`], `head` may be below the latest trusted block, and
+    /// every trusted block after the finalized one that is not an ancestor of `head` is dropped.
+    pub async fn trust_head(&self, head: BlockHash) -> Result<TrustedBlock, RpcError> {
+        let finalized = self
+            .state
+            .finalized_block()
+            .ok_or(VerificationError::UnresolvedTag(
+                BlockNumberOrTag::Finalized,
+            ))?;
+        if head == finalized.hash {
+            if self.state.latest_trusted_block() != Some(finalized) {
+                self.state.reorg_trusted_blocks(finalized.number, &[]);
+            }
+            return Ok(finalized);
+        }
+
+        let block = self.rpc.get_block_by_hash(head, false).await?;
+        let head = TrustedBlock::from(&verify_block_header(&block.header, &head)?);
+        if head.number <= finalized.number || head.number - finalized.number > MAX_ANCESTOR_SYNC {
+            return Err(VerificationError::UnlinkedBlock {
+                number: head.number,
+                hash: head.hash,
+            }
+            .into());
+        }
+
+        let chain = self
+            .link_to_trusted_chain(head, finalized.number + 1)
+            .await?;
+        let ancestor = chain.last().unwrap().number - 1;
+        self.state.reorg_trusted_blocks(ancestor, &chain);
+
+        Ok(head)
+    }
+
+    /// `head` and its ancestors, fetched down to the first one whose parent is trusted.
+    /// Trusted blocks from `floor` on may be replaced, lower ones must be the parent.
+    async fn link_to_trusted_chain(
+        &self,
+        head: TrustedBlock,
+        floor: BlockNumber,
+    ) -> Result<Vec<TrustedBlock>, RpcError> {
+        let unlinked = VerificationError::UnlinkedBlock {
+            number: head.number,
+            hash: head.hash,
+        };
+
+        let mut chain = vec![head];
+        loop {
+            let oldest = *chain.last().unwrap();
+            if oldest.number == 0 {
+                return Err(unlinked.into());
+            }
+            match self.state.get_trusted_block(oldest.number - 1) {
+                Some(parent) if parent.hash == oldest.parent_hash => return Ok(chain),
+                // The parent is not trusted, or reorged out if it can be
+                _ if oldest.number > floor => {}
+                _ => return Err(unlinked.into()),
+            }
+
+            chain.push(self.verified_parent(&oldest).await?);
+        }
+    }
+
+    /// Verified accounts with the values of their requested storage slots, in request order.
+    /// The proofs and code of all accounts are fetched concurrently, then verified
+    /// against the state root of the same trusted block.
+    pub async fn get_accounts_with_storage(
+        &self,
+        accounts: &[(Address, Vec<B256>)],
+        tag: Option<BlockNumberOrTag>,
+    ) -> Result<Vec<VerifiedAccount>, RpcError> {
+        // Get block number from the tag
+        let block_number = self.state.resolve_block_number(tag)?;
+
+        // Ensure we have this block in our trusted blocks
+        let trusted_block = self.trusted_block(block_number).await?;
+
+        try_join_all(accounts.iter().map(|(address, slots)| {
+            self.verified_account(*address, slots, block_number, &trusted_block.state_root)
+        }))
+        .await
+    }
+
+    /// Verified values of the storage `slots` of `address`, in request order, from a single
+    /// proof. Unlike [`Self::get_accounts_with_storage`], the code of the account isn't fetched.
+    pub async fn get_storage(
+        &self,
+        address: Address,
+        slots: &[B256],
+        tag: Option<BlockNumberOrTag>,
+    ) -> Result<Vec<U256>, RpcError> {
+        // Get block number from the tag
+        let block_number = self.state.resolve_block_number(tag)?;
+
+        // Ensure we have this block in our trusted blocks
+        let trusted_block = self.trusted_block(block_number).await?;
+
+        // Get account proof from the RPC
+        let proof = self.rpc.get_proof(address, slots, block_number).await?;
+
+        // MOST IMPORTANT!!
+        // Verify the storage root against the trusted state root first,
+        // otherwise the provider could forge the whole storage trie
+        verify_account_proof(&proof, &trusted_block.state_root)?;
+        verify_storage_proof(&proof)?;
+
+        slots
+            .iter()
+            .map(|slot| {
+                proof
+                    .storage_proof
+                    .iter()
+                    .find(|storage_slot| storage_slot.key.0 == *slot)
+                    .map(|storage_slot| storage_slot.value)
+                    .ok_or_else(|| RpcError::MissingData(format!("Slot {slot} not found in proof")))
+            })
+            .collect()
+    }
+
+    async fn verified_account(
+        &self,
+        address: Address,
+        slots: &[B256],
+        block_number: BlockNumber,
+        state_root: &B256,
+    ) -> Result<VerifiedAccount, RpcError> {
+        let tag = Some(BlockNumberOrTag::Number(block_number));
+
+        // Get account proof from the RPC
+        let proof = self.rpc.get_proof(address, slots, block_number).await?;
+        // Get account code from the RPC, unless the proof shows there is none
+        let code = if proof_to_account(&proof).code_hash == KECCAK_EMPTY {
+            Bytes::new()
+        } else {
+            self.rpc.get_code(address, tag).await?
+        };
+
+        // MOST IMPORTANT!!
+        // Verify the proof fetched from RPC against the trusted state root
+        verify_rpc_proof(&proof, &code, state_root)?;
+
+        let storage = slots
+            .iter()
+            .map(|slot| {
+                proof
+                    .storage_proof
+                    .iter()
+                    .find(|storage_slot| storage_slot.key.0 == *slot)
+                    .map(|storage_slot| (*slot, storage_slot.value))
+                    .ok_or_else(|| RpcError::MissingData(format!("Slot {slot} not found in proof")))
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(VerifiedAccount {
+            address,
+            account: proof_to_account(&proof),
+            code,
+            storage,
         })
     }
+
+    /// Verify the block, fetched with full transactions, against its trusted `hash`:
+    /// its header, its transactions and its withdrawals.
+    fn verify_block(
+        &self,
+        mut block: Block,
+        hash: BlockHash,
+        full: bool,
+    ) -> Result<Block, RpcError> {
+        let header = verify_block_header(&block.header, &hash)?;
+        let block_number = header.number;
+        block.header.hash = hash;
+
+        let BlockTransactions::Full(transactions) = &block.transactions else {
+            return Err(RpcError::MissingData(format!(
+                "Transactions not found in block {block_number}"
+            )));
+        };
+
+        // MOST IMPORTANT!!
+        // Verify the transactions root, and the fields derived from the transactions (e.g.
+        // their senders) only if they are returned
+        let transactions_encoded = Self::verify_transactions(transactions, &header, &hash, full)?;
+
+        // Verify the withdrawals root, blocks before Shanghai have no withdrawals
+        match header.withdrawals_root {
+            Some(withdrawals_root) => {
+                let withdrawals_encoded: Vec<Vec<u8>> = block
+                    .withdrawals
+                    .iter()
+                    .flatten()
+                    .map(rlp::encode)
+                    .collect();
+                let computed_withdrawals_root = ordered_trie_root(&withdrawals_encoded);
+                if computed_withdrawals_root != withdrawals_root {
+                    return Err(VerificationError::WithdrawalsRootMismatch {
+                        block_number,
+                        expected: withdrawals_root,
+                        got: computed_withdrawals_root,
+                    }
+                    .into());
+                }
+            }
+            None => block.withdrawals = None,
+        }
+
+        // Only the uncle hashes are served, not their headers, so only the absence of
+        // uncles can be checked (as for every block since the merge)
+        if header.ommers_hash == EMPTY_OMMER_ROOT_HASH && !block.uncles.is_empty() {
+            return Err(VerificationError::OmmersHashMismatch { block_number }.into());
+        }
+
+        if !full {
+            let tx_hashes = transactions_encoded.iter().map(keccak256).collect();
+            block.transactions = BlockTransactions::Hashes(tx_hashes);
+        }
+
+        Ok(block)
+    }
+
+    /// The EIP-2718 encodings of the `transactions` of the block `hash`, checked against its
+    /// transactions root. Their senders are only recovered, and the fields the RPC derives
+    /// checked, with `derived_fields`.
+    fn verify_transactions(
+        transactions: &[Transaction],
+        header: &ConsensusHeader,
+        hash: &BlockHash,
+        derived_fields: bool,
+    ) -> Result<Vec<Vec<u8>>, VerificationError> {
+        let transactions_encoded = transactions
+            .iter()
+            .enumerate()
+            .map(|(index, tx)| {
+                if derived_fields {
+                    verify_transaction(tx, hash, header.number, index)
+                } else {
+                    Ok(transaction_envelope(tx, header.number)?.encoded_2718())
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let computed_transactions_root = ordered_trie_root(&transactions_encoded);
+        if computed_transactions_root != header.transactions_root {
+            return Err(VerificationError::TransactionsRootMismatch {
+                block_number: header.number,
+                expected: header.transactions_root,
+                got: computed_transactions_root,
+            });
+        }
+
+        Ok(transactions_encoded)
+    }
+
+    /// The header and transaction hashes of a trusted block. The transactions are checked
+    /// against its transactions root from their encodings alone: as in [`Self::verify_block`]
+    /// without full transactions, their senders are not recovered.
+    async fn transaction_hashes(
+        &self,
+        trusted_block: &TrustedBlock,
+    ) -> Result<(ConsensusHeader, Vec<B256>), RpcError> {
+        let block = self.rpc.get_block_by_hash(trusted_block.hash, true).await?;
+        let header = verify_block_header(&block.header, &trusted_block.hash)?;
+
+        let BlockTransactions::Full(transactions) = &block.transactions else {
+            return Err(RpcError::MissingData(format!(
+                "Transactions not found in block {}",
+                header.number
+            )));
+        };
+        let transactions_encoded =
+            Self::verify_transactions(transactions, &header, &trusted_block.hash, false)?;
+
+        let tx_hashes = transactions_encoded.iter().map(keccak256).collect();
+        Ok((header, tx_hashes))
+    }
+
+    /// The transaction at `index` of a verified block
+    fn transaction_at(block: Block, index: u64) -> Result<Transaction, RpcError> {
+        let block_number = block.header.number;
+
+        block
+            .transactions
+            .into_transactions()
+            .nth(index as usize)
+            .ok_or_else(|| {
+                RpcError::MissingData(format!(
+                    "Transaction {index} not found in block {block_number}"
+                ))
+            })
+    }
+
+    /// Fetch the receipts of a trusted block, falling back to one `eth_getTransactionReceipt`
+    /// per transaction (sent concurrently) if the backend doesn't support `eth_getBlockReceipts`
+    async fn fetch_block_receipts(
+        &self,
+        trusted_block: &TrustedBlock,
+    ) -> Result<Vec<TransactionReceipt>, RpcError> {
+        if !self.block_receipts_unsupported.load(Ordering::Relaxed) {
+            let tag = Some(BlockNumberOrTag::Number(trusted_block.number));
+            match self.rpc.get_block_receipts(tag).await {
+                Err(e) if e.is_unsupported_method() => {
+                    tracing::info!(
+                        "eth_getBlockReceipts is not supported, fetching receipts one by one: {e}"
+                    );
+                    self.block_receipts_unsupported
+                        .store(true, Ordering::Relaxed);
+                }
+                result => return result,
+            }
+        }
+
+        // Fetched by the verified transaction hashes, so that each receipt can be checked to
+        // be served for its transaction
+        let (_, tx_hashes) = self.transaction_hashes(trusted_block).await?;
+        let receipts = try_join_all(
+            tx_hashes
+                .iter()
+                .map(|tx_hash| self.rpc.get_transaction_receipt(*tx_hash)),
+        )
+        .await?;
+        for (index, (receipt, tx_hash)) in receipts.iter().zip(&tx_hashes).enumerate() {
+            verify_receipt(
+                receipt,
+                *tx_hash,
+                &trusted_block.hash,
+                trusted_block.number,
+                index,
+            )?;
+        }
+
+        Ok(receipts)
+    }
+
+    /// Probe the optional methods of the backend, so that those it lacks are worked around
+    /// from the start rather than on first use
+    pub async fn detect_capabilities(&self) -> Capabilities {
+        let capabilities = self.rpc.detect_capabilities().await;
+        if !capabilities.get_block_receipts {
+            self.block_receipts_unsupported
+                .store(true, Ordering::Relaxed);
+        }
+
+        *self.capabilities.write().unwrap() = Some(capabilities);
+        capabilities
+    }
+
+    /// The capabilities of the backend, once detected
+    pub fn capabilities(&self) -> Option<Capabilities> {
+        *self.capabilities.read().unwrap()
+    }
+
+    /// Forward the signed transaction `tx` to the backend. Whether it is broadcast can't be
+    /// verified, its hash is computed locally rather than taken from the backend.
+    pub async fn send_raw_transaction(&self, tx: &Bytes) -> Result<B256, RpcError> {
+        let hash = keccak256(tx);
+        let served = self.rpc.send_raw_transaction(tx).await?;
+        if served != hash {
+            tracing::warn!("execution RPC returned hash {served} for transaction {hash}");
+        }
+
+        Ok(hash)
+    }
+
+    /// The untrusted backend, responses from it are **not** verified
+    pub fn backend(&self) -> &B {
+        &self.rpc
+    }
+
+    /// Run `request` pinned to each provider of the backend in turn, best first, until one
+    /// serves data that passes verification. The provider whose data failed verification is
+    /// reported, as is one that lacked the data the next one served.
+    pub async fn retry_invalid<T, Fut>(&self, request: impl Fn() -> Fut) -> Result<T, RpcError>
+    where
+        Fut: Future<Output = Result<T, RpcError>> + Send,
+    {
+        let providers = self.rpc.ranked_providers();
+        let mut missing = Vec::new();
+        let mut last_error = None;
+        for (attempt, &provider) in providers.iter().enumerate() {
+            let error = match self.rpc.pinned(provider, request()).await {
+                Err(RpcError::Verification(e)) if e.is_invalid_data() => {
+                    self.rpc.report_invalid(provider);
+                    e.into()
+                }
+                Err(e @ RpcError::MissingData(_)) => {
+                    missing.push(provider);
+                    e
+                }
+                // Already accounted for by the backend
+                Err(e @ (RpcError::Transport { .. } | RpcError::Timeout { .. })) => e,
+                result => {
+                    if result.is_ok() {
+                        for &provider in &missing {
+                            self.rpc.report_missing(provider);
+                        }
+                    }
+                    return result;
+                }
+            };
+
+            if attempt + 1 < providers.len() {
+                tracing::warn!("provider {provider} failed, retrying on another one: {error}");
+            }
+            last_error = Some(error);
+        }
+
+        Err(last_error.expect("a backend has at least one provider"))
+    }
 }
 
-impl RpcVerifiableMethods for VerifiedRpcClient {
+impl<B: ExecutionRpc> RpcVerifiableMethods for VerifiedRpcClient<B> {
     async fn get_account(
         &self,
         address: Address,
         slots: Option<&[B256]>,
         tag: Option<BlockNumberOrTag>,
-    ) -> Result<Account> {
+    ) -> Result<Account, RpcError> {
         let slots = slots.unwrap_or(&[]);
 
         // Get block number from the tag
-        let block_number = self.rpc.get_block_number(tag).await?;
-        let tag = Some(BlockNumberOrTag::Number(block_number));
+        let block_number = self.state.resolve_block_number(tag)?;
 
         // Ensure we have this block in our trusted blocks
-        let trusted_block = self
-            .state
-            .trusted_blocks
-            .get(&block_number)
-            .ok_or_else(|| eyre!("Block {block_number} is not in trusted list"))?;
+        let trusted_block = self.trusted_block(block_number).await?;
 
-        // Get account proof from the RPC
+        // Get account proof from the RPC, the code is not needed
+        // since the account proof covers its code hash
         let proof = self.rpc.get_proof(address, slots, block_number).await?;
-        // Get account code from the RPC
-        let code = self.rpc.get_code(address, tag).await?;
 
         // MOST IMPORTANT!!
         // Verify the proof fetched from RPC against the trusted state root
-        verify_rpc_proof(&proof, &code, &trusted_block.state_root)?;
+        verify_account_proof(&proof, &trusted_block.state_root)?;
+        verify_storage_proof(&proof)?;
 
         let account = proof_to_account(&proof);
 
         Ok(account)
     }
 
-    async fn get_balance(&self, address: Address, tag: Option<BlockNumberOrTag>) -> Result<U256> {
+    async fn get_balance(
+        &self,
+        address: Address,
+        tag: Option<BlockNumberOrTag>,
+    ) -> Result<U256, RpcError> {
         let account = self.get_account(address, None, tag).await?;
 
         Ok(account.balance)
     }
 
-    async fn get_nonce(&self, address: Address, tag: Option<BlockNumberOrTag>) -> Result<u64> {
+    async fn get_nonce(
+        &self,
+        address: Address,
+        tag: Option<BlockNumberOrTag>,
+    ) -> Result<u64, RpcError> {
         let account = self.get_account(address, None, tag).await?;
 
         Ok(account.nonce)
     }
 
-    async fn get_code(&self, address: Address, tag: Option<BlockNumberOrTag>) -> Result<Bytes> {
+    async fn get_code(
+        &self,
+        address: Address,
+        tag: Option<BlockNumberOrTag>,
+    ) -> Result<Bytes, RpcError> {
         // Get block number from the tag
-        let block_number = self.rpc.get_block_number(tag).await?;
+        let block_number = self.state.resolve_block_number(tag)?;
         let tag = Some(BlockNumberOrTag::Number(block_number));
 
-        // Get account code from the RPC
-        let code = self.rpc.get_code(address, tag).await?;
-        // Get account proof from the RPC
-        let proof = self.rpc.get_proof(address, &[], block_number).await?;
+        // Ensure we have this block in our trusted blocks
+        let trusted_block = self.trusted_block(block_number).await?;
+
+        // Get account code and proof from the RPC, concurrently
+        let (code, proof) = tokio::try_join!(
+            self.rpc.get_code(address, tag),
+            self.rpc.get_proof(address, &[], block_number)
+        )?;
 
         // MOST IMPORTANT!!
-        // Verify the proof fetched from RPC
+        // Verify the code hash against the trusted state root first,
+        // then the code against the verified code hash
+        verify_account_proof(&proof, &trusted_block.state_root)?;
         verify_code_hash(&proof, &code)?;
 
         Ok(code)
@@ -131,49 +912,46 @@ impl RpcVerifiableMethods for VerifiedRpcClient {
         address: Address,
         slot: B256,
         tag: Option<BlockNumberOrTag>,
-    ) -> Result<U256> {
-        // Get block number from the tag
-        let block_number = self.rpc.get_block_number(tag).await?;
-
-        // Get account proof from the RPC
-        let proof = self.rpc.get_proof(address, &[slot], block_number).await?;
-
-        // MOST IMPORTANT!!
-        // Verify the proof fetched from RPC
-        verify_storage_proof(&proof)?;
-
-        proof
-            .storage_proof
-            .iter()
-            .find(|storage_slot| storage_slot.key.0 == slot)
-            .map_or_else(
-                || Err(eyre!("Slot not found")),
-                |storage_slot| Ok(storage_slot.value),
-            )
+    ) -> Result<U256, RpcError> {
+        let values = self.get_storage(address, &[slot], tag).await?;
+        Ok(values[0])
     }
 
-    async fn get_transaction_receipt(&self, tx_hash: B256) -> Result<TransactionReceipt> {
+    async fn get_transaction_receipt(&self, tx_hash: B256) -> Result<TransactionReceipt, RpcError> {
         let receipt = self.rpc.get_transaction_receipt(tx_hash).await?;
 
-        let block_num = receipt
-            .block_number
-            .ok_or_else(|| eyre!("Block number not found in receipt for tx hash {tx_hash}"))?;
+        let block_num = receipt.block_number.ok_or_else(|| {
+            RpcError::MissingData(format!(
+                "Block number not found in receipt for tx hash {tx_hash}"
+            ))
+        })?;
         let tag = Some(BlockNumberOrTag::Number(block_num));
+        let trusted_block = self.trusted_block(block_num).await?;
 
-        let receipts = self.get_block_receipts(tag).await?;
+        let (receipts, (_, tx_hashes)) = tokio::try_join!(
+            self.get_block_receipts(tag),
+            self.transaction_hashes(&trusted_block),
+        )?;
 
-        if !receipts.contains(&receipt) {
-            // Note: Some RPC providers return different response in `eth_getTransactionReceipt` vs `eth_getBlockReceipts`
-            // Primarily due to https://github.com/ethereum/execution-apis/issues/295 not finalized
-            // Which means that the basic equality check on struct can be flaky
-            // So as a fallback do equality check on encoded receipts as well
-            if encode_receipt(&receipt)
-                != encode_receipt(&receipts[receipt.transaction_index.unwrap() as usize])
-            {
-                return Err(eyre!(
-                    "Failed to verify receipt for tx hash {tx_hash} in block {block_num}"
-                ));
+        // The receipt must be served for the transaction at its index in the verified block
+        let index = tx_hashes.iter().position(|hash| *hash == tx_hash).ok_or(
+            VerificationError::InvalidReceipt {
+                tx_hash,
+                block_number: block_num,
+            },
+        )?;
+        verify_receipt(&receipt, tx_hash, &trusted_block.hash, block_num, index)?;
+
+        // Note: Some RPC providers return different response in `eth_getTransactionReceipt` vs `eth_getBlockReceipts`
+        // Primarily due to https://github.com/ethereum/execution-apis/issues/295 not finalized
+        // Which means that the basic equality check on struct can be flaky
+        // So the equality check is done on encoded receipts
+        if receipts.get(index).map(encode_receipt) != Some(encode_receipt(&receipt)) {
+            return Err(VerificationError::InvalidReceipt {
+                tx_hash,
+                block_number: block_num,
             }
+            .into());
         }
 
         Ok(receipt)
@@ -182,32 +960,238 @@ impl RpcVerifiableMethods for VerifiedRpcClient {
     async fn get_block_receipts(
         &self,
         tag: Option<BlockNumberOrTag>,
-    ) -> Result<Vec<TransactionReceipt>> {
+    ) -> Result<Vec<TransactionReceipt>, RpcError> {
         // Get block number from the tag
-        let block_number = self.rpc.get_block_number(tag).await?;
+        let block_number = self.state.resolve_block_number(tag)?;
 
         // Ensure we have this block in our trusted blocks
-        let trusted_block = self
-            .state
-            .trusted_blocks
-            .get(&block_number)
-            .ok_or_else(|| eyre!("Block {block_number} is not trusted"))?;
+        let trusted_block = self.trusted_block(block_number).await?;
 
-        let receipts = self.rpc.get_block_receipts(tag).await?;
+        let receipts = self.fetch_block_receipts(&trusted_block).await?;
 
         // MOST IMPORTANT!!
         // Verify the receipts root
-        let receipts_encoded: Vec<Vec<u8>> = receipts.iter().map(|r| encode_receipt(r)).collect();
+        let receipts_encoded: Vec<Vec<u8>> = receipts.iter().map(encode_receipt).collect();
         let computed_receipts_root = ordered_trie_root(receipts_encoded.as_slice());
         if computed_receipts_root != trusted_block.receipts_root {
-            return Err(eyre!(
-                "Receipts root mismatch for block {:?}: expected {:?}, got {:?}",
+            return Err(VerificationError::ReceiptsRootMismatch {
                 block_number,
-                trusted_block.receipts_root,
-                computed_receipts_root
-            ));
+                expected: trusted_block.receipts_root,
+                got: computed_receipts_root,
+            }
+            .into());
         }
 
         Ok(receipts)
     }
+
+    async fn get_block_by_number(
+        &self,
+        tag: Option<BlockNumberOrTag>,
+        full: bool,
+    ) -> Result<Block, RpcError> {
+        // Get block number from the tag
+        let block_number = self.state.resolve_block_number(tag)?;
+
+        // Ensure we have this block in our trusted blocks
+        let trusted_block = self.trusted_block(block_number).await?;
+
+        // Always fetch the full transactions, their hashes alone cannot be verified
+        let block = self.rpc.get_block_by_hash(trusted_block.hash, true).await?;
+
+        self.verify_block(block, trusted_block.hash, full)
+    }
+
+    async fn get_block_by_hash(&self, hash: BlockHash, full: bool) -> Result<Block, RpcError> {
+        // Always fetch the full transactions, their hashes alone cannot be verified
+        let block = self.rpc.get_block_by_hash(hash, true).await?;
+        let block = self.verify_block(block, hash, full)?;
+
+        // Ensure the block is part of our trusted chain
+        let trusted_block = self.trusted_block(block.header.number).await?;
+        if trusted_block.hash != hash {
+            return Err(VerificationError::UnlinkedBlock {
+                number: block.header.number,
+                hash,
+            }
+            .into());
+        }
+
+        Ok(block)
+    }
+
+    async fn get_transaction_by_hash(&self, tx_hash: B256) -> Result<Transaction, RpcError> {
+        let tx = self.rpc.get_transaction_by_hash(tx_hash).await?;
+
+        let (Some(block_number), Some(index)) = (tx.block_number, tx.transaction_index) else {
+            return Err(RpcError::MissingData(format!(
+                "Transaction {tx_hash} is not included in a block"
+            )));
+        };
+        let tag = Some(BlockNumberOrTag::Number(block_number));
+
+        // MOST IMPORTANT!!
+        // Take the transaction from the verified block, at the index it claims
+        let verified_tx = self
+            .get_transaction_by_block_number_and_index(tag, index)
+            .await?;
+        if verified_tx.hash != tx_hash {
+            return Err(VerificationError::InvalidTransaction {
+                tx_hash,
+                block_number,
+            }
+            .into());
+        }
+
+        Ok(verified_tx)
+    }
+
+    async fn get_transaction_by_block_number_and_index(
+        &self,
+        tag: Option<BlockNumberOrTag>,
+        index: u64,
+    ) -> Result<Transaction, RpcError> {
+        // The transactions of a verified block sit at their claimed index
+        let block = self.get_block_by_number(tag, true).await?;
+
+        Self::transaction_at(block, index)
+    }
+
+    async fn get_transaction_by_block_hash_and_index(
+        &self,
+        hash: BlockHash,
+        index: u64,
+    ) -> Result<Transaction, RpcError> {
+        // The transactions of a verified block sit at their claimed index
+        let block = self.get_block_by_hash(hash, true).await?;
+
+        Self::transaction_at(block, index)
+    }
+
+    async fn get_logs(&self, filter: &Filter) -> Result<Vec<Log>, RpcError> {
+        let (from_block, to_block) = match filter.block_option {
+            FilterBlockOption::Range {
+                from_block,
+                to_block,
+            } => (
+                self.state.resolve_block_number(from_block)?,
+                self.state.resolve_block_number(to_block)?,
+            ),
+            FilterBlockOption::AtBlockHash(hash) => {
+                let block = self.rpc.get_block_by_hash(hash, false).await?;
+                let header = verify_block_header(&block.header, &hash)?;
+
+                // Ensure the block is part of our trusted chain
+                if self.trusted_block(header.number).await?.hash != hash {
+                    return Err(VerificationError::UnlinkedBlock {
+                        number: header.number,
+                        hash,
+                    }
+                    .into());
+                }
+                (header.number, header.number)
+            }
+        };
+        if from_block > to_block || to_block - from_block >= MAX_LOGS_BLOCK_RANGE {
+            return Err(RpcError::InvalidParams(format!(
+                "Invalid block range {from_block}..={to_block}"
+            )));
+        }
+
+        let params = FilteredParams::new(Some(filter.clone()));
+        let address_filter = FilteredParams::address_filter(&filter.address);
+        let topics_filter = FilteredParams::topics_filter(&filter.topics);
+
+        let mut candidates = Vec::new();
+        for block_number in from_block..=to_block {
+            let trusted_block = self.trusted_block(block_number).await?;
+
+            // Skip the blocks whose trusted logs bloom rules out any match
+            if FilteredParams::matches_address(trusted_block.logs_bloom, &address_filter)
+                && FilteredParams::matches_topics(trusted_block.logs_bloom, &topics_filter)
+            {
+                candidates.push(trusted_block);
+            }
+        }
+
+        // MOST IMPORTANT!!
+        // Filter the logs of the verified receipts, so that none can be injected or left out.
+        // Their transaction hashes and timestamp aren't covered by the receipts root, they are
+        // taken from the verified block
+        let verified_blocks: Vec<_> = futures::stream::iter(candidates.iter().copied())
+            .map(|trusted_block| async move {
+                tokio::try_join!(
+                    self.get_block_receipts(Some(BlockNumberOrTag::Number(trusted_block.number))),
+                    self.transaction_hashes(&trusted_block),
+                )
+            })
+            .buffered(MAX_CONCURRENT_LOGS_BLOCKS)
+            .try_collect()
+            .await?;
+
+        let mut logs = Vec::new();
+        for (trusted_block, (receipts, (header, tx_hashes))) in
+            candidates.iter().zip(verified_blocks)
+        {
+            let block_number = trusted_block.number;
+            let block_logs = receipts
+                .iter()
+                .enumerate()
+                .flat_map(|(tx_index, receipt)| {
+                    receipt.inner.logs().iter().map(move |log| (tx_index, log))
+                })
+                .enumerate();
+            for (log_index, (tx_index, log)) in block_logs {
+                if params.filter_address(&log.address()) && params.filter_topics(log.topics()) {
+                    // The position of the log is known from the verified receipts
+                    logs.push(Log {
+                        block_hash: Some(trusted_block.hash),
+                        block_number: Some(block_number),
+                        block_timestamp: Some(header.timestamp),
+                        transaction_hash: tx_hashes.get(tx_index).copied(),
+                        transaction_index: Some(tx_index as u64),
+                        log_index: Some(log_index as u64),
+                        removed: false,
+                        inner: log.inner.clone(),
+                    });
+                }
+            }
+        }
+
+        Ok(logs)
+    }
+
+    async fn call(
+        &self,
+        request: &TransactionRequest,
+        tag: Option<BlockNumberOrTag>,
+    ) -> Result<Bytes, RpcError> {
+        // MOST IMPORTANT!!
+        // Execute the call locally, on state verified against the trusted state root
+        let mut evm = VerifiedEvm::new(self, tag).await?;
+        let result = evm.transact(request).await?.result;
+
+        Ok(execution_output(result)?)
+    }
+
+    async fn estimate_gas(
+        &self,
+        request: &TransactionRequest,
+        tag: Option<BlockNumberOrTag>,
+    ) -> Result<u64, RpcError> {
+        // Estimate locally, the provider could make transactions fail or overpay
+        let mut evm = VerifiedEvm::new(self, tag).await?;
+
+        evm.estimate_gas(request).await
+    }
+
+    async fn create_access_list(
+        &self,
+        request: &TransactionRequest,
+        tag: Option<BlockNumberOrTag>,
+    ) -> Result<AccessListResult, RpcError> {
+        let mut evm = VerifiedEvm::new(self, tag).await?;
+
+        evm.create_access_list(request).await
+    }
 }